@@ -1,32 +1,63 @@
 //! IEEE 2030.5 Client Core Functionality
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{anyhow, Context, Result};
 use httpdate::fmt_http_date;
 use hyper::{
-    header::{ACCEPT, ALLOW, CONTENT_LENGTH, CONTENT_TYPE, DATE, LOCATION},
-    http::HeaderValue,
+    body::{Bytes, HttpBody},
+    header::{
+        HeaderName, ACCEPT, ALLOW, CONTENT_LENGTH, CONTENT_TYPE, DATE, ETAG, HOST, IF_MATCH,
+        IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LOCATION, RETRY_AFTER,
+    },
+    http::{HeaderMap, HeaderValue},
     Body, Method, Request, StatusCode, Uri,
 };
+use openssl::x509::X509StoreContextRef;
 use sep2_common::{
     deserialize,
-    packages::{objects::Error, primitives::Uint32},
+    packages::{
+        edev::{EndDevice, EndDeviceList},
+        fsa::FunctionSetAssignmentsList,
+        identification::{Link, ListLink},
+        objects::Error,
+        primitives::{HexBinary160, Uint32},
+        time::Time,
+        types::{MRIDType, SFDIType, VersionType},
+    },
     serialize,
-    traits::SEResource,
+    traits::{SEIdentifiedObject, SEList, SEResource},
 };
 use std::{
-    collections::BinaryHeap,
-    fmt::Display,
+    collections::{BinaryHeap, HashMap, HashSet},
+    fmt::{Debug, Display},
     future::Future,
-    path::Path,
+    io::Write,
+    net::IpAddr,
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
     pin::Pin,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Semaphore};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
 use crate::{
+    paths::PathRules,
+    persist::{PollKind, PollRecord, StateRecorder},
+    profile::{normalize_namespaces, SchemaProfile},
+    query::ListQuery,
+    security::{cert_not_after, lfdi_from_der, not_after_from_der, security_init, sfdi_gen},
     time::{current_time_with_offset, SEPTime},
-    tls::{create_client, create_client_tls_cfg, create_http_client, ClientInner},
+    tls::{
+        add_trusted_roots, create_client, create_client_tls_cfg,
+        create_client_tls_cfg_from_identity, create_client_tls_cfg_without_identity,
+        create_http_client, create_multi_identity_client, set_verify_callback, ChainPinning,
+        ClientIdentity, ClientInner, ConnectionCounters, IdentitySelector, SocketOptions,
+        TlsClientConfig, VerifyCallback,
+    },
 };
 
 #[cfg(feature = "event")]
@@ -35,33 +66,56 @@ use sep2_common::{
     traits::SERespondableResource,
 };
 
-#[cfg(any(feature = "messaging", feature = "der", feature = "pricing"))]
-use sep2_common::packages::primitives::HexBinary160;
-
 #[cfg(feature = "der")]
-use sep2_common::packages::{der::DERControl, response::DERControlResponse};
+use sep2_common::packages::{
+    der::{
+        DERAvailability, DERControl, DERControlList, DERProgram, DERProgramList, DERStatus,
+        DefaultDERControl, DER,
+    },
+    response::DERControlResponse,
+};
 
 #[cfg(feature = "messaging")]
-use sep2_common::packages::{messaging::TextMessage, response::TextResponse};
+use sep2_common::packages::{
+    messaging::{MessagingProgramList, TextMessage},
+    response::TextResponse,
+};
 
 #[cfg(feature = "drlc")]
 use {
     crate::device::SEDevice,
-    sep2_common::packages::{drlc::EndDeviceControl, response::DrResponse},
+    sep2_common::packages::{
+        drlc::{DemandResponseProgramList, EndDeviceControl},
+        response::DrResponse,
+    },
 };
 
 #[cfg(feature = "pricing")]
-use sep2_common::packages::{pricing::TimeTariffInterval, response::PriceResponse};
+use sep2_common::packages::{
+    pricing::{TariffProfileList, TimeTariffInterval},
+    response::PriceResponse,
+};
 
 /// Possible HTTP Responses for a IEE 2030.5 Client to both send & receive.
+#[derive(Debug, Clone)]
 pub enum SEPResponse {
     /// HTTP 201 w/ Location header value, if it exists - 2030.5-2018 - 5.5.2.4
+    ///
+    /// When received by a [`Client`], a relative Location, or an absolute Location on the same server,
+    /// is normalized into a path relative to the server address, such that it can be passed directly to [`Client::get`].
+    /// A Location on a different server is returned verbatim.
     Created(Option<String>),
     /// HTTP 204 - 2030.5-2018 - 5.5.2.5
     NoContent,
     /// HTTP 400 - 2030.5-2018 - 5.5.2.9
+    ///
+    /// Only sent, such as by a [`ClientNotifServer`](crate::pubsub::ClientNotifServer) route. When received by a [`Client`],
+    /// [`ClientError::BadRequest`] is returned instead, as for all request methods.
     BadRequest(Option<Error>),
     /// HTTP 404 - 2030.5-2018 - 5.5.2.11
+    ///
+    /// Only sent, such as by a [`ClientNotifServer`](crate::pubsub::ClientNotifServer) route. When received by a [`Client`],
+    /// [`ClientError::NotFound`] is returned instead, as for all request methods.
     NotFound,
     /// HTTP 405 w/ Allow header value - 2030.5-2018 - 5.5.2.12
     MethodNotAllowed(String),
@@ -135,40 +189,597 @@ impl TryFrom<SEPResponse> for hyper::Response<Body> {
     }
 }
 
+/// Errors returned by the request methods of a [`Client`], such as [`Client::get`], that callers may wish to handle specifically.
+///
+/// Every unsuccessful HTTP response to a GET request is returned as one of these, as are failures of the underlying transport.
+/// Other functions that return an [`anyhow::Error`] may return one beneath it, which can be retrieved using [`ClientError::of`].
+#[derive(Debug)]
+pub enum ClientError {
+    /// The request was cancelled using it's [`CancellationToken`]
+    Cancelled,
+    /// The server responded with 200 OK, but the response body was empty
+    EmptyBody,
+    /// An absolute href referred to a different server than the one the client is connected to
+    CrossOrigin(String),
+    /// The deadline of the request's [`RequestCtx`] passed before the request completed
+    DeadlineExceeded,
+    /// The server responded with a 5xx status code.
+    ///
+    /// `retryable` is true if the status indicates the error is likely temporary, i.e. 502 Bad Gateway, 503 Service Unavailable & 504 Gateway Timeout.
+    ServerError {
+        status: StatusCode,
+        retryable: bool,
+        body: Option<ErrorBody>,
+        retry_after: Option<Duration>,
+    },
+    /// The server responded with 412 Precondition Failed, as the resource was modified since it was retrieved
+    PreconditionFailed {
+        body: Option<ErrorBody>,
+        retry_after: Option<Duration>,
+    },
+    /// The server responded with 404 Not Found
+    NotFound {
+        body: Option<ErrorBody>,
+        retry_after: Option<Duration>,
+    },
+    /// The server responded with 400 Bad Request
+    BadRequest {
+        body: Option<ErrorBody>,
+        retry_after: Option<Duration>,
+    },
+    /// The server responded with a status code that is not otherwise expected
+    Unexpected {
+        status: StatusCode,
+        body: Option<ErrorBody>,
+        retry_after: Option<Duration>,
+    },
+    /// The request could not be sent, or the response could not be received
+    Transport(hyper::Error),
+    /// A [`Transport`] given to [`Client::with_transport`] failed for a reason other than a [`hyper::Error`]
+    CustomTransport(anyhow::Error),
+    /// The request could not be constructed, such as when the path is not a valid URI
+    InvalidRequest(hyper::http::Error),
+    /// The resource could not be serialized, with the reason given
+    Serialize { resource: String, reason: String },
+    /// The response body could not be deserialized as the requested resource, with the reason given
+    Deserialize { resource: String, reason: String },
+    /// The server responded with a redirect, but no Location header
+    MissingLocationHeader,
+    /// The server responded with 405 Method Not Allowed, but no valid Allow header
+    MissingAllowHeader,
+    /// The server redirected the request more times than [`Client::set_max_redirects`] allows, or redirected it in a loop
+    TooManyRedirects,
+    /// Retrieving every item of a list required more requests than [`Client::set_max_list_requests`] allows,
+    /// such as when the server reports inconsistent counts
+    TooManyListRequests,
+    /// A resource was to be sent to a path not permitted by the client's strict [`PathRules`]
+    UnexpectedPath { resource: String, path: String },
+    /// The server responded with 201 Created, but the Location header was not valid ASCII.
+    ///
+    /// Contains the raw bytes of the header. Not returned if [`Client::set_lenient_location`] is enabled.
+    InvalidLocationHeader(Vec<u8>),
+    /// The client, or one of it's clones, was shut down using [`Client::shutdown`]
+    Shutdown,
+}
+
+impl ClientError {
+    /// Retrieve the [`ClientError`] beneath an [`anyhow::Error`], if any.
+    pub fn of(err: &anyhow::Error) -> Option<&ClientError> {
+        err.downcast_ref()
+    }
+
+    /// Return the captured body of the unexpected HTTP response this error was returned for, if any.
+    pub fn body(&self) -> Option<&ErrorBody> {
+        match self {
+            ClientError::ServerError { body, .. }
+            | ClientError::PreconditionFailed { body, .. }
+            | ClientError::NotFound { body, .. }
+            | ClientError::BadRequest { body, .. }
+            | ClientError::Unexpected { body, .. } => body.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Return how long the server asked the client to wait before retrying the request, if at all.
+    ///
+    /// As per the `Retry-After` header of a 429 Too Many Requests or 503 Service Unavailable response,
+    /// or the `maxRetryDuration` of an [`Error`] resource in the response body. If both are present, the longer is used.
+    /// A `Retry-After` HTTP-date is compared against [`current_time_with_offset`], as it is given by the server's clock.
+    ///
+    /// Polls started using [`Client::start_poll`] and it's variants honour it, by not running again until it has elapsed,
+    /// after which they resume at their normal interval.
+    ///
+    /// [`Error`]: sep2_common::packages::objects::Error
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ClientError::ServerError { retry_after, .. }
+            | ClientError::PreconditionFailed { retry_after, .. }
+            | ClientError::NotFound { retry_after, .. }
+            | ClientError::BadRequest { retry_after, .. }
+            | ClientError::Unexpected { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    // Classify an unexpected HTTP status code, alongside the captured response body, and the server's retry hint, if any
+    fn from_status(
+        status: StatusCode,
+        body: Option<ErrorBody>,
+        retry_after: Option<Duration>,
+    ) -> ClientError {
+        if status.is_server_error() {
+            // 500 Internal Server Error & 501 Not Implemented are unlikely to succeed on retry
+            let retryable = matches!(
+                status,
+                StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            );
+            ClientError::ServerError {
+                status,
+                retryable,
+                body,
+                retry_after,
+            }
+        } else {
+            match status {
+                StatusCode::PRECONDITION_FAILED => {
+                    ClientError::PreconditionFailed { body, retry_after }
+                }
+                StatusCode::NOT_FOUND => ClientError::NotFound { body, retry_after },
+                StatusCode::BAD_REQUEST => ClientError::BadRequest { body, retry_after },
+                status => ClientError::Unexpected {
+                    status,
+                    body,
+                    retry_after,
+                },
+            }
+        }
+    }
+}
+
+impl From<hyper::Error> for ClientError {
+    fn from(err: hyper::Error) -> Self {
+        ClientError::Transport(err)
+    }
+}
+
+impl From<hyper::http::Error> for ClientError {
+    fn from(err: hyper::http::Error) -> Self {
+        ClientError::InvalidRequest(err)
+    }
+}
+
+impl From<hyper::http::uri::InvalidUri> for ClientError {
+    fn from(err: hyper::http::uri::InvalidUri) -> Self {
+        ClientError::InvalidRequest(err.into())
+    }
+}
+
+/// The body of an unexpected HTTP response, as captured for error reporting.
+///
+/// Contained in the [`ClientError`] returned for the response, and can be retrieved using [`ClientError::body`].
+/// Bodies longer than [`Client::set_error_body_limit`] are truncated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorBody {
+    pub body: String,
+    /// Whether the body was longer than the limit, and was truncated to it
+    pub truncated: bool,
+}
+
+impl Display for ErrorBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.body)?;
+        if self.truncated {
+            write!(f, "... (truncated)")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ErrorBody {}
+
+impl ErrorBody {
+    /// Deserialize the body as an [`Error`] resource, if it is one, and was not truncated.
+    pub fn error(&self) -> Option<Error> {
+        (!self.truncated)
+            .then(|| deserialize(&self.body).ok())
+            .flatten()
+    }
+}
+
+// Determine the retry hint of an unexpected response, if any. If both hints are present, the longer is used.
+fn retry_after(
+    status: StatusCode,
+    headers: &HeaderMap,
+    body: Option<&ErrorBody>,
+) -> Option<Duration> {
+    let header = matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+    )
+    .then(|| headers.get(RETRY_AFTER))
+    .flatten()
+    .and_then(|value| value.to_str().ok())
+    .and_then(|value| match value.trim().parse::<u64>() {
+        Ok(secs) => Some(Duration::from_secs(secs)),
+        Err(_) => httpdate::parse_http_date(value.trim()).ok().map(|at| {
+            at.duration_since(current_time_with_offset().into())
+                .unwrap_or_default()
+        }),
+    });
+    let body = body
+        .and_then(ErrorBody::error)
+        .and_then(|err| err.max_retry_duration)
+        .map(|secs| Duration::from_secs(secs.0 as u64));
+    header.max(body)
+}
+
+// Capture up to `limit` bytes of the body of an unexpected response
+async fn capture_error_body(body: &mut Body, limit: usize) -> Option<ErrorBody> {
+    let mut buf = Vec::new();
+    let mut truncated = false;
+    while let Some(Ok(chunk)) = body.data().await {
+        let remaining = limit - buf.len();
+        if chunk.len() > remaining {
+            buf.extend_from_slice(&chunk[..remaining]);
+            truncated = true;
+            break;
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    (!buf.is_empty()).then(|| ErrorBody {
+        body: String::from_utf8_lossy(&buf).into_owned(),
+        truncated,
+    })
+}
+
+// Classify an unexpected HTTP response, capturing up to `limit` bytes of it's body
+async fn unexpected_response(res: hyper::Response<Body>, limit: usize) -> ClientError {
+    let (parts, mut body) = res.into_parts();
+    let body = capture_error_body(&mut body, limit).await;
+    let retry = retry_after(parts.status, &parts.headers, body.as_ref());
+    ClientError::from_status(parts.status, body, retry)
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::Cancelled => write!(f, "Request was cancelled"),
+            ClientError::Shutdown => write!(f, "Client has been shut down"),
+            ClientError::EmptyBody => write!(f, "Server responded with an empty body"),
+            ClientError::DeadlineExceeded => write!(f, "Request deadline exceeded"),
+            ClientError::PreconditionFailed { .. } => {
+                write!(f, "Resource was modified since it was retrieved")
+            }
+            ClientError::TooManyRedirects => write!(f, "Too many redirects"),
+            ClientError::TooManyListRequests => write!(f, "Too many requests for pages of a list"),
+            ClientError::NotFound { .. } => write!(f, "Server responded with 404 Not Found"),
+            ClientError::BadRequest { .. } => write!(f, "Server responded with 400 Bad Request"),
+            ClientError::Unexpected { status, .. } => {
+                write!(f, "Unexpected HTTP response from server: {status}")
+            }
+            ClientError::Transport(err) => write!(f, "Request failed: {err}"),
+            ClientError::CustomTransport(err) => write!(f, "Request failed: {err}"),
+            ClientError::InvalidRequest(err) => write!(f, "Failed to construct request: {err}"),
+            ClientError::Serialize { resource, reason } => {
+                write!(f, "Failed to serialize {resource}: {reason}")
+            }
+            ClientError::Deserialize { resource, reason } => {
+                write!(f, "Failed to deserialize {resource}: {reason}")
+            }
+            ClientError::MissingLocationHeader => {
+                write!(
+                    f,
+                    "Server responded with a redirect, but no Location header"
+                )
+            }
+            ClientError::MissingAllowHeader => {
+                write!(
+                    f,
+                    "Server responded with 405 Method Not Allowed, but no Allow header"
+                )
+            }
+            ClientError::ServerError {
+                status, retryable, ..
+            } => write!(
+                f,
+                "Server error: {status}{}",
+                if *retryable { " (retryable)" } else { "" }
+            ),
+            ClientError::CrossOrigin(href) => {
+                write!(
+                    f,
+                    "{href} does not refer to the server the client is connected to"
+                )
+            }
+            ClientError::UnexpectedPath { resource, path } => {
+                write!(f, "{resource} is not expected to be sent to {path}")
+            }
+            ClientError::InvalidLocationHeader(raw) => write!(
+                f,
+                "Server responded with an invalid Location header: {}",
+                String::from_utf8_lossy(raw).escape_debug()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            // The error itself is already displayed
+            ClientError::Transport(err) => err.source(),
+            ClientError::CustomTransport(err) => err.source(),
+            ClientError::InvalidRequest(err) => err.source(),
+            err => err.body().map(|body| body as _),
+        }
+    }
+}
+
+/// The reason a connectivity check performed by [`Client::check_connectivity`] failed.
+///
+/// Returned within an [`anyhow::Error`], and can be retrieved using [`anyhow::Error::downcast_ref`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectivityError {
+    /// The server address could not be resolved.
+    Dns(String),
+    /// A TCP connection to the server could not be established.
+    Tcp(String),
+    /// The TLS handshake failed, such as when the server certificate could not be verified, or the server did not accept the client certificate.
+    Tls(String),
+    /// The server responded with 401 Unauthorized or 403 Forbidden.
+    Unauthorized(StatusCode),
+    /// The server responded with an unexpected HTTP status code.
+    UnexpectedStatus(StatusCode),
+    /// The request failed for any other reason.
+    Other(String),
+}
+
+impl Display for ConnectivityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectivityError::Dns(e) => write!(f, "Failed to resolve server address: {e}"),
+            ConnectivityError::Tcp(e) => write!(f, "Failed to connect to server: {e}"),
+            ConnectivityError::Tls(e) => write!(f, "Failed to perform TLS handshake: {e}"),
+            ConnectivityError::Unauthorized(s) => {
+                write!(f, "Server did not authorize the client: {s}")
+            }
+            ConnectivityError::UnexpectedStatus(s) => {
+                write!(f, "Unexpected HTTP response from server: {s}")
+            }
+            ConnectivityError::Other(e) => write!(f, "Request failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ConnectivityError {}
+
+impl From<ClientError> for ConnectivityError {
+    fn from(err: ClientError) -> Self {
+        match err {
+            ClientError::Transport(err) => err.into(),
+            err => ConnectivityError::Other(format!("{:#}", anyhow::Error::from(err))),
+        }
+    }
+}
+
+impl From<hyper::Error> for ConnectivityError {
+    fn from(err: hyper::Error) -> Self {
+        let connect = err.is_connect();
+        let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&err);
+        let mut dns = false;
+        let mut tls = false;
+        while let Some(e) = source {
+            dns |= e.to_string() == "dns error";
+            tls |= e.is::<openssl::ssl::Error>() || e.is::<openssl::error::ErrorStack>();
+            source = e.source();
+        }
+        // Include the full chain of causes
+        let msg = format!("{:#}", anyhow::Error::from(err));
+        if tls {
+            ConnectivityError::Tls(msg)
+        } else if dns {
+            ConnectivityError::Dns(msg)
+        } else if connect {
+            ConnectivityError::Tcp(msg)
+        } else {
+            ConnectivityError::Other(msg)
+        }
+    }
+}
+
+/// The future returned by [`Transport::request`]
+pub type TransportFuture =
+    Pin<Box<dyn Future<Output = Result<hyper::Response<Body>>> + Send + 'static>>;
+
+/// A means of sending HTTP requests to a server, and receiving their responses.
+///
+/// A [`Client`] uses a `hyper` connection pool by default, but can be constructed with any Transport using [`Client::with_transport`],
+/// such as a test double returning canned responses.
+///
+/// A [`hyper::Error`] returned by the transport is returned by the client as a [`ClientError::Transport`], a [`ClientError`] is returned as is,
+/// and any other error is returned as a [`ClientError::CustomTransport`].
+pub trait Transport: Send + Sync + 'static {
+    /// Send the given request, returning the server's response.
+    fn request(&self, req: Request<Body>) -> TransportFuture;
+}
+
+// Run the given request future to completion, unless the token is cancelled first
+async fn cancellable<T>(
+    token: &CancellationToken,
+    req: impl Future<Output = Result<T, ClientError>>,
+) -> Result<T, ClientError> {
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(ClientError::Cancelled),
+        res = req => res,
+    }
+}
+
+/// Options that apply to a single request, used with the `*_with_ctx` variants of [`Client`] request methods.
+///
+/// A single context can be shared between many requests, such that they all share the same deadline & cancellation.
+#[derive(Debug, Clone, Default)]
+pub struct RequestCtx {
+    /// The time by which the request must complete, otherwise a [`ClientError::DeadlineExceeded`] is returned.
+    /// This includes any time spent retrying.
+    pub deadline: Option<Instant>,
+    /// A token that can be used to cancel the request, in which case a [`ClientError::Cancelled`] is returned.
+    pub cancel: Option<CancellationToken>,
+    /// How many times the request is retried if a connection to the server cannot be established.
+    pub retries: u32,
+}
+
+impl RequestCtx {
+    const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+    // Perform the request, subject to this context
+    async fn run<T, F, Fut>(&self, mut req: F) -> Result<T, ClientError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ClientError>>,
+    {
+        let attempts = async {
+            let mut backoff = Self::RETRY_BACKOFF;
+            let mut attempt = 0;
+            loop {
+                match req().await {
+                    // Requests that never reached the server are safe to retry, regardless of method
+                    Err(e) if attempt < self.retries && is_connect_error(&e) => {
+                        attempt += 1;
+                        log::warn!(
+                            "Client: Failed to connect to server: {e}, retrying in {backoff:?} ({attempt}/{})",
+                            self.retries
+                        );
+                        tokio::time::sleep(backoff).await;
+                        backoff = backoff.saturating_mul(2);
+                    }
+                    res => return res,
+                }
+            }
+        };
+        let attempts = async {
+            match self.deadline {
+                Some(deadline) => tokio::time::timeout_at(deadline.into(), attempts)
+                    .await
+                    .unwrap_or_else(|_| Err(ClientError::DeadlineExceeded)),
+                None => attempts.await,
+            }
+        };
+        match &self.cancel {
+            Some(token) => cancellable(token, attempts).await,
+            None => attempts.await,
+        }
+    }
+}
+
+fn is_connect_error(err: &ClientError) -> bool {
+    matches!(err, ClientError::Transport(e) if e.is_connect())
+}
+
+// Whether the request that failed with the given error is likely to succeed if retried,
+// regardless of whether it reached the server
+fn is_retryable_error(err: &ClientError) -> bool {
+    matches!(
+        err,
+        ClientError::Transport(_)
+            | ClientError::ServerError {
+                retryable: true,
+                ..
+            }
+    )
+}
+
 // async `TryFrom<Response<Body>> for SEPResponse`` implementation
-async fn into_sepresponse(res: hyper::Response<Body>) -> Result<SEPResponse> {
+// `base` is the address of the server that sent the response
+// If `lenient_location` is set, a Location header that is not valid ASCII is converted lossily, rather than returning an error
+async fn into_sepresponse(
+    base: &str,
+    res: hyper::Response<Body>,
+    error_body_limit: usize,
+    lenient_location: bool,
+) -> Result<SEPResponse, ClientError> {
     match res.status() {
         // We leave the checking of the location header up to the client
         StatusCode::CREATED => {
-            let loc = res
-                .headers()
-                .get(LOCATION)
-                .and_then(|h| h.to_str().ok())
-                .map(|r| r.to_string());
+            let loc = match res.headers().get(LOCATION) {
+                Some(h) => match h.to_str() {
+                    Ok(loc) => Some(normalize_location(base, loc)),
+                    Err(_) if lenient_location => {
+                        let loc = String::from_utf8_lossy(h.as_bytes());
+                        log::warn!(
+                            "Client: Received Location header that is not valid ASCII, using {loc}"
+                        );
+                        Some(normalize_location(base, &loc))
+                    }
+                    Err(_) => {
+                        return Err(ClientError::InvalidLocationHeader(h.as_bytes().to_vec()))
+                    }
+                },
+                None => None,
+            };
             Ok(SEPResponse::Created(loc))
         }
         StatusCode::NO_CONTENT => Ok(SEPResponse::NoContent),
-        StatusCode::BAD_REQUEST => Ok(SEPResponse::BadRequest(
-            hyper::body::to_bytes(res.into_body())
-                .await
-                .ok()
-                .and_then(|b| {
-                    let out = String::from_utf8_lossy(&b);
-                    deserialize(&out).ok()
-                }),
-        )),
-        StatusCode::NOT_FOUND => Ok(SEPResponse::NotFound),
         StatusCode::METHOD_NOT_ALLOWED => {
             let loc = res
                 .headers()
                 .get(ALLOW)
                 .and_then(|h| h.to_str().ok())
                 .map(|r| r.to_string())
-                .context("Failed to extract expected ALLOW header from Response")?;
+                .ok_or(ClientError::MissingAllowHeader)?;
             Ok(SEPResponse::MethodNotAllowed(loc))
         }
-        _ => Err(anyhow!("Unexpected HTTP response from server")),
+        _ => Err(unexpected_response(res, error_body_limit).await),
+    }
+}
+
+// Read the body of a 200 OK response to a GET request
+async fn read_body(res: hyper::Response<Body>) -> Result<String, ClientError> {
+    let body = hyper::body::to_bytes(res.into_body()).await?;
+    // Distinguish an empty body from a malformed resource
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Err(ClientError::EmptyBody);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+// Return the local names of all elements in the given XML document, in document order
+fn element_names(xml: &str) -> Vec<&str> {
+    xml.split('<')
+        .skip(1)
+        .filter(|tag| !tag.starts_with(['/', '?', '!']))
+        .filter_map(|tag| {
+            let name = tag
+                .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+                .next()?;
+            // Strip any namespace prefix
+            let name = name.rsplit(':').next()?;
+            (!name.is_empty()).then_some(name)
+        })
+        .collect()
+}
+
+// Determine which elements in the received XML were not retained in the deserialized resource,
+// by comparing it to the resource once serialized again.
+fn ignored_elements(received: &str, reserialized: &str) -> Vec<String> {
+    let retained: std::collections::HashSet<&str> =
+        element_names(reserialized).into_iter().collect();
+    let mut out: Vec<String> = vec![];
+    for name in element_names(received) {
+        if !retained.contains(name) && !out.iter().any(|e| e == name) {
+            out.push(name.to_owned());
+        }
     }
+    out
+}
+
+// An EndDevice is registered once the server has assigned it function sets
+fn is_registered(edev: &EndDevice) -> bool {
+    edev.function_set_assignments_list_link
+        .as_ref()
+        .is_some_and(|link| link.all.is_none_or(|all| all.get() > 0))
 }
 
 /// A trait implemented by types that can be used as a poll callback by [`Client::start_poll`].
@@ -187,8 +798,43 @@ where
     }
 }
 
+/// A trait implemented by types that can be used as a poll callback by [`Client::start_poll_ref`].
+///
+/// Unlike [`PollCallback`], the callback borrows the polled resource, which remains owned by the poll task.
+pub trait PollRefCallback<T: SEResource>: Send + Sync + 'static {
+    fn callback<'a>(&'a self, resource: &'a T) -> impl Future<Output = ()> + Send + 'a;
+}
+
+/// Automatically implemented for all synchronous [`Fn`] with a matching function signature.
+impl<F, T: SEResource> PollRefCallback<T> for F
+where
+    F: Fn(&T) + Send + Sync + 'static,
+{
+    fn callback<'a>(&'a self, resource: &'a T) -> impl Future<Output = ()> + Send + 'a {
+        self(resource);
+        std::future::ready(())
+    }
+}
+
+/// A trait implemented by types that can be used as a change callback by [`Client::start_poll_changes`].
+pub trait ListChangeCallback<T: SEIdentifiedObject>: Send + Sync + 'static {
+    /// Called with the items that are new, or whose version has changed, since the previous poll.
+    ///
+    /// Not called if no items have changed.
+    fn changed(&self, items: Vec<T>) -> impl Future<Output = ()> + Send;
+
+    /// Called with the mRIDs of the items that were present in the previous poll, but are no longer.
+    ///
+    /// Not called if no items were removed.
+    fn removed(&self, mrids: Vec<MRIDType>) -> impl Future<Output = ()> + Send {
+        let _ = mrids;
+        std::future::ready(())
+    }
+}
+
+// Each future returned by the handler resolves to whether that run of the poll succeeded
 type PollHandler =
-    Box<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> + Send + Sync + 'static>;
+    Box<dyn Fn() -> Pin<Box<dyn Future<Output = bool> + Send + 'static>> + Send + Sync + 'static>;
 
 struct PollJob {
     handler: PollHandler,
@@ -196,13 +842,199 @@ struct PollJob {
     // Since poll intervals are duration based,
     // and not real-world timestamp based, we use [`Instant`]
     next: Instant,
+    // A copy of `next` readable by the job's PollHandle
+    next_fire: Arc<std::sync::Mutex<Instant>>,
+    last_run: Option<Instant>,
+    // The number of consecutive failed runs, updated as each run completes
+    failures: Arc<AtomicU32>,
+    // The number of runs currently in progress
+    running: Arc<AtomicUsize>,
+    // If set, the interval is read from this rate, in seconds, before each run, so that it may be changed while the job is queued
+    rate: Option<Arc<AtomicU32>>,
+    // If set, when the server last asked for the job not to run again before, as recorded by the job's handler
+    retry_hint: Option<RetryHint>,
+    // Set once a run of the job panics, after which the job is not run again, unless restarted by the watchdog
+    panicked: Arc<AtomicBool>,
+    // The number of times the job has been restarted by the watchdog
+    restarts: u32,
+}
+
+// When the server asked for a poll not to run again before, shared between a PollJob and it's handler
+type RetryHint = Arc<std::sync::Mutex<Option<Instant>>>;
+
+// Record the retry hint of an error from a run of a poll, if any
+fn record_retry_hint(hint: &RetryHint, err: &ClientError) {
+    if let Some(after) = err.retry_after() {
+        *hint.lock().unwrap() = Some(Instant::now() + after);
+    }
+}
+
+// Decrements the number of runs of a poll in progress when dropped, including if the run panics
+struct RunGuard(Arc<AtomicUsize>);
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 impl PollJob {
-    /// Run the stored handler, and increment the `next` Instant
-    async fn execute(&mut self) {
-        tokio::spawn((self.handler)());
-        self.next = Instant::now() + self.interval;
+    fn new(handler: PollHandler, interval: Duration, next: Instant) -> (Self, PollHandle) {
+        let next_fire = Arc::new(std::sync::Mutex::new(next));
+        let handle = PollHandle {
+            next_fire: Arc::downgrade(&next_fire),
+        };
+        let job = PollJob {
+            handler,
+            interval,
+            next,
+            next_fire,
+            last_run: None,
+            failures: Arc::default(),
+            running: Arc::default(),
+            rate: None,
+            retry_hint: None,
+            panicked: Arc::default(),
+            restarts: 0,
+        };
+        (job, handle)
+    }
+
+    // Create a job that does not run again before the time its handler records in the given hint
+    fn with_retry_hint(
+        handler: PollHandler,
+        interval: Duration,
+        next: Instant,
+        hint: RetryHint,
+    ) -> (Self, PollHandle) {
+        let (mut job, handle) = Self::new(handler, interval, next);
+        job.retry_hint = Some(hint);
+        (job, handle)
+    }
+
+    // If the server has asked for the job not to run before a time that is yet to pass, return it.
+    // Hints that have passed are cleared, such that the normal interval resumes.
+    fn retry_until(&self, now: Instant) -> Option<Instant> {
+        let mut hint = self.retry_hint.as_ref()?.lock().unwrap();
+        match *hint {
+            Some(until) if until > now => Some(until),
+            _ => hint.take().and(None),
+        }
+    }
+
+    // Create a job whose interval is the given rate, in seconds, as it is when each run occurs
+    fn with_rate(handler: PollHandler, rate: Arc<AtomicU32>, next: Instant) -> (Self, PollHandle) {
+        let interval = Duration::from_secs(rate.load(Ordering::Relaxed).max(1) as u64);
+        let (mut job, handle) = Self::new(handler, interval, next);
+        job.rate = Some(rate);
+        (job, handle)
+    }
+
+    /// Run the stored handler, once a permit is available if one is given, and increment the `next` Instant
+    ///
+    /// If `max_running` runs of the job are already in progress, the run is skipped.
+    async fn execute(
+        &mut self,
+        tasks: &TaskTracker,
+        permits: Option<Arc<Semaphore>>,
+        max_running: usize,
+    ) {
+        let now = Instant::now();
+        if let Some(rate) = &self.rate {
+            self.interval = Duration::from_secs(rate.load(Ordering::Relaxed).max(1) as u64);
+        }
+        if self.running.load(Ordering::Relaxed) >= max_running {
+            log::debug!(
+                "Client: Skipping poll, as {max_running} previous run(s) are still in progress"
+            );
+            self.set_next(now + self.interval);
+            return;
+        }
+        let Ok(run) = std::panic::catch_unwind(AssertUnwindSafe(|| (self.handler)())) else {
+            self.panicked.store(true, Ordering::Relaxed);
+            return;
+        };
+        self.running.fetch_add(1, Ordering::Relaxed);
+        let guard = RunGuard(self.running.clone());
+        let failures = self.failures.clone();
+        let panicked = self.panicked.clone();
+        tasks.spawn(async move {
+            let _guard = guard;
+            // The semaphore is never closed
+            let _permit = match permits {
+                Some(permits) => permits.acquire_owned().await.ok(),
+                None => None,
+            };
+            // The run is spawned separately, such that a panicking callback is contained, and can be detected
+            match tokio::spawn(run).await {
+                Ok(true) => failures.store(0, Ordering::Relaxed),
+                Ok(false) => {
+                    failures.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(err) if err.is_panic() => panicked.store(true, Ordering::Relaxed),
+                // The runtime is shutting down
+                Err(_) => (),
+            }
+        });
+        self.last_run = Some(now);
+        self.set_next(now + self.interval);
+    }
+
+    fn set_next(&mut self, next: Instant) {
+        self.next = next;
+        *self.next_fire.lock().unwrap() = next;
+    }
+
+    // If the job has failed consecutively, and backoff is enabled, return when it is next due,
+    // with the interval doubled for each consecutive failure, up to `max`
+    fn backoff_until(&self, max: Option<Duration>) -> Option<Instant> {
+        let (max, last_run) = (max?, self.last_run?);
+        let failures = self.failures.load(Ordering::Relaxed);
+        if failures == 0 {
+            return None;
+        }
+        let factor = 1u32.checked_shl(failures).unwrap_or(u32::MAX);
+        let interval = self.interval.saturating_mul(factor).min(max);
+        Some(last_run + interval.max(self.interval))
+    }
+}
+
+/// How a poll is run when it becomes due while it's previous run is still in progress, such as when it's callback is slow relative to it's interval.
+///
+/// Set using [`Client::set_poll_overlap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollOverlap {
+    /// Skip the run. The poll is next due one interval later.
+    Skip,
+    /// Run concurrently with previous runs, with at most the given number of runs in progress at once.
+    /// Runs that become due while the limit is reached are skipped. A limit of zero is treated as one.
+    Concurrent(usize),
+}
+
+/// A handle to a poll created using [`Client::start_poll`], or a report created using [`Client::start_report`].
+///
+/// Dropping the handle does not cancel the poll.
+#[derive(Clone, Debug)]
+pub struct PollHandle {
+    next_fire: std::sync::Weak<std::sync::Mutex<Instant>>,
+}
+
+impl PollHandle {
+    /// Return when the poll is next due to run, or `None` if it has been cancelled, or stopped after panicking.
+    ///
+    /// This is updated each time the poll runs, including when forced by [`Client::force_polls`],
+    /// and is when the poll will be restarted, while it is awaiting a restart by the watchdog set using [`Client::set_poll_watchdog`].
+    /// As polls are serviced by the client's background task, a poll may run up to one tickrate after this time.
+    pub fn next_fire(&self) -> Option<Instant> {
+        self.next_fire.upgrade().map(|next| *next.lock().unwrap())
+    }
+
+    /// Return the time remaining until the poll is next due to run, or `None` if it has been cancelled, or stopped after panicking.
+    ///
+    /// Returns [`Duration::ZERO`] if the poll is overdue.
+    pub fn time_until_next(&self) -> Option<Duration> {
+        self.next_fire()
+            .map(|next| next.saturating_duration_since(Instant::now()))
     }
 }
 
@@ -228,51 +1060,644 @@ impl Ord for PollJob {
 
 type PollQueue = Arc<Mutex<BinaryHeap<PollJob>>>;
 
-/// Represents an IEEE 2030.5 Client connection to a single server
+// Clamp the requested poll rate up to the given minimum, logging if it was clamped
+fn clamp_poll_rate(requested: Uint32, min: Uint32) -> Uint32 {
+    if requested < min {
+        log::warn!(
+            "Client: Requested poll rate of {} seconds is below the minimum of {} seconds, using the minimum instead.",
+            requested.get(),
+            min.get()
+        );
+        min
+    } else {
+        requested
+    }
+}
+
+/// Implemented by resources that advertise how often the server would like them to be polled, via their `pollRate`.
 ///
-/// Can be cloned cheaply as poll tasks, and the underlying `hyper` connection pool are shared between cloned clients.
-#[derive(Clone)]
-pub struct Client {
-    addr: Arc<String>,
-    inner: ClientInner,
-    polls: PollQueue,
+/// See [`Client::use_advertised_poll_rate`].
+pub trait AdvertisedPollRate: SEResource {
+    /// The advertised poll rate, in seconds, if any.
+    fn poll_rate(&self) -> Option<Uint32>;
 }
 
-impl Client {
-    const DEFAULT_POLLRATE: Uint32 = Uint32(900);
-    const DEFAULT_TICKRATE: Duration = Duration::from_secs(600);
+/// Implemented by resources that advertise how often the server would like the client to POST to it, via their `postRate`.
+///
+/// See [`Client::use_advertised_post_rate`].
+pub trait AdvertisedPostRate: SEResource {
+    /// The advertised post rate, in seconds, if any.
+    fn post_rate(&self) -> Option<Uint32>;
+}
 
-    /// Construct an IEEE 2030.5 Client instance that uses HTTP
-    ///
-    /// **TCP KeepAlive**:
-    ///
-    /// Pass the given value to `SO_KEEPALIVE`.
-    ///
-    /// **Tickrate**:
-    ///
-    /// Set how often the client background task should wakeup to check the polling queue.
-    ///
-    /// Defaults to 10 minutes, if this function is not called.
-    ///
-    /// If [`Client::start_poll`] is never called, this setting has no effect.
-    pub fn new(
-        server_addr: &str,
-        tcp_keepalive: Option<Duration>,
-        tickrate: Option<Duration>,
-    ) -> Result<Self> {
-        let out = Client {
-            addr: server_addr.to_owned().into(),
-            inner: ClientInner::Http(create_http_client(tcp_keepalive)),
-            polls: Arc::new(Mutex::new(BinaryHeap::new())),
-        };
-        tokio::spawn(
-            out.clone()
-                .poll_task(tickrate.unwrap_or(Self::DEFAULT_TICKRATE)),
-        );
-        Ok(out)
-    }
+macro_rules! impl_advertised_rate {
+    ($trait:ident, $field:ident, $($rsrc:ty),+) => {
+        $(
+            impl $trait for $rsrc {
+                fn $field(&self) -> Option<Uint32> {
+                    self.$field
+                }
+            }
+        )+
+    };
+}
+
+impl_advertised_rate!(
+    AdvertisedPollRate,
+    poll_rate,
+    sep2_common::packages::dcap::DeviceCapability,
+    sep2_common::packages::edev::DeviceStatus,
+    sep2_common::packages::edev::EndDeviceList,
+    sep2_common::packages::edev::Registration,
+    sep2_common::packages::edev::SelfDevice,
+    sep2_common::packages::time::Time
+);
+impl_advertised_rate!(AdvertisedPostRate, post_rate, EndDevice);
+
+#[cfg(feature = "der")]
+impl_advertised_rate!(
+    AdvertisedPollRate,
+    poll_rate,
+    sep2_common::packages::der::DERList,
+    sep2_common::packages::der::DERProgramList
+);
+#[cfg(feature = "drlc")]
+impl_advertised_rate!(
+    AdvertisedPollRate,
+    poll_rate,
+    sep2_common::packages::drlc::DemandResponseProgramList,
+    sep2_common::packages::drlc::LoadShedAvailabilityList
+);
+#[cfg(feature = "messaging")]
+impl_advertised_rate!(
+    AdvertisedPollRate,
+    poll_rate,
+    sep2_common::packages::messaging::MessagingProgramList
+);
+#[cfg(feature = "pricing")]
+impl_advertised_rate!(
+    AdvertisedPollRate,
+    poll_rate,
+    sep2_common::packages::pricing::TariffProfileList
+);
+#[cfg(feature = "metering")]
+impl_advertised_rate!(
+    AdvertisedPollRate,
+    poll_rate,
+    sep2_common::packages::metering::UsagePointList
+);
+#[cfg(feature = "metering")]
+impl_advertised_rate!(
+    AdvertisedPostRate,
+    post_rate,
+    sep2_common::packages::metering_mirror::MirrorUsagePoint
+);
+#[cfg(feature = "event")]
+impl_advertised_rate!(
+    AdvertisedPollRate,
+    poll_rate,
+    sep2_common::packages::response::ResponseSetList
+);
+#[cfg(feature = "flow_reservation")]
+impl_advertised_rate!(
+    AdvertisedPollRate,
+    poll_rate,
+    sep2_common::packages::flow_reservation::FlowReservationRequestList,
+    sep2_common::packages::flow_reservation::FlowReservationResponseList
+);
+
+// The size of each chunk sent by `Client::post_streaming`, and how many may be buffered before serialization is paused
+const STREAM_CHUNK_SIZE: usize = 8 * 1024;
+const STREAM_CHUNK_BUFFER: usize = 4;
+
+// Forwards fixed-size chunks of a serialized resource to a request body
+struct ChunkWriter {
+    buf: Vec<u8>,
+    tx: tokio::sync::mpsc::Sender<Bytes>,
+}
+
+impl ChunkWriter {
+    fn send_chunk(&mut self) -> std::io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buf, Vec::with_capacity(STREAM_CHUNK_SIZE));
+        self.tx
+            .blocking_send(chunk.into())
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= STREAM_CHUNK_SIZE {
+            self.send_chunk()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.send_chunk()
+    }
+}
+
+// How a client built by a ClientBuilder secures it's connections
+enum ClientSecurity {
+    Http,
+    PemFiles {
+        cert_path: PathBuf,
+        pk_path: PathBuf,
+        rootca_path: PathBuf,
+    },
+    Identity(ClientIdentity, PathBuf),
+    Identities(Vec<ClientIdentity>, IdentitySelector, PathBuf),
+}
+
+/// A builder for a [`Client`], obtained via [`Client::builder`].
+///
+/// Certificates & keys are not loaded until [`ClientBuilder::build`] is called.
+pub struct ClientBuilder {
+    server_addr: String,
+    security: ClientSecurity,
+    socket: SocketOptions,
+    tickrate: Option<Duration>,
+    poll_rate: Option<Uint32>,
+    verify: Option<VerifyCallback>,
+    roots: Vec<PathBuf>,
+    pinning: Option<ChainPinning>,
+    host: Option<String>,
+}
+
+impl ClientBuilder {
+    fn from_parts(
+        server_addr: &str,
+        tcp_keepalive: Option<Duration>,
+        tickrate: Option<Duration>,
+    ) -> Self {
+        ClientBuilder {
+            server_addr: server_addr.to_owned(),
+            security: ClientSecurity::Http,
+            socket: SocketOptions {
+                keepalive: tcp_keepalive,
+                ..Default::default()
+            },
+            tickrate,
+            poll_rate: None,
+            verify: None,
+            roots: vec![],
+            pinning: None,
+            host: None,
+        }
+    }
+
+    /// Use HTTPS, with the certificate & private key at the given paths, as per [`Client::new_https`].
+    pub fn with_https(
+        mut self,
+        cert_path: impl AsRef<Path>,
+        pk_path: impl AsRef<Path>,
+        rootca_path: impl AsRef<Path>,
+    ) -> Self {
+        self.security = ClientSecurity::PemFiles {
+            cert_path: cert_path.as_ref().to_owned(),
+            pk_path: pk_path.as_ref().to_owned(),
+            rootca_path: rootca_path.as_ref().to_owned(),
+        };
+        self
+    }
+
+    /// Use HTTPS, with an already loaded certificate & private key, as per [`Client::new_https_from_identity`].
+    pub fn with_https_identity(
+        mut self,
+        identity: &ClientIdentity,
+        rootca_path: impl AsRef<Path>,
+    ) -> Self {
+        self.security = ClientSecurity::Identity(identity.clone(), rootca_path.as_ref().to_owned());
+        self
+    }
+
+    /// Use HTTPS, presenting one of many client certificates, as per [`Client::new_https_with_identities`].
+    pub fn with_https_identities(
+        mut self,
+        identities: Vec<ClientIdentity>,
+        selector: IdentitySelector,
+        rootca_path: impl AsRef<Path>,
+    ) -> Self {
+        self.security =
+            ClientSecurity::Identities(identities, selector, rootca_path.as_ref().to_owned());
+        self
+    }
+
+    /// Pass the given value to `SO_KEEPALIVE`.
+    pub fn with_tcp_keepalive(mut self, keepalive: Duration) -> Self {
+        self.socket.keepalive = Some(keepalive);
+        self
+    }
+
+    /// Set the size of the send buffer of each connection's socket, in bytes, via `SO_SNDBUF`.
+    ///
+    /// Larger buffers may improve throughput on high-latency links. Defaults to the operating system default.
+    pub fn with_send_buffer_size(mut self, size: usize) -> Self {
+        self.socket.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Set the size of the receive buffer of each connection's socket, in bytes, via `SO_RCVBUF`.
+    ///
+    /// Larger buffers may improve throughput on high-latency links. Defaults to the operating system default.
+    pub fn with_recv_buffer_size(mut self, size: usize) -> Self {
+        self.socket.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Bind each connection's socket to the given local address before connecting, such that requests originate from it.
+    ///
+    /// On a device with multiple network interfaces, this determines the interface requests are sent from.
+    /// Defaults to an address chosen by the operating system.
+    pub fn with_local_address(mut self, addr: IpAddr) -> Self {
+        self.socket.local_address = Some(addr);
+        self
+    }
+
+    /// Set how often the client background task should wakeup to check the polling queue.
+    ///
+    /// Defaults to 10 minutes, if this function is not called.
+    pub fn with_tickrate(mut self, tickrate: Duration) -> Self {
+        self.tickrate = Some(tickrate);
+        self
+    }
+
+    /// Set the poll rate, in seconds, that [`Client::start_poll`] will use when one is not specified, as per [`Client::set_default_poll_rate`].
+    ///
+    /// Defaults to 900 seconds (15 minutes), if this function is not called.
+    pub fn with_default_poll_rate(mut self, rate: Uint32) -> Self {
+        self.poll_rate = Some(rate);
+        self
+    }
+
+    /// Run the given [`VerifyCallback`] on each certificate presented by the server during the TLS handshake,
+    /// such as to pin a specific server certificate, or to log the chain.
+    ///
+    /// Has no effect if the client does not use HTTPS.
+    pub fn with_verify_callback(
+        mut self,
+        verify: impl Fn(bool, &mut X509StoreContextRef) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.verify = Some(Box::new(verify));
+        self
+    }
+
+    /// Trust the root CA(s) at the given path, in addition to the `rootca_path` the client's HTTPS configuration was given,
+    /// such as when a server's chain may be issued by one of several SERCAs.
+    ///
+    /// As with `rootca_path`, the path may be either a single PEM file, or a directory of PEM files.
+    /// May be called multiple times. Has no effect if the client does not use HTTPS.
+    pub fn with_trusted_root(mut self, root_path: impl AsRef<Path>) -> Self {
+        self.roots.push(root_path.as_ref().to_owned());
+        self
+    }
+
+    /// Pin the certificate chain presented by the server on it's first successful handshake, as per [`ChainPinning`].
+    ///
+    /// Pinning is checked after the [`VerifyCallback`], if any. Has no effect if the client does not use HTTPS.
+    pub fn with_chain_pinning(mut self, pinning: ChainPinning) -> Self {
+        self.pinning = Some(pinning);
+        self
+    }
+
+    /// Send the given value as the `Host` header of every request, rather than the authority of the server address.
+    ///
+    /// This is independent of the address connected to, allowing a client to connect to a virtual-hosted gateway,
+    /// or reverse proxy, by it's IP address. It does not affect the server name used for TLS.
+    pub fn with_host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    // Apply the trusted roots & verification of this builder to the given TLS configuration
+    fn configure_tls(
+        roots: &[PathBuf],
+        verify: Option<VerifyCallback>,
+        pinning: Option<ChainPinning>,
+        cfg: &mut TlsClientConfig,
+    ) -> Result<()> {
+        add_trusted_roots(cfg, roots)?;
+        match (verify, pinning) {
+            (None, None) => (),
+            (Some(verify), None) => set_verify_callback(cfg, verify),
+            (verify, Some(pinning)) => set_verify_callback(
+                cfg,
+                Box::new(move |ok, ctx| {
+                    let ok = verify.as_ref().map_or(ok, |verify| verify(ok, ctx));
+                    pinning.check(ok, ctx)
+                }),
+            ),
+        }
+        Ok(())
+    }
+
+    /// Construct the [`Client`], and start it's background poll task.
+    ///
+    /// Returns an error if the certificates or private key could not be loaded.
+    pub fn build(self) -> Result<Client> {
+        let host = self
+            .host
+            .map(|host| HeaderValue::try_from(host).context("Invalid Host header"))
+            .transpose()?;
+        let counters: Arc<ConnectionCounters> = Arc::default();
+        let (inner, fdi, cert_expiry) = match self.security {
+            ClientSecurity::Http => (
+                ClientInner::Http(create_http_client(self.socket, counters.clone())),
+                None,
+                None,
+            ),
+            ClientSecurity::PemFiles {
+                cert_path,
+                pk_path,
+                rootca_path,
+            } => {
+                let fdi = security_init(&cert_path)?;
+                let expiry = cert_not_after(&cert_path)?;
+                let mut cfg = create_client_tls_cfg(cert_path, pk_path, rootca_path)?;
+                Self::configure_tls(&self.roots, self.verify, self.pinning, &mut cfg)?;
+                (
+                    ClientInner::Https(create_client(cfg, self.socket, counters.clone())),
+                    Some(fdi),
+                    Some(expiry),
+                )
+            }
+            ClientSecurity::Identity(identity, rootca_path) => {
+                let der = identity.cert_der()?;
+                let lfdi = lfdi_from_der(&der);
+                let mut cfg = create_client_tls_cfg_from_identity(&identity, rootca_path)?;
+                Self::configure_tls(&self.roots, self.verify, self.pinning, &mut cfg)?;
+                (
+                    ClientInner::Https(create_client(cfg, self.socket, counters.clone())),
+                    Some((lfdi, sfdi_gen(&lfdi))),
+                    Some(not_after_from_der(&der)?),
+                )
+            }
+            ClientSecurity::Identities(identities, selector, rootca_path) => {
+                // The earliest expiry of all identities
+                let mut expiry = None;
+                for identity in &identities {
+                    let not_after = not_after_from_der(&identity.cert_der()?)?;
+                    expiry = Some(expiry.map_or(not_after, |e: SEPTime| e.min(not_after)));
+                }
+                let mut cfg = create_client_tls_cfg_without_identity(rootca_path)?;
+                Self::configure_tls(&self.roots, self.verify, self.pinning, &mut cfg)?;
+                (
+                    ClientInner::Https(create_multi_identity_client(
+                        cfg,
+                        identities,
+                        selector,
+                        self.socket,
+                        counters.clone(),
+                    )),
+                    None,
+                    expiry,
+                )
+            }
+        };
+        let client = Client::from_inner(
+            &self.server_addr,
+            inner,
+            counters,
+            fdi,
+            cert_expiry,
+            host,
+            self.tickrate,
+        );
+        if let Some(rate) = self.poll_rate {
+            client.set_default_poll_rate(rate);
+        }
+        Ok(client)
+    }
+}
+
+/// Represents an IEEE 2030.5 Client connection to a single server
+///
+/// Can be cloned cheaply as poll tasks, and the underlying `hyper` connection pool are shared between cloned clients.
+#[derive(Clone)]
+pub struct Client {
+    addr: Arc<String>,
+    // Shared between clones, such that the connection pool can be closed by `shutdown`. None once shut down.
+    inner: Arc<std::sync::RwLock<Option<ClientInner>>>,
+    polls: PollQueue,
+    // Poll rate used when one is not specified, in seconds
+    default_poll_rate: Arc<AtomicU32>,
+    // Post rate used when one is not specified, in seconds
+    default_post_rate: Arc<AtomicU32>,
+    // Client-configured lower bound on poll rates, in seconds
+    min_poll_rate: Arc<AtomicU32>,
+    // Maximum number of redirects followed by a single GET request
+    max_redirects: Arc<AtomicU32>,
+    // Maximum number of GET requests made when retrieving every item of a list
+    max_list_requests: Arc<AtomicU32>,
+    // Maximum number of bytes captured from the body of an unexpected response
+    error_body_limit: Arc<AtomicU32>,
+    // Maximum interval polls back off to after consecutive failures, in milliseconds, zero if disabled
+    poll_backoff: Arc<AtomicU64>,
+    // Limits the number of polls running at once, if set
+    poll_permits: Arc<std::sync::RwLock<Option<Arc<Semaphore>>>>,
+    // Maximum number of runs of a single poll in progress at once, zero if unbounded
+    poll_overlap: Arc<AtomicUsize>,
+    // Server-advertised poll rate, in seconds, zero if not yet discovered
+    server_poll_rate: Arc<AtomicU32>,
+    counters: Arc<ConnectionCounters>,
+    // LFDI & SFDI of the certificate presented by this client, if there is only one
+    fdi: Option<(HexBinary160, SFDIType)>,
+    // When the certificate presented by this client expires, or the earliest expiry if there are many
+    cert_expiry: Option<SEPTime>,
+    // Signals the background poll task to stop
+    shutdown: CancellationToken,
+    // The background poll task, and all running poll callbacks
+    tasks: Arc<TaskTracker>,
+    watchdog: Arc<PollWatchdog>,
+    // ETags of resources retrieved using `get_if_changed`, by path
+    etags: Arc<std::sync::Mutex<HashMap<String, HeaderValue>>>,
+    // Profile targeted when serializing outgoing resources, if any
+    profile: Arc<std::sync::RwLock<Option<Arc<SchemaProfile>>>>,
+    // Whether the namespaces of incoming resources are normalized before deserialization
+    lenient_namespaces: Arc<AtomicBool>,
+    // Whether a Location header that is not valid ASCII is converted lossily, rather than returning an error
+    lenient_location: Arc<AtomicBool>,
+    // Polls & Subscriptions to be saved to the StateStore, if any
+    state: Arc<StateRecorder>,
+    // Paths outgoing resources are checked against, if any
+    path_rules: Arc<std::sync::RwLock<Option<Arc<PathRules>>>>,
+    // Value of the Host header sent with every request, if overridden
+    host: Option<HeaderValue>,
+}
+
+// Configuration for restarting polls & the background poll task, should they panic, and the polls awaiting a restart
+#[derive(Default)]
+struct PollWatchdog {
+    max_restarts: AtomicU32,
+    // Milliseconds
+    backoff: AtomicU64,
+    // Polls that panicked, each due to be restarted at it's `next` Instant
+    dead: std::sync::Mutex<Vec<PollJob>>,
+}
+
+impl PollWatchdog {
+    // The wait before restarting something that has been restarted the given number of times, doubling after each restart
+    fn backoff(&self, restarts: u32) -> Duration {
+        Duration::from_millis(
+            self.backoff
+                .load(Ordering::Relaxed)
+                .saturating_mul(1 << restarts.min(16)),
+        )
+    }
+
+    // Take a poll that panicked, to be restarted once it's backoff has elapsed, if it has restarts remaining.
+    // Otherwise, the poll is stopped.
+    fn bury(&self, mut job: PollJob) {
+        let max_restarts = self.max_restarts.load(Ordering::Relaxed);
+        if job.restarts >= max_restarts {
+            log::error!("Client: Poll panicked, and has been stopped.");
+            return;
+        }
+        let backoff = self.backoff(job.restarts);
+        log::warn!(
+            "Client: Poll panicked, restarting in {backoff:?} ({}/{max_restarts})",
+            job.restarts + 1
+        );
+        job.set_next(Instant::now() + backoff);
+        self.dead.lock().unwrap().push(job);
+    }
+
+    // Remove the polls that are due to be restarted
+    fn take_due(&self, now: Instant) -> Vec<PollJob> {
+        let mut dead = self.dead.lock().unwrap();
+        let (due, waiting) = std::mem::take(&mut *dead)
+            .into_iter()
+            .partition(|job| job.next <= now);
+        *dead = waiting;
+        due
+    }
+}
+
+// Summarises the client configuration, omitting the TLS configuration & poll queue
+impl std::fmt::Debug for Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client")
+            .field("addr", &self.addr)
+            .field(
+                "transport",
+                &match &*self.inner.read().unwrap() {
+                    Some(ClientInner::Https(_)) => "HTTPS",
+                    Some(ClientInner::Http(_)) => "HTTP",
+                    Some(ClientInner::Custom(_)) => "Custom",
+                    None => "Shutdown",
+                },
+            )
+            .field("min_poll_rate", &self.min_poll_rate().get())
+            .field("connection_stats", &self.connection_stats())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A snapshot of the number of requests made, and connections opened by a [`Client`], as returned by [`Client::connection_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    /// The number of HTTP requests made
+    pub requests: u64,
+    /// The number of new connections that were attempted, each of which requires a TCP & TLS handshake
+    pub new_connections: u64,
+}
+
+impl ConnectionStats {
+    /// The number of requests that reused an existing pooled connection
+    pub fn reused_connections(&self) -> u64 {
+        self.requests.saturating_sub(self.new_connections)
+    }
+}
+
+impl Client {
+    const DEFAULT_POLLRATE: Uint32 = Uint32(900);
+    const DEFAULT_POSTRATE: Uint32 = Uint32(900);
+    const DEFAULT_MIN_POLLRATE: Uint32 = Uint32(1);
+    const DEFAULT_TICKRATE: Duration = Duration::from_secs(600);
+    const DEFAULT_REGISTRATION_POLLRATE: Uint32 = Uint32(30);
+    const DEFAULT_MAX_REDIRECTS: u32 = 5;
+    const DEFAULT_MAX_LIST_REQUESTS: u32 = 256;
+    // Largest page requested by `get_all`, the `l` query parameter being a UInt8
+    const LIST_PAGE_SIZE: u8 = u8::MAX;
+    const DEFAULT_ERROR_BODY_LIMIT: u32 = 4096;
+    const IDEMPOTENT_PAGE_SIZE: u8 = 32;
+    const CERT_EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+    const REPORT_RETRIES: u32 = 3;
+    const REPORT_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+    /// Construct an IEEE 2030.5 Client instance that uses HTTP
+    ///
+    /// **TCP KeepAlive**:
+    ///
+    /// Pass the given value to `SO_KEEPALIVE`.
+    ///
+    /// **Tickrate**:
+    ///
+    /// Set how often the client background task should wakeup to check the polling queue.
+    ///
+    /// Defaults to 10 minutes, if this function is not called.
+    ///
+    /// If [`Client::start_poll`] is never called, this setting has no effect.
+    pub fn new(
+        server_addr: &str,
+        tcp_keepalive: Option<Duration>,
+        tickrate: Option<Duration>,
+    ) -> Result<Self> {
+        ClientBuilder::from_parts(server_addr, tcp_keepalive, tickrate).build()
+    }
+
+    /// Construct an IEEE 2030.5 Client instance for local testing, that uses HTTPS with self-signed certificates on both ends.
+    ///
+    /// The client presents the certificate at `cert_path`, and trusts only the server certificate at `peer_cert_path`,
+    /// which need not be issued by a CA. The server's hostname is not verified. Mutual TLS is still performed,
+    /// such that the client can be used with a server constructed using `ClientNotifServer::new_test`.
+    ///
+    /// **This constructor is insecure, and must not be used outside of tests.**
+    #[cfg(feature = "test_util")]
+    pub fn new_test(
+        server_addr: &str,
+        cert_path: impl AsRef<Path>,
+        pk_path: impl AsRef<Path>,
+        peer_cert_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let fdi = security_init(&cert_path)?;
+        let expiry = cert_not_after(&cert_path)?;
+        let counters: Arc<ConnectionCounters> = Arc::default();
+        let inner = ClientInner::Https(crate::tls::create_test_client(
+            cert_path,
+            pk_path,
+            peer_cert_path,
+            counters.clone(),
+        )?);
+        Ok(Self::from_inner(
+            server_addr,
+            inner,
+            counters,
+            Some(fdi),
+            Some(expiry),
+            None,
+            None,
+        ))
+    }
+
+    /// Create a [`ClientBuilder`] for a Client connecting to the given server address,
+    /// allowing options not accepted by the other constructors to be set.
+    ///
+    /// The client uses HTTP, unless one of the `with_https` methods is called on the builder.
+    pub fn builder(server_addr: &str) -> ClientBuilder {
+        ClientBuilder::from_parts(server_addr, None, None)
+    }
 
     /// Construct an IEEE 2030.5 Client instance that uses HTTPS
+    ///
+    /// `rootca_path` may be either a single PEM file, or a directory of PEM files, in which case all certificates in the directory are trusted.
+    ///
+    /// The private key must be unencrypted, passphrase protected keys can be loaded using [`ClientIdentity::from_encrypted_pem_files`].
     pub fn new_https(
         server_addr: &str,
         cert_path: impl AsRef<Path>,
@@ -281,28 +1706,550 @@ impl Client {
         tcp_keepalive: Option<Duration>,
         tickrate: Option<Duration>,
     ) -> Result<Self> {
-        let cfg = create_client_tls_cfg(cert_path, pk_path, rootca_path)?;
+        ClientBuilder::from_parts(server_addr, tcp_keepalive, tickrate)
+            .with_https(cert_path, pk_path, rootca_path)
+            .build()
+    }
+
+    /// Construct an IEEE 2030.5 Client instance that sends all requests using the given [`Transport`].
+    ///
+    /// `server_addr` is prepended to the relative paths given to request methods, as with the other constructors.
+    ///
+    /// [`Client::connection_stats`] does not report new connections made by a custom transport.
+    pub fn with_transport(
+        server_addr: &str,
+        transport: impl Transport,
+        tickrate: Option<Duration>,
+    ) -> Self {
+        let inner = ClientInner::Custom(Arc::new(transport));
+        Self::from_inner(
+            server_addr,
+            inner,
+            Arc::default(),
+            None,
+            None,
+            None,
+            tickrate,
+        )
+    }
+
+    // Construct a client using the given transport, and start it's background poll task
+    fn from_inner(
+        server_addr: &str,
+        inner: ClientInner,
+        counters: Arc<ConnectionCounters>,
+        fdi: Option<(HexBinary160, SFDIType)>,
+        cert_expiry: Option<SEPTime>,
+        host: Option<HeaderValue>,
+        tickrate: Option<Duration>,
+    ) -> Self {
         let out = Client {
             addr: server_addr.to_owned().into(),
-            inner: ClientInner::Https(create_client(cfg, tcp_keepalive)),
+            inner: Arc::new(std::sync::RwLock::new(Some(inner))),
             polls: Arc::new(Mutex::new(BinaryHeap::new())),
+            default_poll_rate: Arc::new(AtomicU32::new(Self::DEFAULT_POLLRATE.get())),
+            default_post_rate: Arc::new(AtomicU32::new(Self::DEFAULT_POSTRATE.get())),
+            min_poll_rate: Arc::new(AtomicU32::new(Self::DEFAULT_MIN_POLLRATE.get())),
+            max_redirects: Arc::new(AtomicU32::new(Self::DEFAULT_MAX_REDIRECTS)),
+            max_list_requests: Arc::new(AtomicU32::new(Self::DEFAULT_MAX_LIST_REQUESTS)),
+            error_body_limit: Arc::new(AtomicU32::new(Self::DEFAULT_ERROR_BODY_LIMIT)),
+            poll_backoff: Arc::default(),
+            poll_permits: Arc::default(),
+            poll_overlap: Arc::default(),
+            server_poll_rate: Arc::new(AtomicU32::new(0)),
+            counters,
+            fdi,
+            cert_expiry,
+            shutdown: CancellationToken::new(),
+            tasks: Arc::new(TaskTracker::new()),
+            watchdog: Arc::default(),
+            etags: Arc::default(),
+            profile: Arc::default(),
+            lenient_namespaces: Arc::default(),
+            lenient_location: Arc::default(),
+            state: Arc::default(),
+            path_rules: Arc::default(),
+            host,
         };
-        tokio::spawn(
+        out.tasks.spawn(
             out.clone()
-                .poll_task(tickrate.unwrap_or(Self::DEFAULT_TICKRATE)),
+                .supervise_poll_task(tickrate.unwrap_or(Self::DEFAULT_TICKRATE)),
         );
-        Ok(out)
+        out
+    }
+
+    /// Construct an IEEE 2030.5 Client instance that uses HTTPS, with an already loaded certificate & private key.
+    ///
+    /// This allows the same [`ClientIdentity`] to be used by both a Client and a `ClientNotifServer`.
+    ///
+    /// `rootca_path` may be either a single PEM file, or a directory of PEM files, in which case all certificates in the directory are trusted.
+    pub fn new_https_from_identity(
+        server_addr: &str,
+        identity: &ClientIdentity,
+        rootca_path: impl AsRef<Path>,
+        tcp_keepalive: Option<Duration>,
+        tickrate: Option<Duration>,
+    ) -> Result<Self> {
+        ClientBuilder::from_parts(server_addr, tcp_keepalive, tickrate)
+            .with_https_identity(identity, rootca_path)
+            .build()
+    }
+
+    /// Construct an IEEE 2030.5 Client instance that uses HTTPS, and presents one of many client certificates.
+    ///
+    /// When a new connection is established, `selector` is given the URI of the server being connected to,
+    /// and chooses which of `identities` is presented to that server.
+    /// This allows a single process to act on behalf of many devices, without constructing a Client per certificate.
+    ///
+    /// `rootca_path` may be either a single PEM file, or a directory of PEM files, in which case all certificates in the directory are trusted.
+    pub fn new_https_with_identities(
+        server_addr: &str,
+        identities: Vec<ClientIdentity>,
+        selector: IdentitySelector,
+        rootca_path: impl AsRef<Path>,
+        tcp_keepalive: Option<Duration>,
+        tickrate: Option<Duration>,
+    ) -> Result<Self> {
+        ClientBuilder::from_parts(server_addr, tcp_keepalive, tickrate)
+            .with_https_identities(identities, selector, rootca_path)
+            .build()
+    }
+
+    /// Return the LFDI of the certificate this client presents to the server.
+    ///
+    /// Returns `None` if the client uses HTTP, or was constructed with [`Client::new_https_with_identities`].
+    pub fn lfdi(&self) -> Option<HexBinary160> {
+        self.fdi.map(|(lfdi, _)| lfdi)
+    }
+
+    /// Return the SFDI of the certificate this client presents to the server.
+    ///
+    /// Returns `None` if the client uses HTTP, or was constructed with [`Client::new_https_with_identities`].
+    pub fn sfdi(&self) -> Option<SFDIType> {
+        self.fdi.map(|(_, sfdi)| sfdi)
+    }
+
+    /// Return when the certificate this client presents to the server expires, as per it's `notAfter` field.
+    ///
+    /// If the client was constructed with [`Client::new_https_with_identities`], the earliest expiry of all it's certificates is returned.
+    /// Returns `None` if the client uses HTTP.
+    pub fn cert_expiry(&self) -> Option<SEPTime> {
+        self.cert_expiry
+    }
+
+    /// Begin checking whether the certificate this client presents expires within `warn_before`, as per [`Client::cert_expiry`].
+    ///
+    /// The certificate is checked before this function returns, then every hour, against [`current_time_with_offset`].
+    /// The callback is called once, with the time the certificate expires, when it is first found to be within `warn_before` of expiry,
+    /// giving operators time to rotate the certificate before TLS handshakes with the server begin to fail.
+    ///
+    /// Checks are stopped by [`Client::cancel_polls`], alongside polls.
+    ///
+    /// Returns an error if the client does not present a certificate.
+    pub async fn start_cert_expiry_monitor(
+        &self,
+        warn_before: Duration,
+        callback: impl Fn(SEPTime) + Send + Sync + 'static,
+    ) -> Result<PollHandle> {
+        let not_after = self
+            .cert_expiry
+            .context("Client does not present a certificate")?;
+        let warned = AtomicBool::new(false);
+        let check = Arc::new(move || {
+            let remaining = i64::from(not_after) - i64::from(current_time_with_offset());
+            if remaining <= warn_before.as_secs() as i64 && !warned.swap(true, Ordering::Relaxed) {
+                log::warn!(
+                    "Client: Certificate expires in {}s, and should be renewed",
+                    remaining.max(0)
+                );
+                callback(not_after);
+            }
+        });
+        check();
+        let new: PollHandler = Box::new(move || {
+            let check = check.clone();
+            Box::pin(async move {
+                check();
+                true
+            })
+        });
+        let interval = Self::CERT_EXPIRY_CHECK_INTERVAL;
+        let (poll, handle) = PollJob::new(new, interval, Instant::now() + interval);
+        self.polls.lock().await.push(poll);
+        Ok(handle)
+    }
+
+    /// Return the number of requests made, and the number of new connections opened by this client, and all of it's clones.
+    ///
+    /// Requests that did not require a new connection reused an existing pooled connection,
+    /// which may be used to determine whether KeepAlive is working as expected.
+    ///
+    /// A new connection may occasionally be opened while a request is waiting for a pooled connection,
+    /// in which case it is counted even if the request ends up reusing the pooled connection.
+    pub fn connection_stats(&self) -> ConnectionStats {
+        ConnectionStats {
+            requests: self.counters.requests.load(Ordering::Relaxed),
+            new_connections: self.counters.connections.load(Ordering::Relaxed),
+        }
+    }
+
+    // Perform a request using the underlying transport
+    fn request(
+        &self,
+        mut req: Request<Body>,
+    ) -> impl Future<Output = Result<hyper::Response<Body>, ClientError>> + Send + 'static {
+        self.counters.requests.fetch_add(1, Ordering::Relaxed);
+        if let Some(host) = &self.host {
+            req.headers_mut().insert(HOST, host.clone());
+        }
+        let res = self
+            .inner
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|inner| inner.request(req));
+        async move {
+            let res = res.ok_or(ClientError::Shutdown)?;
+            // Custom transports may return any error, but hyper's are classified as transport failures
+            res.await
+                .map_err(|err| match err.downcast::<hyper::Error>() {
+                    Ok(err) => ClientError::Transport(err),
+                    Err(err) => match err.downcast::<ClientError>() {
+                        Ok(err) => err,
+                        Err(err) => ClientError::CustomTransport(err),
+                    },
+                })
+        }
+    }
+
+    /// Set the poll rate, in seconds, that [`Client::start_poll`] will use when one is not specified.
+    ///
+    /// Only affects polls started after this call. Defaults to 900 seconds (15 minutes), as per IEEE 2030.5.
+    pub fn set_default_poll_rate(&self, rate: Uint32) {
+        self.default_poll_rate.store(rate.get(), Ordering::Relaxed);
+    }
+
+    /// Return the poll rate, in seconds, that [`Client::start_poll`] will use when one is not specified.
+    pub fn default_poll_rate(&self) -> Uint32 {
+        Uint32(self.default_poll_rate.load(Ordering::Relaxed))
+    }
+
+    /// Set the post rate, in seconds, that [`Client::start_report`] will use when one is not specified.
+    ///
+    /// Only affects reports started after this call. Defaults to 900 seconds (15 minutes).
+    pub fn set_default_post_rate(&self, rate: Uint32) {
+        self.default_post_rate.store(rate.get(), Ordering::Relaxed);
+    }
+
+    /// Return the post rate, in seconds, that [`Client::start_report`] will use when one is not specified.
+    pub fn default_post_rate(&self) -> Uint32 {
+        Uint32(self.default_post_rate.load(Ordering::Relaxed))
+    }
+
+    /// Use the `pollRate` advertised by the given resource, such as a [`DeviceCapability`], as the [`Client::default_poll_rate`].
+    ///
+    /// Does nothing if the resource does not advertise a poll rate. Returns the default poll rate, as updated.
+    ///
+    /// [`DeviceCapability`]: sep2_common::packages::dcap::DeviceCapability
+    pub fn use_advertised_poll_rate(&self, resource: &impl AdvertisedPollRate) -> Uint32 {
+        if let Some(rate) = resource.poll_rate() {
+            self.set_default_poll_rate(rate);
+        }
+        self.default_poll_rate()
+    }
+
+    /// Use the `postRate` advertised by the given resource, such as an [`EndDevice`], as the [`Client::default_post_rate`],
+    /// such that reports, including DERStatus & DERAvailability reports, are sent at the rate the server expects.
+    ///
+    /// Does nothing if the resource does not advertise a post rate. Returns the default post rate, as updated.
+    pub fn use_advertised_post_rate(&self, resource: &impl AdvertisedPostRate) -> Uint32 {
+        if let Some(rate) = resource.post_rate() {
+            self.set_default_post_rate(rate);
+        }
+        self.default_post_rate()
+    }
+
+    /// Set the maximum number of redirects that will be followed when retrieving a resource.
+    ///
+    /// Redirects are only followed for GET requests, and must refer to the server the client is connected to.
+    /// If a request is redirected more times than this, or is redirected to a path it has already visited,
+    /// [`ClientError::TooManyRedirects`] is returned. A limit of zero disables following redirects.
+    ///
+    /// Defaults to 5.
+    pub fn set_max_redirects(&self, max: u32) {
+        self.max_redirects.store(max, Ordering::Relaxed);
+    }
+
+    /// Set the maximum number of GET requests made when retrieving every item of a list, such as using [`Client::get_all`].
+    ///
+    /// If all items have not been retrieved within this many requests, [`ClientError::TooManyListRequests`] is returned.
+    /// This prevents a server that reports inconsistent counts from causing requests to be made indefinitely.
+    ///
+    /// Defaults to 256.
+    pub fn set_max_list_requests(&self, max: u32) {
+        self.max_list_requests.store(max, Ordering::Relaxed);
+    }
+
+    /// Set the maximum number of bytes of an unexpected response's body that will be captured, as an [`ErrorBody`], for error reporting.
+    ///
+    /// Longer bodies, such as large HTML error pages, are truncated. A limit of zero disables capturing bodies.
+    ///
+    /// Defaults to 4096 bytes.
+    pub fn set_error_body_limit(&self, limit: u32) {
+        self.error_body_limit.store(limit, Ordering::Relaxed);
+    }
+
+    fn error_body_limit(&self) -> usize {
+        self.error_body_limit.load(Ordering::Relaxed) as usize
+    }
+
+    /// Set the revision, or profile, of IEEE 2030.5 targeted when serializing resources sent in PUT & POST requests.
+    ///
+    /// Elements of those resources that are not part of the profile are omitted, and a warning is logged for each.
+    /// `None` sends resources in full, as is the default.
+    ///
+    /// Resources sent using [`Client::post_streaming`] are also serialized using the profile, which requires them to be held in memory in their serialized form.
+    pub fn set_schema_profile(&self, profile: Option<SchemaProfile>) {
+        *self.profile.write().unwrap() = profile.map(Arc::new);
+    }
+
+    /// Return the revision, or profile, of IEEE 2030.5 targeted when serializing resources, if any.
+    pub fn schema_profile(&self) -> Option<Arc<SchemaProfile>> {
+        self.profile.read().unwrap().clone()
+    }
+
+    /// Set whether resources retrieved from the server tolerate non-conformant use of XML namespaces.
+    ///
+    /// When enabled, element prefixes that are undeclared, or bound to the IEEE 2030.5 namespace, are ignored,
+    /// and the default namespace of the resource is assumed to be the IEEE 2030.5 namespace, whether it is missing or not.
+    /// Intended for interoperating with servers that do not conform to the schema, and disabled by default.
+    pub fn set_lenient_namespaces(&self, lenient: bool) {
+        self.lenient_namespaces.store(lenient, Ordering::Relaxed);
+    }
+
+    /// Set whether a 201 Created response with a Location header that is not valid ASCII is still returned as [`SEPResponse::Created`].
+    ///
+    /// When enabled, the header is converted lossily, and a warning is logged.
+    /// When disabled, as it is by default, [`ClientError::InvalidLocationHeader`] is returned instead.
+    pub fn set_lenient_location(&self, lenient: bool) {
+        self.lenient_location.store(lenient, Ordering::Relaxed);
+    }
+
+    // Deserialize a resource retrieved from the server, normalizing it's namespaces if lenient
+    fn deserialize<R: SEResource>(&self, xml: &str) -> Result<R, ClientError> {
+        let res = if self.lenient_namespaces.load(Ordering::Relaxed) {
+            deserialize(&normalize_namespaces(xml))
+        } else {
+            deserialize(xml)
+        };
+        res.map_err(|err| ClientError::Deserialize {
+            resource: R::name().to_owned(),
+            reason: err.to_string(),
+        })
+    }
+
+    /// Set the rules that the paths of resources sent in PUT & POST requests are checked against.
+    ///
+    /// Resources sent to a path the rules do not permit are logged, or, if the rules are strict,
+    /// are not sent, and a [`ClientError::UnexpectedPath`] is returned.
+    /// `None` performs no checks, as is the default.
+    pub fn set_path_rules(&self, rules: Option<PathRules>) {
+        *self.path_rules.write().unwrap() = rules.map(Arc::new);
+    }
+
+    /// Return the rules that the paths of outgoing resources are checked against, if any.
+    pub fn path_rules(&self) -> Option<Arc<PathRules>> {
+        self.path_rules.read().unwrap().clone()
+    }
+
+    // Check that a resource of type `R` may be sent to the given path, as per the client's path rules
+    fn check_path<R: SEResource>(&self, path: &str) -> Result<(), ClientError> {
+        match self.path_rules() {
+            Some(rules) => rules.check::<R>(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Back off polls that fail consecutively, such as during a prolonged server outage.
+    ///
+    /// After each consecutive failure, the interval until a poll next runs is doubled, up to `max_interval`,
+    /// returning to it's poll rate after the first success. Applies to all polls, reports & keep-alive probes.
+    /// Polls are still run immediately by [`Client::force_polls`].
+    ///
+    /// `None` disables backoff, as is the default.
+    pub fn set_poll_backoff(&self, max_interval: Option<Duration>) {
+        let millis = max_interval.map_or(0, |max| (max.as_millis() as u64).max(1));
+        self.poll_backoff.store(millis, Ordering::Relaxed);
+    }
+
+    fn poll_backoff(&self) -> Option<Duration> {
+        match self.poll_backoff.load(Ordering::Relaxed) {
+            0 => None,
+            millis => Some(Duration::from_millis(millis)),
+        }
+    }
+
+    /// Limit the number of polls, reports & keep-alive probes that may run at once, such as on constrained hardware.
+    ///
+    /// Polls that become due while the limit is reached are queued, and run in the order they became due as running polls complete.
+    /// Polls already running, or queued, when this is called are not subject to the new limit.
+    ///
+    /// `None` removes the limit, as is the default. A limit of zero is treated as one.
+    pub fn set_max_concurrent_polls(&self, max: Option<usize>) {
+        *self.poll_permits.write().unwrap() = max.map(|max| Arc::new(Semaphore::new(max.max(1))));
+    }
+
+    fn poll_permits(&self) -> Option<Arc<Semaphore>> {
+        self.poll_permits.read().unwrap().clone()
+    }
+
+    /// Set how a poll, or report, is run when it becomes due while it's previous run is still in progress.
+    ///
+    /// `None` runs every poll when it becomes due, regardless of how many previous runs are still in progress, as is the default.
+    pub fn set_poll_overlap(&self, overlap: Option<PollOverlap>) {
+        let max = match overlap {
+            None => 0,
+            Some(PollOverlap::Skip) => 1,
+            Some(PollOverlap::Concurrent(max)) => max.max(1),
+        };
+        self.poll_overlap.store(max, Ordering::Relaxed);
+    }
+
+    // The maximum number of runs of a single poll in progress at once
+    fn max_running_polls(&self) -> usize {
+        match self.poll_overlap.load(Ordering::Relaxed) {
+            0 => usize::MAX,
+            max => max,
+        }
+    }
+
+    /// Set the minimum poll rate, in seconds, that [`Client::start_poll`] will use.
+    ///
+    /// Requested poll rates lower than this value will be clamped up to it.
+    ///
+    /// Defaults to 1 second.
+    pub fn set_min_poll_rate(&self, rate: Uint32) {
+        self.min_poll_rate.store(rate.get(), Ordering::Relaxed);
+    }
+
+    /// Inform the client of the poll rate advertised by the server, such as the `pollRate` of a [`DeviceCapability`] or a List resource.
+    ///
+    /// Subsequent calls to [`Client::start_poll`] will not poll faster than this rate, to avoid being throttled by the server.
+    ///
+    /// [`DeviceCapability`]: sep2_common::packages::dcap::DeviceCapability
+    pub fn set_server_poll_rate(&self, rate: Uint32) {
+        self.server_poll_rate.store(rate.get(), Ordering::Relaxed);
+    }
+
+    /// Return the minimum poll rate, in seconds, that will be used by [`Client::start_poll`].
+    ///
+    /// This is the greater of the client-configured minimum, and the server-advertised poll rate, if known.
+    pub fn min_poll_rate(&self) -> Uint32 {
+        Uint32(
+            self.min_poll_rate
+                .load(Ordering::Relaxed)
+                .max(self.server_poll_rate.load(Ordering::Relaxed)),
+        )
+    }
+
+    /// Restart polls created using [`Client::start_poll`] and it's variants, and the background task that services them, should they panic.
+    ///
+    /// A poll whose callback panics is not run again until it is restarted. Each poll is restarted at most `max_restarts` times,
+    /// after which it is stopped, and it's [`PollHandle::next_fire`] returns `None`. The background task is likewise restarted at most
+    /// `max_restarts` times, over the lifetime of the client. The first restart of each occurs after `backoff`,
+    /// with the wait doubling after each subsequent restart. Each restart is logged.
+    ///
+    /// Poll callbacks are run in their own tasks, and a panic in a callback does not affect the background task, nor other polls.
+    ///
+    /// Disabled by default, in which case a poll stops permanently if it's callback panics, and all polling stops permanently if the background task panics.
+    pub fn set_poll_watchdog(&self, max_restarts: u32, backoff: Duration) {
+        self.watchdog
+            .max_restarts
+            .store(max_restarts, Ordering::Relaxed);
+        self.watchdog
+            .backoff
+            .store(backoff.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    // Run the poll task, restarting it, and any polls that panicked, as per the watchdog configuration
+    async fn supervise_poll_task(self, tickrate: Duration) {
+        let mut restarts = 0;
+        let mut task = tokio::spawn(self.clone().poll_task(tickrate));
+        loop {
+            tokio::select! {
+                res = &mut task => match res {
+                    Err(e) if e.is_panic() => {
+                        let max_restarts = self.watchdog.max_restarts.load(Ordering::Relaxed);
+                        if restarts >= max_restarts {
+                            log::error!("Client: Poll task panicked, polling has stopped.");
+                            break;
+                        }
+                        let backoff = self.watchdog.backoff(restarts);
+                        restarts += 1;
+                        log::warn!(
+                            "Client: Poll task panicked, restarting in {backoff:?} ({restarts}/{max_restarts})"
+                        );
+                        tokio::select! {
+                            _ = tokio::time::sleep(backoff) => (),
+                            _ = self.shutdown.cancelled() => break,
+                        }
+                        task = tokio::spawn(self.clone().poll_task(tickrate));
+                    }
+                    // Shutdown, or the runtime is shutting down
+                    _ => break,
+                },
+                _ = tokio::time::sleep(tickrate) => self.restart_dead_polls().await,
+            }
+        }
+    }
+
+    // Requeue the polls that panicked, and whose backoff has elapsed
+    async fn restart_dead_polls(&self) {
+        let due = self.watchdog.take_due(Instant::now());
+        if due.is_empty() {
+            return;
+        }
+        let mut polls = self.polls.lock().await;
+        for mut job in due {
+            job.restarts += 1;
+            job.panicked.store(false, Ordering::Relaxed);
+            polls.push(job);
+        }
     }
 
     async fn poll_task(self, tickrate: Duration) {
         loop {
-            tokio::time::sleep(tickrate).await;
+            tokio::select! {
+                _ = tokio::time::sleep(tickrate) => (),
+                _ = self.shutdown.cancelled() => {
+                    log::info!("Client: Shutting down poll task...");
+                    break
+                },
+            }
             let mut polls = self.polls.lock().await;
             while let Some(task) = polls.peek() {
                 if task.next < Instant::now() {
                     // unwrap trivially safe
                     let mut cur = polls.pop().unwrap();
-                    cur.execute().await;
+                    if cur.panicked.load(Ordering::Relaxed) {
+                        self.watchdog.bury(cur);
+                        continue;
+                    }
+                    if let Some(until) = cur.retry_until(Instant::now()) {
+                        log::debug!(
+                            "Client: Deferring poll, as the server requested it not be retried yet"
+                        );
+                        cur.set_next(until);
+                        polls.push(cur);
+                        continue;
+                    }
+                    match cur.backoff_until(self.poll_backoff()) {
+                        Some(until) if until > Instant::now() => cur.set_next(until),
+                        _ => {
+                            cur.execute(&self.tasks, self.poll_permits(), self.max_running_polls())
+                                .await
+                        }
+                    }
                     polls.push(cur);
                 } else {
                     break;
@@ -311,57 +2258,488 @@ impl Client {
         }
     }
 
-    /// Retrieve the [`SEResource`] at the given relative path.
+    /// Check that the server is reachable, and that it accepts the client's certificate, by performing a GET request on `/dcap`.
+    ///
+    /// The response body is not deserialized.
+    ///
+    /// Returns a [`ConnectivityError`] describing the failure if the check did not succeed.
+    pub async fn check_connectivity(&self) -> Result<()> {
+        let req = self.dcap_request()?;
+        log::info!("Client: Checking connectivity with {}", req.uri());
+        let res = self.request(req).await.map_err(ConnectivityError::from)?;
+        match res.status() {
+            StatusCode::OK => Ok(()),
+            s @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+                Err(ConnectivityError::Unauthorized(s).into())
+            }
+            s => Err(ConnectivityError::UnexpectedStatus(s).into()),
+        }
+    }
+
+    // A GET request on `/dcap`, as used by connectivity checks & keep-alive probes
+    fn dcap_request(&self) -> Result<Request<Body>, ClientError> {
+        let uri: Uri = format!("{}/dcap", self.addr).parse()?;
+        Ok(Request::builder()
+            .method(Method::GET)
+            .header(ACCEPT, "application/sep+xml")
+            .header(DATE, fmt_http_date(current_time_with_offset().into()))
+            .uri(uri)
+            .body(Body::empty())?)
+    }
+
+    /// Begin sending a keep-alive probe, a GET request on `/dcap`, every `interval`.
+    ///
+    /// Intended for clients that are idle between infrequent polls, where a NAT or firewall may otherwise drop the idle connection,
+    /// causing the next poll to require a new TLS handshake, or to fail.
+    /// `interval` should therefore be shorter than the idle timeout of the network path to the server.
+    ///
+    /// The response body is read and discarded so that the connection can be reused. Failed probes are logged, and not retried.
+    ///
+    /// Probes are stopped by [`Client::cancel_polls`], alongside polls.
+    ///
+    /// Returns a [`PollHandle`] that can be used to query when the next probe will be sent.
+    pub async fn start_keep_alive(&self, interval: Duration) -> PollHandle {
+        let new: PollHandler = Box::new({
+            let client = self.clone();
+            move || {
+                let client = client.clone();
+                Box::pin(async move {
+                    let res = match client.dcap_request() {
+                        Ok(req) => client.request(req).await,
+                        Err(err) => Err(err),
+                    };
+                    match res {
+                        Ok(res) => {
+                            let status = res.status();
+                            let _ = hyper::body::to_bytes(res.into_body()).await;
+                            log::debug!("Client: Keep-alive probe returned {status}");
+                            true
+                        }
+                        Err(err) => {
+                            log::warn!("Client: Keep-alive probe failed with reason {err}");
+                            false
+                        }
+                    }
+                })
+            }
+        });
+        let (poll, handle) = PollJob::new(new, interval, Instant::now() + interval);
+        self.polls.lock().await.push(poll);
+        handle
+    }
+
+    /// Retrieve the [`SEResource`] at the given relative path.
+    ///
+    /// Returns an error if the resource could not be retrieved or deserialized.
+    /// If the server responds with 200 OK and an empty body, the error is a [`ClientError::EmptyBody`].
+    pub async fn get<R: SEResource>(&self, path: &str) -> Result<R, ClientError> {
+        let xml = self.get_xml::<R>(path).await?;
+        self.deserialize(&xml)
+    }
+
+    /// Retrieve a page of the [`SEList`] at the given relative path, as determined by the given [`ListQuery`].
+    ///
+    /// Returns an error if the list could not be retrieved or deserialized.
+    ///
+    /// [`SEList`]: sep2_common::traits::SEList
+    pub async fn get_with_query<L: SEList>(
+        &self,
+        path: &str,
+        query: &ListQuery<L>,
+    ) -> Result<L, ClientError> {
+        let query = query.to_string();
+        if query.is_empty() {
+            self.get(path).await
+        } else {
+            self.get(&format!("{path}?{query}")).await
+        }
+    }
+
+    /// Retrieve the [`SEResource`] referred to by the given [`Link`].
+    ///
+    /// Relative hrefs are resolved against the server address. Absolute hrefs are followed directly,
+    /// but must refer to the server the client is connected to, otherwise a [`ClientError::CrossOrigin`] is returned.
+    pub async fn get_link<R: SEResource>(&self, link: &Link) -> Result<R, ClientError> {
+        self.get(&resolve_href(&self.addr, &link.href)?).await
+    }
+
+    /// Retrieve the [`SEList`] referred to by the given [`ListLink`].
+    ///
+    /// Hrefs are resolved in the same manner as [`Client::get_link`].
+    ///
+    /// [`SEList`]: sep2_common::traits::SEList
+    pub async fn get_list_link<R: SEResource>(&self, link: &ListLink) -> Result<R, ClientError> {
+        self.get(&resolve_href(&self.addr, &link.href)?).await
+    }
+
+    /// Retrieve every item of the [`SEList`] at the given relative path.
+    ///
+    /// Pages of the list are requested using the `s` & `l` query parameters, starting at the number of items received so far,
+    /// until as many items as the list's `all` attribute have been received, or the server returns an empty page.
+    ///
+    /// Returns [`ClientError::TooManyListRequests`] if this requires more requests than [`Client::set_max_list_requests`] allows.
+    ///
+    /// [`SEList`]: sep2_common::traits::SEList
+    pub async fn get_all<L: SEList>(&self, path: &str) -> Result<Vec<L::Inner>, ClientError> {
+        self.get_list_all::<L>(path, Self::LIST_PAGE_SIZE).await
+    }
+
+    /// Retrieve every item of the [`SEList`] referred to by the given [`ListLink`],
+    /// requesting it in pages of at most `page_size` items, as per [`Client::get_all`].
+    ///
+    /// Hrefs are resolved in the same manner as [`Client::get_link`].
+    pub async fn get_list_link_all<L: SEList>(
+        &self,
+        link: &ListLink,
+        page_size: u8,
+    ) -> Result<Vec<L::Inner>, ClientError> {
+        self.get_list_all::<L>(&resolve_href(&self.addr, &link.href)?, page_size)
+            .await
+    }
+
+    // Retrieve every item of the SEList at the given relative path, in pages of at most `page_size` items
+    async fn get_list_all<L: SEList>(
+        &self,
+        path: &str,
+        page_size: u8,
+    ) -> Result<Vec<L::Inner>, ClientError> {
+        let max_requests = self.max_list_requests.load(Ordering::Relaxed);
+        let mut items = vec![];
+        for requests in 0.. {
+            if requests >= max_requests {
+                return Err(ClientError::TooManyListRequests);
+            }
+            let query = ListQuery::<L>::new()
+                .start(items.len() as u32)
+                .limit(page_size);
+            let mut page: L = self.get_with_query(path, &query).await?;
+            let all = page.all().get() as usize;
+            let received = std::mem::take(page.list_mut());
+            if received.is_empty() {
+                break;
+            }
+            items.extend(received);
+            if items.len() >= all {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// Retrieve the [`SEResource`] at the given relative path, alongside the names of any XML elements that were ignored during deserialization.
+    ///
+    /// Unknown elements are ignored when deserializing resources, for forward-compatibility. A non-empty list of ignored elements may indicate the server
+    /// is using a newer revision of IEEE 2030.5 than is supported by this client. Each ignored element is also logged as a warning.
+    ///
+    /// Returns an error if the resource could not be retrieved or deserialized.
+    pub async fn get_with_warnings<R: SEResource>(
+        &self,
+        path: &str,
+    ) -> Result<(R, Vec<String>), ClientError> {
+        let xml = self.get_xml::<R>(path).await?;
+        let rsrc: R = self.deserialize(&xml)?;
+        let ignored = ignored_elements(&xml, &serialize_rsrc(&rsrc)?);
+        for elem in &ignored {
+            log::warn!(
+                "Client: Element {elem} in {} at {path} was not understood and has been ignored",
+                R::name()
+            );
+        }
+        Ok((rsrc, ignored))
+    }
+
+    /// Retrieve the [`SEResource`] at the given relative path, alongside the XML body it was deserialized from.
+    ///
+    /// The body is returned exactly as it was received, and may be archived, or compared against an expected representation.
+    ///
+    /// Returns an error if the resource could not be retrieved or deserialized.
+    pub async fn get_raw<R: SEResource>(&self, path: &str) -> Result<(R, String), ClientError> {
+        let xml = self.get_xml::<R>(path).await?;
+        let rsrc = self.deserialize(&xml)?;
+        Ok((rsrc, xml))
+    }
+
+    /// Retrieve the [`SEResource`] at the given relative path, if it has changed since it was last retrieved using this function.
+    ///
+    /// If the server provided an `ETag` when the resource was last retrieved, it is sent in an `If-None-Match` header,
+    /// and a 304 Not Modified response returns `None`. ETags are stored per-path, and shared between cloned clients.
+    ///
+    /// Returns an error if the resource could not be retrieved or deserialized.
+    pub async fn get_if_changed<R: SEResource>(
+        &self,
+        path: &str,
+    ) -> Result<Option<R>, ClientError> {
+        let etag = self.etags.lock().unwrap().get(path).cloned();
+        let res = self
+            .get_response::<R>(path, etag.map(|etag| (IF_NONE_MATCH, etag)))
+            .await?;
+        if res.status() == StatusCode::NOT_MODIFIED {
+            log::debug!("Client: {} at {path} has not been modified", R::name());
+            return Ok(None);
+        }
+        let etag = res.headers().get(ETAG).cloned();
+        let xml = read_body(res).await?;
+        let rsrc = self.deserialize(&xml)?;
+        // Only store the ETag once we know it corresponds to a valid resource
+        let mut etags = self.etags.lock().unwrap();
+        match etag {
+            Some(etag) => etags.insert(path.to_owned(), etag),
+            None => etags.remove(path),
+        };
+        Ok(Some(rsrc))
+    }
+
+    /// Retrieve the [`SEResource`] at the given relative path, if it has been modified since the given time.
+    ///
+    /// The time is sent in an `If-Modified-Since` header, and a 304 Not Modified response returns `None`.
+    /// Servers that do not support conditional requests respond with the resource regardless.
+    ///
+    /// Returns an error if the resource could not be retrieved or deserialized.
+    pub async fn get_if_modified_since<R: SEResource>(
+        &self,
+        path: &str,
+        since: SEPTime,
+    ) -> Result<Option<R>, ClientError> {
+        Ok(self.get_modified::<R>(path, Some(since)).await?.0)
+    }
+
+    // Retrieve the resource at the given relative path, if it has been modified since the given time, if any.
+    // Also returns when the resource was last modified, as per the `Last-Modified` header,
+    // or when the request was made, if the server did not provide one.
+    async fn get_modified<R: SEResource>(
+        &self,
+        path: &str,
+        since: Option<SEPTime>,
+    ) -> Result<(Option<R>, SEPTime), ClientError> {
+        let requested = current_time_with_offset();
+        let condition = since
+            .map(|since| -> Result<_, ClientError> {
+                let since = fmt_http_date(since.into()).parse();
+                Ok((IF_MODIFIED_SINCE, since.map_err(hyper::http::Error::from)?))
+            })
+            .transpose()?;
+        let res = self.get_response::<R>(path, condition).await?;
+        let modified = res
+            .headers()
+            .get(LAST_MODIFIED)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| httpdate::parse_http_date(h).ok())
+            .map_or(requested, SEPTime::from);
+        if res.status() == StatusCode::NOT_MODIFIED {
+            log::debug!("Client: {} at {path} has not been modified", R::name());
+            return Ok((None, modified));
+        }
+        let xml = read_body(res).await?;
+        Ok((Some(self.deserialize(&xml)?), modified))
+    }
+
+    /// Clear all ETags stored by [`Client::get_if_changed`], such that the next request for each path retrieves the resource.
+    pub fn clear_etags(&self) {
+        self.etags.lock().unwrap().clear();
+    }
+
+    // Retrieve the XML body of the resource at the given relative path
+    async fn get_xml<R: SEResource>(&self, path: &str) -> Result<String, ClientError> {
+        let res = self.get_response::<R>(path, None).await?;
+        read_body(res).await
+    }
+
+    // Perform a GET request, returning the response if it is 200 OK, or 304 Not Modified if a conditional header is given
+    async fn get_response<R: SEResource>(
+        &self,
+        path: &str,
+        condition: Option<(HeaderName, HeaderValue)>,
+    ) -> Result<hyper::Response<Body>, ClientError> {
+        let conditional = condition.is_some();
+        let max_redirects = self.max_redirects.load(Ordering::Relaxed);
+        let mut path = path.to_owned();
+        let mut visited = HashSet::new();
+        loop {
+            let uri: Uri = format!("{}{}", self.addr, path).parse()?;
+            log::info!("Client: GET {} from {}", R::name(), uri);
+            let mut req = Request::builder()
+                .method(Method::GET)
+                .header(ACCEPT, "application/sep+xml")
+                .header(DATE, fmt_http_date(current_time_with_offset().into()));
+            if let Some((name, value)) = &condition {
+                req = req.header(name, value);
+            }
+            let req = req.uri(uri).body(Body::default())?;
+            log::debug!("Client: Outgoing HTTP Request: {:?}", req);
+            let res = self.request(req).await?;
+            log::debug!("Client: Incoming HTTP Response: {:?}", res);
+            match res.status() {
+                StatusCode::OK => return Ok(res),
+                StatusCode::NOT_MODIFIED if conditional => return Ok(res),
+                // Moved resources - 2030.5-2018 - 5.5.2.6
+                StatusCode::MOVED_PERMANENTLY
+                | StatusCode::FOUND
+                | StatusCode::SEE_OTHER
+                | StatusCode::TEMPORARY_REDIRECT
+                | StatusCode::PERMANENT_REDIRECT => {
+                    let loc = res
+                        .headers()
+                        .get(LOCATION)
+                        .and_then(|h| h.to_str().ok())
+                        .ok_or(ClientError::MissingLocationHeader)?;
+                    let next = resolve_href(&self.addr, loc)?;
+                    visited.insert(path);
+                    if visited.contains(&next) || visited.len() > max_redirects as usize {
+                        return Err(ClientError::TooManyRedirects);
+                    }
+                    log::info!("Client: {} redirected to {next}", R::name());
+                    path = next;
+                }
+                _ => return Err(unexpected_response(res, self.error_body_limit()).await),
+            }
+        }
+    }
+
+    /// Update a [`SEResource`] at the given relative path.
     ///
-    /// Returns an error if the resource could not be retrieved or deserialized.
-    pub async fn get<R: SEResource>(&self, path: &str) -> Result<R> {
-        let uri: Uri = format!("{}{}", self.addr, path)
-            .parse()
-            .context("Failed to parse address")?;
-        log::info!("Client: GET {} from {}", R::name(), uri);
+    /// Returns an error if the server does not respond with 204 No Content or 201 Created.
+    pub async fn post<R: SEResource>(
+        &self,
+        path: &str,
+        resource: &R,
+    ) -> Result<SEPResponse, ClientError> {
+        let path = format!("{}{}", self.addr, path);
+        self.put_post(
+            path.parse()?,
+            resource,
+            Method::POST,
+            current_time_with_offset(),
+            None,
+        )
+        .await
+    }
+
+    /// Update a [`SEResource`] at the given relative path, serializing it directly into a chunked request body.
+    ///
+    /// Unlike [`Client::post`], the resource is never held in memory in it's serialized form,
+    /// making this more suitable for very large resources, such as a `MirrorMeterReading` containing many readings.
+    /// For small resources, [`Client::post`] should be preferred, since not all servers support chunked transfer encoding.
+    ///
+    /// If a [`SchemaProfile`] is set using [`Client::set_schema_profile`], the resource is serialized in full so that the profile can be applied,
+    /// such that it is sent exactly as [`Client::post`] would send it, and is then sent in chunks.
+    ///
+    /// Returns an error if the resource could not be serialized, or if the server does not respond with 204 No Content or 201 Created.
+    pub async fn post_streaming<R: SEResource>(
+        &self,
+        path: &str,
+        resource: R,
+    ) -> Result<SEPResponse, ClientError> {
+        let abs_path: Uri = format!("{}{}", self.addr, path).parse()?;
+        self.check_path::<R>(abs_path.path())?;
+        log::info!("POST {} to {} (streaming)", R::name(), abs_path);
+        let (mut body_tx, body) = Body::channel();
+        let (chunk_tx, mut chunk_rx) = tokio::sync::mpsc::channel(STREAM_CHUNK_BUFFER);
+        let profile = self.schema_profile();
+        let serializer = tokio::task::spawn_blocking(move || {
+            let mut writer = ChunkWriter {
+                buf: Vec::with_capacity(STREAM_CHUNK_SIZE),
+                tx: chunk_tx,
+            };
+            match profile {
+                // Profiles are applied to the serialized document as a whole
+                Some(profile) => {
+                    let rsrce = profile.apply(serialize(&resource)?);
+                    for chunk in rsrce.as_bytes().chunks(STREAM_CHUNK_SIZE) {
+                        writer.write_all(chunk)?;
+                    }
+                    Ok(writer.flush()?)
+                }
+                None => {
+                    sepserde::ser::serialize_with_writer(&resource, writer, &Default::default())
+                        .map_err(|e| anyhow!(e))
+                        .and_then(|mut w| Ok(w.flush()?))
+                }
+            }
+        });
+        let forward = async move {
+            while let Some(chunk) = chunk_rx.recv().await {
+                if body_tx.send_data(chunk).await.is_err() {
+                    return;
+                }
+            }
+            // If serialization failed part way through, make sure the server doesn't receive a truncated resource
+            if !matches!(serializer.await, Ok(Ok(()))) {
+                log::warn!(
+                    "Client: Failed to serialize {}, aborting request",
+                    R::name()
+                );
+                body_tx.abort();
+            }
+        };
         let req = Request::builder()
-            .method(Method::GET)
-            .header(ACCEPT, "application/sep+xml")
+            .method(Method::POST)
+            .header(CONTENT_TYPE, "application/sep+xml")
             .header(DATE, fmt_http_date(current_time_with_offset().into()))
-            .uri(uri)
-            .body(Body::default())?;
+            .uri(abs_path)
+            .body(body)?;
         log::debug!("Client: Outgoing HTTP Request: {:?}", req);
-        let res = self.inner.request(req).await?;
+        let (res, ()) = tokio::join!(self.request(req), forward);
+        let res = res?;
         log::debug!("Client: Incoming HTTP Response: {:?}", res);
-        // TODO: Handle moved resources - implement HTTP redirects
-        match res.status() {
-            StatusCode::OK => (),
-            e => bail!("Unexpected HTTP response from server: {}", e),
-        }
-        let body = hyper::body::to_bytes(res.into_body()).await?;
-        let xml = String::from_utf8_lossy(&body);
-        deserialize(&xml)
+        into_sepresponse(
+            &self.addr,
+            res,
+            self.error_body_limit(),
+            self.lenient_location.load(Ordering::Relaxed),
+        )
+        .await
     }
 
-    /// Update a [`SEResource`] at the given relative path.
+    /// Create a [`SEResource`] at the given relative path.
     ///
     /// Returns an error if the server does not respond with 204 No Content or 201 Created.
-    pub async fn post<R: SEResource>(&self, path: &str, resource: &R) -> Result<SEPResponse> {
+    pub async fn put<R: SEResource>(
+        &self,
+        path: &str,
+        resource: &R,
+    ) -> Result<SEPResponse, ClientError> {
         let path = format!("{}{}", self.addr, path);
         self.put_post(
-            path.parse().context("Failed to parse address")?,
+            path.parse()?,
             resource,
-            Method::POST,
+            Method::PUT,
             current_time_with_offset(),
+            None,
         )
         .await
     }
 
-    /// Create a [`SEResource`] at the given relative path.
+    /// Update the [`SEResource`] at the given relative path, by retrieving it, applying `mutate` to it, and PUTting it back.
     ///
-    /// Returns an error if the server does not respond with 204 No Content or 201 Created.
-    pub async fn put<R: SEResource>(&self, path: &str, resource: &R) -> Result<SEPResponse> {
-        let path = format!("{}{}", self.addr, path);
+    /// If the server provided an `ETag` with the resource, it is sent in an `If-Match` header on the PUT,
+    /// such that the update is rejected if the resource was modified in the meantime.
+    /// In that case, [`ClientError::PreconditionFailed`] is returned, and the update may be retried.
+    /// If no `ETag` was provided, the PUT is unconditional.
+    ///
+    /// Returns an error if the resource could not be retrieved or deserialized,
+    /// or if the server does not respond to the PUT with 204 No Content or 201 Created.
+    pub async fn update_with<R: SEResource>(
+        &self,
+        path: &str,
+        mutate: impl FnOnce(&mut R),
+    ) -> Result<SEPResponse, ClientError> {
+        let res = self.get_response::<R>(path, None).await?;
+        let etag = res.headers().get(ETAG).cloned();
+        let mut rsrc: R = self.deserialize(&read_body(res).await?)?;
+        mutate(&mut rsrc);
+        if etag.is_none() {
+            log::debug!(
+                "Client: {} at {path} has no ETag, updating unconditionally",
+                R::name()
+            );
+        }
+        let uri = format!("{}{}", self.addr, path);
         self.put_post(
-            path.parse().context("Failed to parse address")?,
-            resource,
+            uri.parse()?,
+            &rsrc,
             Method::PUT,
             current_time_with_offset(),
+            etag,
         )
         .await
     }
@@ -369,10 +2747,8 @@ impl Client {
     /// Delete the [`SEResource`] at the given relative path.
     ///
     /// Returns an error if the server does not respond with 204 No Content.
-    pub async fn delete(&self, path: &str) -> Result<SEPResponse> {
-        let uri: Uri = format!("{}{}", self.addr, path)
-            .parse()
-            .context("Failed to parse address")?;
+    pub async fn delete(&self, path: &str) -> Result<SEPResponse, ClientError> {
+        let uri: Uri = format!("{}{}", self.addr, path).parse()?;
         log::info!("Client: DELETE at {}", uri);
         let req = Request::builder()
             .method(Method::DELETE)
@@ -380,83 +2756,819 @@ impl Client {
             .uri(uri)
             .body(Body::empty())?;
         log::debug!("Client: Outgoing HTTP Request: {:?}", req);
-        let res = self.inner.request(req).await?;
+        let res = self.request(req).await?;
         log::debug!("Client: Incoming HTTP Response: {:?}", res);
-        into_sepresponse(res).await
+        into_sepresponse(
+            &self.addr,
+            res,
+            self.error_body_limit(),
+            self.lenient_location.load(Ordering::Relaxed),
+        )
+        .await
+    }
+
+    /// Retrieve the [`SEResource`] at the given relative path, as per [`Client::get`].
+    ///
+    /// Returns [`ClientError::Cancelled`] if the given token is cancelled before the request completes.
+    pub async fn get_cancellable<R: SEResource>(
+        &self,
+        path: &str,
+        token: &CancellationToken,
+    ) -> Result<R, ClientError> {
+        cancellable(token, self.get(path)).await
+    }
+
+    /// Update a [`SEResource`] at the given relative path, as per [`Client::post`].
+    ///
+    /// Returns [`ClientError::Cancelled`] if the given token is cancelled before the request completes.
+    pub async fn post_cancellable<R: SEResource>(
+        &self,
+        path: &str,
+        resource: &R,
+        token: &CancellationToken,
+    ) -> Result<SEPResponse, ClientError> {
+        cancellable(token, self.post(path, resource)).await
+    }
+
+    /// Create a [`SEResource`] at the given relative path, as per [`Client::put`].
+    ///
+    /// Returns [`ClientError::Cancelled`] if the given token is cancelled before the request completes.
+    pub async fn put_cancellable<R: SEResource>(
+        &self,
+        path: &str,
+        resource: &R,
+        token: &CancellationToken,
+    ) -> Result<SEPResponse, ClientError> {
+        cancellable(token, self.put(path, resource)).await
+    }
+
+    /// Delete the [`SEResource`] at the given relative path, as per [`Client::delete`].
+    ///
+    /// Returns [`ClientError::Cancelled`] if the given token is cancelled before the request completes.
+    pub async fn delete_cancellable(
+        &self,
+        path: &str,
+        token: &CancellationToken,
+    ) -> Result<SEPResponse, ClientError> {
+        cancellable(token, self.delete(path)).await
+    }
+
+    /// Retrieve the [`SEResource`] at the given relative path, as per [`Client::get`], subject to the given [`RequestCtx`].
+    pub async fn get_with_ctx<R: SEResource>(
+        &self,
+        path: &str,
+        ctx: &RequestCtx,
+    ) -> Result<R, ClientError> {
+        ctx.run(|| self.get(path)).await
+    }
+
+    /// Update a [`SEResource`] at the given relative path, as per [`Client::post`], subject to the given [`RequestCtx`].
+    pub async fn post_with_ctx<R: SEResource>(
+        &self,
+        path: &str,
+        resource: &R,
+        ctx: &RequestCtx,
+    ) -> Result<SEPResponse, ClientError> {
+        ctx.run(|| self.post(path, resource)).await
+    }
+
+    /// Create a resource by POSTing it to the [`SEList`] at the given relative path, subject to the given [`RequestCtx`],
+    /// without creating a duplicate if the request is retried.
+    ///
+    /// Unlike [`Client::post_with_ctx`], requests that may have reached the server are also retried, such as when the response was lost,
+    /// or the server responded with a retryable [`ClientError::ServerError`].
+    /// Before retrying such a request, the list is searched for an item that `is_same` as the resource, such as by comparing mRIDs or LFDIs.
+    /// If one is found, it's href is returned as [`SEPResponse::Created`], instead of POSTing the resource again.
+    ///
+    /// i.e. to register an [`EndDevice`] at most once:
+    /// ```no_run
+    /// # use sep2_client::client::{Client, RequestCtx};
+    /// # use sep2_common::packages::edev::{EndDevice, EndDeviceList};
+    /// # async fn register(client: &Client, edev: &EndDevice) -> anyhow::Result<()> {
+    /// let ctx = RequestCtx {
+    ///     retries: 3,
+    ///     ..Default::default()
+    /// };
+    /// client
+    ///     .post_idempotent::<EndDeviceList, _>("/edev", edev, &ctx, |other| other.lfdi == edev.lfdi)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`SEList`]: sep2_common::traits::SEList
+    pub async fn post_idempotent<L, R>(
+        &self,
+        path: &str,
+        resource: &R,
+        ctx: &RequestCtx,
+        is_same: impl Fn(&R) -> bool,
+    ) -> Result<SEPResponse, ClientError>
+    where
+        L: SEList<Inner = R>,
+        R: SEResource,
+    {
+        // Retries are handled here, such that the list can be checked before each
+        let once = RequestCtx {
+            retries: 0,
+            ..ctx.clone()
+        };
+        once.run(|| async {
+            let mut backoff = RequestCtx::RETRY_BACKOFF;
+            let mut attempt = 0;
+            // Whether a previous attempt may have created the resource
+            let mut reached = false;
+            loop {
+                let res = if reached {
+                    self.find_created::<L, R>(path, &is_same).await
+                } else {
+                    Ok(None)
+                };
+                let res = match res {
+                    Ok(Some(href)) => {
+                        log::info!(
+                            "Client: {} was already created at {href}, not POSTing it again",
+                            R::name()
+                        );
+                        return Ok(SEPResponse::Created(Some(href)));
+                    }
+                    Ok(None) => self.post(path, resource).await,
+                    Err(e) => Err(e),
+                };
+                match res {
+                    Err(e) if attempt < ctx.retries && is_retryable_error(&e) => {
+                        attempt += 1;
+                        reached |= !is_connect_error(&e);
+                        log::warn!(
+                            "Client: Failed to POST {} to {path}: {e}, retrying in {backoff:?} ({attempt}/{})",
+                            R::name(),
+                            ctx.retries
+                        );
+                        tokio::time::sleep(backoff).await;
+                        backoff = backoff.saturating_mul(2);
+                    }
+                    res => return res,
+                }
+            }
+        })
+        .await
+    }
+
+    // Search the SEList at the given relative path for an item matching `is_same`, returning it's href
+    async fn find_created<L, R>(
+        &self,
+        path: &str,
+        is_same: impl Fn(&R) -> bool,
+    ) -> Result<Option<String>, ClientError>
+    where
+        L: SEList<Inner = R>,
+        R: SEResource,
+    {
+        let items = self
+            .get_list_all::<L>(path, Self::IDEMPOTENT_PAGE_SIZE)
+            .await?;
+        Ok(items
+            .iter()
+            .find(|item| is_same(item))
+            .and_then(|item| item.href())
+            .map(|href| normalize_location(&self.addr, href)))
+    }
+
+    /// Create a [`SEResource`] at the given relative path, as per [`Client::put`], subject to the given [`RequestCtx`].
+    pub async fn put_with_ctx<R: SEResource>(
+        &self,
+        path: &str,
+        resource: &R,
+        ctx: &RequestCtx,
+    ) -> Result<SEPResponse, ClientError> {
+        ctx.run(|| self.put(path, resource)).await
+    }
+
+    /// Delete the [`SEResource`] at the given relative path, as per [`Client::delete`], subject to the given [`RequestCtx`].
+    pub async fn delete_with_ctx(
+        &self,
+        path: &str,
+        ctx: &RequestCtx,
+    ) -> Result<SEPResponse, ClientError> {
+        ctx.run(|| self.delete(path)).await
     }
 
     /// Begin polling the given route by performing GET requests on a regular interval. Passes the returned [`SEResource`] to the given callback.
     ///
     /// The callback will not be run if the GET request fails, or the resource cannot be deserialized.
+    /// For callbacks that only need to read the resource, see [`Client::start_poll_ref`].
     ///
-    /// As per IEEE 2030.5, if a poll rate is not specified, a default of 900 seconds (15 minutes) is used.
+    /// If a poll rate is not specified, [`Client::default_poll_rate`] is used.
+    ///
+    /// Poll rates lower than [`Client::min_poll_rate`] will be clamped up to it.
+    ///
+    /// If the server responds with a retry hint, as per [`ClientError::retry_after`], the poll does not run again until it has elapsed.
     ///
     /// All poll events created can be forcibly run using [`Client::force_polls`], such as is required when reconnecting to the server after a period of connectivity loss.
+    ///
+    /// If a [`StateStore`](crate::persist::StateStore) is set using [`Client::set_state_store`], the poll is saved to it, such that it can be resumed using [`Client::resume_polls`].
+    ///
+    /// Returns a [`PollHandle`] that can be used to query when the poll will next run.
     pub async fn start_poll<T>(
         &self,
         path: impl Into<String>,
-        poll_rate: Option<Uint32>,
-        callback: impl PollCallback<T>,
-    ) where
-        T: SEResource,
+        poll_rate: Option<Uint32>,
+        callback: impl PollCallback<T>,
+    ) -> PollHandle
+    where
+        T: SEResource,
+    {
+        self.make_poll::<T, _>(
+            path.into(),
+            poll_rate,
+            PollKind::Resource,
+            move |client, path| {
+                // Each run of the poll produces a single future that includes our callback,
+                // so we need to clone it every invocation of the poll callback.
+                let callback = callback.clone();
+                async move {
+                    let rsrc = client.get::<T>(&path).await?;
+                    log::info!(
+                        "Client: Scheduled poll for Resource {} successful.",
+                        T::name()
+                    );
+                    callback.callback(rsrc).await;
+                    Ok(())
+                }
+            },
+        )
+        .await
+    }
+
+    /// Begin polling the given route, as per [`Client::start_poll`], passing a reference to the returned [`SEResource`] to the given callback.
+    ///
+    /// The callback is neither cloned, nor given ownership of the resource, each poll, making this suited to handlers that only read from the resource.
+    ///
+    /// Returns a [`PollHandle`] that can be used to query when the poll will next run.
+    pub async fn start_poll_ref<T>(
+        &self,
+        path: impl Into<String>,
+        poll_rate: Option<Uint32>,
+        callback: impl PollRefCallback<T>,
+    ) -> PollHandle
+    where
+        T: SEResource,
+    {
+        let callback = Arc::new(callback);
+        self.make_poll::<T, _>(
+            path.into(),
+            poll_rate,
+            PollKind::Ref,
+            move |client, path| {
+                let callback = callback.clone();
+                async move {
+                    let rsrc = client.get::<T>(&path).await?;
+                    log::info!(
+                        "Client: Scheduled poll for Resource {} successful.",
+                        T::name()
+                    );
+                    callback.callback(&rsrc).await;
+                    Ok(())
+                }
+            },
+        )
+        .await
+    }
+
+    /// Begin polling the given route, as per [`Client::start_poll`], using conditional GET requests, as per [`Client::get_if_modified_since`].
+    ///
+    /// Each request after the first successful one is sent with an `If-Modified-Since` header,
+    /// set to the `Last-Modified` time of the resource when it was last retrieved, or, if the server did not provide one, when it was last requested.
+    /// The callback is not run if the server responds with 304 Not Modified.
+    ///
+    /// Returns a [`PollHandle`] that can be used to query when the poll will next run.
+    pub async fn start_poll_if_modified<T>(
+        &self,
+        path: impl Into<String>,
+        poll_rate: Option<Uint32>,
+        callback: impl PollCallback<T>,
+    ) -> PollHandle
+    where
+        T: SEResource,
+    {
+        // When the resource was last modified, as of the last successful poll
+        let since: Arc<std::sync::Mutex<Option<SEPTime>>> = Arc::default();
+        self.make_poll::<T, _>(path.into(), poll_rate, PollKind::IfModified, move |client, path| {
+            let callback = callback.clone();
+            let since = since.clone();
+            async move {
+                let prev = *since.lock().unwrap();
+                let (rsrc, modified) = client.get_modified::<T>(&path, prev).await?;
+                *since.lock().unwrap() = Some(modified);
+                match rsrc {
+                    Some(rsrc) => {
+                        log::info!(
+                            "Client: Scheduled poll for Resource {} successful.",
+                            T::name()
+                        );
+                        callback.callback(rsrc).await;
+                    }
+                    None => log::info!(
+                        "Client: Scheduled poll for Resource {} successful, but it has not been modified.",
+                        T::name()
+                    ),
+                }
+                Ok(())
+            }
+        })
+        .await
+    }
+
+    /// Begin polling the given list route, as per [`Client::start_poll`], passing only the items of the list that have changed to the given callback.
+    ///
+    /// Items are identified by their mRID, and are considered changed if their version differs from that of the previous poll.
+    /// All items are considered new on the first successful poll.
+    /// Items that are no longer present in the list are passed to [`ListChangeCallback::removed`].
+    ///
+    /// Only the list as returned by the server is compared, no additional pages of the list are retrieved.
+    ///
+    /// Returns a [`PollHandle`] that can be used to query when the poll will next run.
+    pub async fn start_poll_changes<L>(
+        &self,
+        path: impl Into<String>,
+        poll_rate: Option<Uint32>,
+        callback: impl ListChangeCallback<L::Inner>,
+    ) -> PollHandle
+    where
+        L: SEList,
+        L::Inner: SEIdentifiedObject + Send,
+    {
+        let callback = Arc::new(callback);
+        // The version of each item, by mRID, as of the previous successful poll
+        let known: Arc<std::sync::Mutex<HashMap<MRIDType, Option<VersionType>>>> = Arc::default();
+        self.make_poll::<L, _>(
+            path.into(),
+            poll_rate,
+            PollKind::Changes,
+            move |client, path| {
+                let callback = callback.clone();
+                let known = known.clone();
+                async move {
+                    let mut list = client.get::<L>(&path).await?;
+                    let (changed, removed) = {
+                        let mut known = known.lock().unwrap();
+                        let mut current = HashMap::new();
+                        let mut changed = vec![];
+                        for item in std::mem::take(list.list_mut()) {
+                            let mrid = *item.mrid();
+                            let version = item.version();
+                            current.insert(mrid, version);
+                            if known.get(&mrid) != Some(&version) {
+                                changed.push(item);
+                            }
+                        }
+                        let removed: Vec<MRIDType> = known
+                            .keys()
+                            .filter(|mrid| !current.contains_key(mrid))
+                            .copied()
+                            .collect();
+                        *known = current;
+                        (changed, removed)
+                    };
+                    log::info!(
+                    "Client: Scheduled poll for Resource {} successful, {} changed, {} removed.",
+                    L::name(),
+                    changed.len(),
+                    removed.len()
+                );
+                    if !changed.is_empty() {
+                        callback.changed(changed).await;
+                    }
+                    if !removed.is_empty() {
+                        callback.removed(removed).await;
+                    }
+                    Ok(())
+                }
+            },
+        )
+        .await
+    }
+
+    // Begin polling the given path on a regular interval, as per `start_poll`, where each run of the poll is performed by `fetch`.
+    // `fetch` retrieves the resource of type `T`, and passes it to the poll's callback.
+    // Failed runs are logged, and any retry hint the server responded with is honoured.
+    // The poll is recorded as being of the given kind, such that it can be resumed by the same variant.
+    async fn make_poll<T, Fut>(
+        &self,
+        path: String,
+        poll_rate: Option<Uint32>,
+        kind: PollKind,
+        fetch: impl Fn(Client, String) -> Fut + Send + Sync + 'static,
+    ) -> PollHandle
+    where
+        T: SEResource,
+        Fut: Future<Output = Result<(), ClientError>> + Send + 'static,
+    {
+        let poll_rate = clamp_poll_rate(
+            poll_rate.unwrap_or(self.default_poll_rate()),
+            self.min_poll_rate(),
+        )
+        .get();
+        self.state.record_poll(PollRecord {
+            path: path.clone(),
+            resource: T::name().to_owned(),
+            poll_rate,
+            kind,
+        });
+        let hint = RetryHint::default();
+        let new: PollHandler = Box::new({
+            let client = self.clone();
+            let hint = hint.clone();
+            move || {
+                let run = fetch(client.clone(), path.clone());
+                let path = path.clone();
+                let hint = hint.clone();
+                Box::pin(async move {
+                    match run.await {
+                        Ok(()) => true,
+                        Err(err) => {
+                            log::warn!(
+                                "Client: Scheduled poll for Resource {} at {} failed with reason {}. Retrying in {} seconds.",
+                                T::name(),
+                                &path,
+                                err,
+                                &poll_rate
+                            );
+                            record_retry_hint(&hint, &err);
+                            false
+                        }
+                    }
+                })
+            }
+        });
+        let interval = Duration::from_secs(poll_rate as u64);
+        let (poll, handle) =
+            PollJob::with_retry_hint(new, interval, Instant::now() + interval, hint);
+        self.polls.lock().await.push(poll);
+        handle
+    }
+
+    /// Begin polling the [`Time`] referred to by the given [`Link`], as per [`Client::start_poll`].
+    ///
+    /// Hrefs are resolved in the same manner as [`Client::get_link`].
+    pub async fn poll_time(
+        &self,
+        link: &Link,
+        poll_rate: Option<Uint32>,
+        callback: impl PollCallback<Time>,
+    ) -> Result<PollHandle> {
+        self.start_poll_link(&link.href, poll_rate, callback).await
+    }
+
+    /// Begin polling the [`EndDeviceList`] referred to by the given [`ListLink`], as per [`Client::start_poll`].
+    ///
+    /// Hrefs are resolved in the same manner as [`Client::get_link`].
+    pub async fn poll_end_device_list(
+        &self,
+        link: &ListLink,
+        poll_rate: Option<Uint32>,
+        callback: impl PollCallback<EndDeviceList>,
+    ) -> Result<PollHandle> {
+        self.start_poll_link(&link.href, poll_rate, callback).await
+    }
+
+    /// Begin polling the [`FunctionSetAssignmentsList`] referred to by the given [`ListLink`], as per [`Client::start_poll`].
+    ///
+    /// Hrefs are resolved in the same manner as [`Client::get_link`].
+    pub async fn poll_fsa_list(
+        &self,
+        link: &ListLink,
+        poll_rate: Option<Uint32>,
+        callback: impl PollCallback<FunctionSetAssignmentsList>,
+    ) -> Result<PollHandle> {
+        self.start_poll_link(&link.href, poll_rate, callback).await
+    }
+
+    /// Begin polling the [`DERProgramList`] referred to by the given [`ListLink`], as per [`Client::start_poll`].
+    ///
+    /// Hrefs are resolved in the same manner as [`Client::get_link`].
+    #[cfg(feature = "der")]
+    pub async fn poll_der_program_list(
+        &self,
+        link: &ListLink,
+        poll_rate: Option<Uint32>,
+        callback: impl PollCallback<DERProgramList>,
+    ) -> Result<PollHandle> {
+        self.start_poll_link(&link.href, poll_rate, callback).await
+    }
+
+    /// Begin polling the [`DERControlList`] referred to by the given [`ListLink`], as per [`Client::start_poll`].
+    ///
+    /// Hrefs are resolved in the same manner as [`Client::get_link`].
+    #[cfg(feature = "der")]
+    pub async fn poll_der_control_list(
+        &self,
+        link: &ListLink,
+        poll_rate: Option<Uint32>,
+        callback: impl PollCallback<DERControlList>,
+    ) -> Result<PollHandle> {
+        self.start_poll_link(&link.href, poll_rate, callback).await
+    }
+
+    /// Begin polling the [`DefaultDERControl`] of the given [`DERProgram`], as per [`Client::start_poll`].
+    ///
+    /// Hrefs are resolved in the same manner as [`Client::get_link`].
+    ///
+    /// Returns an error if the DERProgram does not contain a DefaultDERControlLink.
+    #[cfg(feature = "der")]
+    pub async fn poll_default_der_control(
+        &self,
+        program: &DERProgram,
+        poll_rate: Option<Uint32>,
+        callback: impl PollCallback<DefaultDERControl>,
+    ) -> Result<PollHandle> {
+        let link = program
+            .default_der_control_link
+            .as_ref()
+            .context("DERProgram does not contain a DefaultDERControlLink")?;
+        self.start_poll_link(&link.href, poll_rate, callback).await
+    }
+
+    /// Begin polling the [`DemandResponseProgramList`] referred to by the given [`ListLink`], as per [`Client::start_poll`].
+    ///
+    /// Hrefs are resolved in the same manner as [`Client::get_link`].
+    #[cfg(feature = "drlc")]
+    pub async fn poll_demand_response_program_list(
+        &self,
+        link: &ListLink,
+        poll_rate: Option<Uint32>,
+        callback: impl PollCallback<DemandResponseProgramList>,
+    ) -> Result<PollHandle> {
+        self.start_poll_link(&link.href, poll_rate, callback).await
+    }
+
+    /// Begin polling the [`TariffProfileList`] referred to by the given [`ListLink`], as per [`Client::start_poll`].
+    ///
+    /// Hrefs are resolved in the same manner as [`Client::get_link`].
+    #[cfg(feature = "pricing")]
+    pub async fn poll_tariff_profile_list(
+        &self,
+        link: &ListLink,
+        poll_rate: Option<Uint32>,
+        callback: impl PollCallback<TariffProfileList>,
+    ) -> Result<PollHandle> {
+        self.start_poll_link(&link.href, poll_rate, callback).await
+    }
+
+    /// Begin polling the [`MessagingProgramList`] referred to by the given [`ListLink`], as per [`Client::start_poll`].
+    ///
+    /// Hrefs are resolved in the same manner as [`Client::get_link`].
+    #[cfg(feature = "messaging")]
+    pub async fn poll_messaging_program_list(
+        &self,
+        link: &ListLink,
+        poll_rate: Option<Uint32>,
+        callback: impl PollCallback<MessagingProgramList>,
+    ) -> Result<PollHandle> {
+        self.start_poll_link(&link.href, poll_rate, callback).await
+    }
+
+    // Begin polling the resource at the given href, as per `start_poll`
+    async fn start_poll_link<T: SEResource>(
+        &self,
+        href: &str,
+        poll_rate: Option<Uint32>,
+        callback: impl PollCallback<T>,
+    ) -> Result<PollHandle> {
+        let path = resolve_href(&self.addr, href)?;
+        Ok(self.start_poll(path, poll_rate, callback).await)
+    }
+
+    /// Begin periodically POSTing the resource produced by `report` to the given relative path, such as to meet an [`EndDevice`]'s `postRate`.
+    ///
+    /// The first report is sent on the next tick of the poll task, and subsequent reports every `post_rate` seconds thereafter.
+    /// If a post rate is not specified, [`Client::default_post_rate`] is used.
+    ///
+    /// A report that fails to send due to a connection or server error is retried up to 3 times, with a backoff starting at 1 second.
+    /// Reports rejected by the server, such as with 400 Bad Request, are not retried.
+    ///
+    /// Reports are stopped by [`Client::cancel_polls`], alongside polls.
+    ///
+    /// Returns a [`PollHandle`] that can be used to query when the report will next be sent.
+    pub async fn start_report<R>(
+        &self,
+        path: impl Into<String>,
+        post_rate: Option<Uint32>,
+        report: impl Fn() -> R + Send + Sync + 'static,
+    ) -> PollHandle
+    where
+        R: SEResource,
+    {
+        let post_rate = post_rate.unwrap_or(self.default_post_rate()).get().max(1);
+        self.start_report_with_rate(path, Arc::new(AtomicU32::new(post_rate)), report)
+            .await
+    }
+
+    // Begin periodically POSTing a report as per `start_report`, at the given post rate, in seconds, as it is when each report is sent
+    pub(crate) async fn start_report_with_rate<R>(
+        &self,
+        path: impl Into<String>,
+        post_rate: Arc<AtomicU32>,
+        report: impl Fn() -> R + Send + Sync + 'static,
+    ) -> PollHandle
+    where
+        R: SEResource,
     {
-        let poll_rate = poll_rate.unwrap_or(Self::DEFAULT_POLLRATE).get();
+        let report = Arc::new(report);
         let new: PollHandler = Box::new({
             let client = self.clone();
             let path: String = path.into();
+            let post_rate = post_rate.clone();
             move || {
-                Box::pin({
-                    let path = path.clone();
-                    let client = client.clone();
-                    // Each time this closure is called, we need to produce a single future to return,
-                    // that future includes our callback, so we need to clone it every invocation of the poll callback.
-                    let callback = callback.clone();
-                    async move {
-                        match client.get::<T>(&path).await {
-                            Ok(rsrc) => {
+                let client = client.clone();
+                let path = path.clone();
+                let report = report.clone();
+                let post_rate = post_rate.load(Ordering::Relaxed).max(1);
+                Box::pin(async move {
+                    let rsrc = report();
+                    let mut backoff = Self::REPORT_RETRY_BACKOFF;
+                    for attempt in 0..=Self::REPORT_RETRIES {
+                        match client.post(&path, &rsrc).await {
+                            Ok(r @ (SEPResponse::Created(_) | SEPResponse::NoContent)) => {
                                 log::info!(
-                                    "Client: Scheduled poll for Resource {} successful.",
-                                    T::name()
+                                    "Client: Scheduled report of {} to {path} successful: {r}",
+                                    R::name()
                                 );
-                                callback.callback(rsrc).await;
+                                return true;
                             }
-                            Err(err) => {
+                            // The server is reachable, so a rejection does not warrant backing off
+                            Ok(r) => {
                                 log::warn!(
-                            "Client: Scheduled poll for Resource {} at {} failed with reason {}. Retrying in {} seconds.",
-                            T::name(),
-                            &path,
-                            err,
-                            &poll_rate
-                        );
+                                    "Client: Scheduled report of {} to {path} was rejected: {r}",
+                                    R::name()
+                                );
+                                return true;
+                            }
+                            Err(
+                                err @ (ClientError::BadRequest { .. }
+                                | ClientError::NotFound { .. }),
+                            ) => {
+                                log::warn!(
+                                    "Client: Scheduled report of {} to {path} was rejected: {err}",
+                                    R::name()
+                                );
+                                return true;
+                            }
+                            Err(err) if attempt < Self::REPORT_RETRIES => {
+                                log::warn!(
+                                    "Client: Scheduled report of {} to {path} failed with reason {err}. Retrying in {backoff:?}.",
+                                    R::name()
+                                );
+                                tokio::time::sleep(backoff).await;
+                                backoff = backoff.saturating_mul(2);
                             }
-                        };
+                            Err(err) => log::warn!(
+                                "Client: Scheduled report of {} to {path} failed with reason {err}. Retrying in {post_rate} seconds.",
+                                R::name()
+                            ),
+                        }
                     }
+                    false
                 })
             }
         });
-        let interval = Duration::from_secs(poll_rate as u64);
-        let poll = PollJob {
-            handler: new,
-            interval,
-            next: Instant::now() + interval,
-        };
+        let (poll, handle) = PollJob::with_rate(new, post_rate, Instant::now());
         self.polls.lock().await.push(poll);
+        handle
+    }
+
+    /// Begin periodically reporting the [`DERStatus`] produced by `status` to the DERStatusLink of the given [`DER`], using [`Client::start_report`].
+    ///
+    /// `post_rate` should be the `postRate` of the [`EndDevice`] the DER belongs to, if not supplied [`Client::default_post_rate`] is used.
+    ///
+    /// Returns a [`PollHandle`] for the report, or an error if the DER does not contain a DERStatusLink.
+    #[cfg(feature = "der")]
+    pub async fn start_der_status_report(
+        &self,
+        der: &DER,
+        post_rate: Option<Uint32>,
+        status: impl Fn() -> DERStatus + Send + Sync + 'static,
+    ) -> Result<PollHandle> {
+        let link = der
+            .der_status_link
+            .as_ref()
+            .context("DER does not contain a DERStatusLink")?;
+        let path = resolve_href(&self.addr, &link.href)?;
+        Ok(self.start_report(path, post_rate, status).await)
+    }
+
+    /// Begin periodically reporting the [`DERAvailability`] produced by `availability` to the DERAvailabilityLink of the given [`DER`], using [`Client::start_report`].
+    ///
+    /// `post_rate` should be the `postRate` of the [`EndDevice`] the DER belongs to, if not supplied [`Client::default_post_rate`] is used.
+    ///
+    /// Returns a [`PollHandle`] for the report, or an error if the DER does not contain a DERAvailabilityLink.
+    #[cfg(feature = "der")]
+    pub async fn start_der_availability_report(
+        &self,
+        der: &DER,
+        post_rate: Option<Uint32>,
+        availability: impl Fn() -> DERAvailability + Send + Sync + 'static,
+    ) -> Result<PollHandle> {
+        let link = der
+            .der_availability_link
+            .as_ref()
+            .context("DER does not contain a DERAvailabilityLink")?;
+        let path = resolve_href(&self.addr, &link.href)?;
+        Ok(self.start_report(path, post_rate, availability).await)
+    }
+
+    /// Wait until the [`EndDevice`] at the given relative path has been registered by the server,
+    /// by polling it until it contains a non-empty [`FunctionSetAssignmentsListLink`].
+    ///
+    /// Failed requests, such as a server responding with 403 Forbidden before the device has been approved, are retried.
+    ///
+    /// The EndDevice is polled every 30 seconds, or at [`Client::min_poll_rate`] if it is greater.
+    ///
+    /// Returns the registered [`EndDevice`], or an error if the device was not registered before the given timeout.
+    ///
+    /// [`FunctionSetAssignmentsListLink`]: sep2_common::packages::links::FunctionSetAssignmentsListLink
+    pub async fn await_registration(
+        &self,
+        edev_href: &str,
+        timeout: Duration,
+    ) -> Result<EndDevice> {
+        let rate = Duration::from_secs(
+            Self::DEFAULT_REGISTRATION_POLLRATE
+                .max(self.min_poll_rate())
+                .get() as u64,
+        );
+        let wait = async {
+            loop {
+                match self.get::<EndDevice>(edev_href).await {
+                    Ok(edev) if is_registered(&edev) => return edev,
+                    Ok(_) => log::info!(
+                        "Client: EndDevice at {edev_href} has not yet been assigned any function sets. Retrying in {} seconds.",
+                        rate.as_secs()
+                    ),
+                    Err(err) => log::info!(
+                        "Client: Failed to retrieve EndDevice at {edev_href} with reason {err}. Retrying in {} seconds.",
+                        rate.as_secs()
+                    ),
+                }
+                tokio::time::sleep(rate).await;
+            }
+        };
+        tokio::time::timeout(timeout, wait).await.map_err(|_| {
+            anyhow!(
+                "Timed out waiting for EndDevice at {edev_href} to be registered after {} seconds",
+                timeout.as_secs()
+            )
+        })
     }
 
     /// Forcibly poll & run the callbacks of all routes polled using [`Client::start_poll`]
     pub async fn force_polls(&self) {
         let mut polls = self.polls.lock().await;
-        while polls.peek().is_some() {
-            // unwrap trivially safe
-            let mut cur = polls.pop().unwrap();
-            cur.execute().await;
-            polls.push(cur);
+        // Each poll is run exactly once, then rescheduled
+        let mut ran = Vec::with_capacity(polls.len());
+        while let Some(mut cur) = polls.pop() {
+            if cur.panicked.load(Ordering::Relaxed) {
+                self.watchdog.bury(cur);
+                continue;
+            }
+            cur.execute(&self.tasks, self.poll_permits(), self.max_running_polls())
+                .await;
+            ran.push(cur);
         }
+        polls.extend(ran);
     }
 
     /// Cancel all poll tasks created using [`Client::start_poll`]
+    ///
+    /// The polls are also removed from the client's [`StateStore`](crate::persist::StateStore), if any.
     pub async fn cancel_polls(&self) {
         self.polls.lock().await.clear();
+        self.watchdog.dead.lock().unwrap().clear();
+        self.state.clear_polls();
+    }
+
+    pub(crate) fn state_recorder(&self) -> &StateRecorder {
+        &self.state
+    }
+
+    /// Shutdown the client, and all of it's clones.
+    ///
+    /// Cancels all poll tasks created using [`Client::start_poll`] and it's variants, including polls of [`Time`] resources used for time synchronisation,
+    /// and stops the background poll task, returning once it, and any poll callbacks that were running, have completed.
+    /// The connection pool is then closed, closing all idle connections.
+    ///
+    /// Unlike [`Client::cancel_polls`], the polls are retained in the client's [`StateStore`](crate::persist::StateStore), if any, such that they can be resumed.
+    ///
+    /// Requests made using clones of this client after it has been shut down return [`ClientError::Shutdown`].
+    pub async fn shutdown(self) {
+        self.shutdown.cancel();
+        self.polls.lock().await.clear();
+        self.watchdog.dead.lock().unwrap().clear();
+        self.tasks.close();
+        self.tasks.wait().await;
+        // Pooled connections are closed once the last handle to the pool is dropped
+        drop(self.inner.write().unwrap().take());
+        log::info!("Client: Shutdown complete");
     }
 
     // Create a PUT or POST request
@@ -466,21 +3578,34 @@ impl Client {
         resource: &R,
         method: Method,
         time: SEPTime,
-    ) -> Result<SEPResponse> {
+        if_match: Option<HeaderValue>,
+    ) -> Result<SEPResponse, ClientError> {
+        self.check_path::<R>(abs_path.path())?;
         log::info!("POST {} to {}", R::name(), abs_path);
-        let rsrce = serialize(resource)?;
+        let mut rsrce = serialize_rsrc(resource)?;
+        if let Some(profile) = self.schema_profile() {
+            rsrce = profile.apply(rsrce);
+        }
         let rsrce_size = rsrce.len();
-        let req = Request::builder()
+        let mut req = Request::builder()
             .method(method)
             .header(CONTENT_TYPE, "application/sep+xml")
             .header(CONTENT_LENGTH, rsrce_size)
-            .header(DATE, fmt_http_date(time.into()))
-            .uri(abs_path)
-            .body(Body::from(rsrce))?;
+            .header(DATE, fmt_http_date(time.into()));
+        if let Some(etag) = if_match {
+            req = req.header(IF_MATCH, etag);
+        }
+        let req = req.uri(abs_path).body(Body::from(rsrce))?;
         log::debug!("Client: Outgoing HTTP Request: {:?}", req);
-        let res = self.inner.request(req).await?;
+        let res = self.request(req).await?;
         log::debug!("Client: Incoming HTTP Response: {:?}", res);
-        into_sepresponse(res).await
+        into_sepresponse(
+            &self.addr,
+            res,
+            self.error_body_limit(),
+            self.lenient_location.load(Ordering::Relaxed),
+        )
+        .await
     }
 
     #[cfg(feature = "messaging")]
@@ -491,28 +3616,9 @@ impl Client {
         status: ResponseStatus,
         time: SEPTime,
     ) -> Result<SEPResponse> {
-        // As per Messaging in Table 27
-        match (status, event.response_required) {
-            (ResponseStatus::EventReceived, Some(rr))
-                if rr.contains(ResponseRequired::MessageReceived) => {}
-            (ResponseStatus::EventStarted, Some(rr))
-                if rr.contains(ResponseRequired::SpecificResponse) => {}
-            (ResponseStatus::EventCompleted, Some(rr))
-                if rr.contains(ResponseRequired::SpecificResponse) => {}
-            (ResponseStatus::EventSuperseded, Some(rr))
-                if rr.contains(ResponseRequired::SpecificResponse) => {}
-            (ResponseStatus::EventAcknowledge, Some(rr))
-                if rr.contains(ResponseRequired::ResponseRequired) => {}
-            (ResponseStatus::EventNoDisplay, Some(rr))
-                if rr.contains(ResponseRequired::SpecificResponse) => {}
-            (ResponseStatus::EventAbortedServer, Some(rr))
-                if rr.contains(ResponseRequired::SpecificResponse) => {}
-            (ResponseStatus::EventAbortedProgram, Some(rr))
-                if rr.contains(ResponseRequired::SpecificResponse) => {}
-            (ResponseStatus::EventExpired, Some(rr))
-                if rr.contains(ResponseRequired::SpecificResponse) => {}
-            _ => bail!("Attempted to send a response for an event where one was not required, either due to it's status or the event's responseRequired field.")
-        };
+        if !msg_response_required(status, event.response_required) {
+            anyhow::bail!("Attempted to send a response for an event where one was not required, either due to it's status or the event's responseRequired field.")
+        }
         let resp = TextResponse {
             created_date_time: Some(time.into()),
             end_device_lfdi: lfdi,
@@ -520,17 +3626,19 @@ impl Client {
             subject: event.mrid,
             href: None,
         };
-        self.put_post(
-            event
-                .reply_to()
-                .context("Event does not contain a ReplyTo Field")?
-                .parse()
-                .context("Failed to parse ReplyTo Field")?,
-            &resp,
-            Method::POST,
-            time,
-        )
-        .await
+        Ok(self
+            .put_post(
+                event
+                    .reply_to()
+                    .context("Event does not contain a ReplyTo Field")?
+                    .parse()
+                    .context("Failed to parse ReplyTo Field")?,
+                &resp,
+                Method::POST,
+                time,
+                None,
+            )
+            .await?)
     }
 
     #[cfg(feature = "der")]
@@ -541,19 +3649,9 @@ impl Client {
         status: ResponseStatus,
         time: SEPTime,
     ) -> Result<SEPResponse> {
-        // As per Table 27 - DER Column
-
-        match (status, event.response_required) {
-            (ResponseStatus::EventReceived, Some(rr))
-                if rr.contains(ResponseRequired::MessageReceived) => {}
-            (ResponseStatus::EventAcknowledge, Some(rr))
-                if rr.contains(ResponseRequired::ResponseRequired) => {}
-            (ResponseStatus::EventNoDisplay, _) => {
-                bail!("Attempted to send a response for an event where one was not required, either due to it's status or the event's responseRequired field.")
-            }
-            (_, Some(rr)) if rr.contains(ResponseRequired::SpecificResponse) => {}
-            _ => bail!("Attempted to send a response for an event where one was not required, either due to it's status or the event's responseRequired field."),
-        };
+        if !der_response_required(status, event.response_required) {
+            anyhow::bail!("Attempted to send a response for an event where one was not required, either due to it's status or the event's responseRequired field.")
+        }
 
         let resp = DERControlResponse {
             created_date_time: Some(time.into()),
@@ -562,17 +3660,19 @@ impl Client {
             subject: event.mrid,
             href: None,
         };
-        self.put_post(
-            event
-                .reply_to()
-                .context("Event does not contain a ReplyTo Field")?
-                .parse()
-                .context("Failed to parse ReplyTo Field")?,
-            &resp,
-            Method::POST,
-            time,
-        )
-        .await
+        Ok(self
+            .put_post(
+                event
+                    .reply_to()
+                    .context("Event does not contain a ReplyTo Field")?
+                    .parse()
+                    .context("Failed to parse ReplyTo Field")?,
+                &resp,
+                Method::POST,
+                time,
+                None,
+            )
+            .await?)
     }
 
     #[cfg(feature = "drlc")]
@@ -583,19 +3683,9 @@ impl Client {
         status: ResponseStatus,
         time: SEPTime,
     ) -> Result<SEPResponse> {
-        // As per Table 27 - DRLC Column
-
-        match (status, event.response_required) {
-            (ResponseStatus::EventReceived, Some(rr))
-                if rr.contains(ResponseRequired::MessageReceived) => {}
-            (ResponseStatus::EventAcknowledge, Some(rr))
-                if rr.contains(ResponseRequired::ResponseRequired) => {}
-            (ResponseStatus::EventNoDisplay, _) => {
-                bail!("Attempted to send a response for an event where one was not required, either due to it's status or the event's responseRequired field.")
-            }
-            (_, Some(rr)) if rr.contains(ResponseRequired::SpecificResponse) => {}
-            _ => bail!("Attempted to send a response for an event where one was not required, either due to it's status or the event's responseRequired field."),
-        };
+        if !drlc_response_required(status, event.response_required) {
+            anyhow::bail!("Attempted to send a response for an event where one was not required, either due to it's status or the event's responseRequired field.")
+        }
 
         let resp = DrResponse {
             created_date_time: Some(time.into()),
@@ -610,17 +3700,19 @@ impl Client {
             override_duration: device.override_duration,
             set_point: device.set_point.clone(),
         };
-        self.put_post(
-            event
-                .reply_to()
-                .context("Event does not contain a ReplyTo Field")?
-                .parse()
-                .context("Failed to parse ReplyTo Field")?,
-            &resp,
-            Method::POST,
-            time,
-        )
-        .await
+        Ok(self
+            .put_post(
+                event
+                    .reply_to()
+                    .context("Event does not contain a ReplyTo Field")?
+                    .parse()
+                    .context("Failed to parse ReplyTo Field")?,
+                &resp,
+                Method::POST,
+                time,
+                None,
+            )
+            .await?)
     }
 
     #[cfg(feature = "pricing")]
@@ -631,26 +3723,9 @@ impl Client {
         status: ResponseStatus,
         time: SEPTime,
     ) -> Result<SEPResponse> {
-        // As per Pricing in Table 27
-        match (status, event.response_required) {
-            (ResponseStatus::EventReceived, Some(rr))
-                if rr.contains(ResponseRequired::MessageReceived) => {}
-            (ResponseStatus::EventStarted, Some(rr))
-                if rr.contains(ResponseRequired::SpecificResponse) => {}
-            (ResponseStatus::EventCompleted, Some(rr))
-                if rr.contains(ResponseRequired::SpecificResponse) => {}
-            (ResponseStatus::EventSuperseded, Some(rr))
-                if rr.contains(ResponseRequired::SpecificResponse) => {}
-            (ResponseStatus::EventAcknowledge, Some(rr))
-                if rr.contains(ResponseRequired::ResponseRequired) => {}
-            (ResponseStatus::EventAbortedServer, Some(rr))
-                if rr.contains(ResponseRequired::SpecificResponse) => {}
-            (ResponseStatus::EventAbortedProgram, Some(rr))
-                if rr.contains(ResponseRequired::SpecificResponse) => {}
-            (ResponseStatus::EventExpired, Some(rr))
-                if rr.contains(ResponseRequired::SpecificResponse) => {}
-            _ => bail!("Attempted to send a response for an event where one was not required, either due to it's status or the event's responseRequired field.")
-        };
+        if !pricing_response_required(status, event.response_required) {
+            anyhow::bail!("Attempted to send a response for an event where one was not required, either due to it's status or the event's responseRequired field.")
+        }
         let resp = PriceResponse {
             created_date_time: Some(time.into()),
             end_device_lfdi: lfdi,
@@ -658,16 +3733,1327 @@ impl Client {
             subject: event.mrid,
             href: None,
         };
-        self.put_post(
-            event
-                .reply_to()
-                .context("Event does not contain a ReplyTo Field")?
-                .parse()
-                .context("Failed to parse ReplyTo Field")?,
-            &resp,
-            Method::POST,
-            time,
-        )
+        Ok(self
+            .put_post(
+                event
+                    .reply_to()
+                    .context("Event does not contain a ReplyTo Field")?
+                    .parse()
+                    .context("Failed to parse ReplyTo Field")?,
+                &resp,
+                Method::POST,
+                time,
+                None,
+            )
+            .await?)
+    }
+}
+
+/// Whether a Response with the given status is required for a TextMessage, as per Messaging in Table 27
+#[cfg(feature = "messaging")]
+pub(crate) fn msg_response_required(
+    status: ResponseStatus,
+    response_required: Option<ResponseRequired>,
+) -> bool {
+    let Some(rr) = response_required else {
+        return false;
+    };
+    match status {
+        ResponseStatus::EventReceived => rr.contains(ResponseRequired::MessageReceived),
+        ResponseStatus::EventAcknowledge => rr.contains(ResponseRequired::ResponseRequired),
+        ResponseStatus::EventStarted
+        | ResponseStatus::EventCompleted
+        | ResponseStatus::EventSuperseded
+        | ResponseStatus::EventNoDisplay
+        | ResponseStatus::EventAbortedServer
+        | ResponseStatus::EventAbortedProgram
+        | ResponseStatus::EventExpired => rr.contains(ResponseRequired::SpecificResponse),
+        _ => false,
+    }
+}
+
+/// Whether a Response with the given status is required for a TimeTariffInterval, as per Pricing in Table 27
+#[cfg(feature = "pricing")]
+pub(crate) fn pricing_response_required(
+    status: ResponseStatus,
+    response_required: Option<ResponseRequired>,
+) -> bool {
+    let Some(rr) = response_required else {
+        return false;
+    };
+    match status {
+        ResponseStatus::EventReceived => rr.contains(ResponseRequired::MessageReceived),
+        ResponseStatus::EventAcknowledge => rr.contains(ResponseRequired::ResponseRequired),
+        ResponseStatus::EventStarted
+        | ResponseStatus::EventCompleted
+        | ResponseStatus::EventSuperseded
+        | ResponseStatus::EventAbortedServer
+        | ResponseStatus::EventAbortedProgram
+        | ResponseStatus::EventExpired => rr.contains(ResponseRequired::SpecificResponse),
+        _ => false,
+    }
+}
+
+/// Whether a Response with the given status is required for a DERControl, as per the DER column of Table 27
+#[cfg(any(feature = "der", feature = "drlc"))]
+pub(crate) fn der_response_required(
+    status: ResponseStatus,
+    response_required: Option<ResponseRequired>,
+) -> bool {
+    let Some(rr) = response_required else {
+        return false;
+    };
+    match status {
+        ResponseStatus::EventNoDisplay => false,
+        ResponseStatus::EventReceived if rr.contains(ResponseRequired::MessageReceived) => true,
+        ResponseStatus::EventAcknowledge if rr.contains(ResponseRequired::ResponseRequired) => true,
+        _ => rr.contains(ResponseRequired::SpecificResponse),
+    }
+}
+
+/// Whether a Response with the given status is required for an EndDeviceControl, as per the DRLC column of Table 27
+#[cfg(feature = "drlc")]
+pub(crate) fn drlc_response_required(
+    status: ResponseStatus,
+    response_required: Option<ResponseRequired>,
+) -> bool {
+    // The DER & DRLC columns are identical
+    der_response_required(status, response_required)
+}
+
+// Resolve an href from a Link or ListLink into a path relative to the server address.
+// Absolute hrefs must share the scheme & authority of the server address.
+fn resolve_href(base: &str, href: &str) -> Result<String, ClientError> {
+    let uri = match href.parse::<Uri>() {
+        Ok(uri) if uri.scheme().is_some() && uri.authority().is_some() => uri,
+        // Relative to the server root
+        _ if href.starts_with('/') => return Ok(href.to_owned()),
+        _ => return Ok(format!("/{href}")),
+    };
+    let base: Uri = base.parse()?;
+    if base.scheme() != uri.scheme() || base.authority() != uri.authority() {
+        return Err(ClientError::CrossOrigin(href.to_owned()));
+    }
+    Ok(uri
+        .path_and_query()
+        .map_or("/", |pq| pq.as_str())
+        .to_owned())
+}
+
+// Serialize a resource to be sent to the server
+fn serialize_rsrc<R: SEResource>(resource: &R) -> Result<String, ClientError> {
+    serialize(resource).map_err(|err| ClientError::Serialize {
+        resource: R::name().to_owned(),
+        reason: err.to_string(),
+    })
+}
+
+// Normalize a Location header into a path relative to the server address, if it is on the same server.
+fn normalize_location(base: &str, loc: &str) -> String {
+    resolve_href(base, loc).unwrap_or_else(|err| {
+        log::warn!("Client: Received Location header {loc} that could not be normalized: {err}");
+        loc.to_owned()
+    })
+}
+
+#[test]
+fn server_errors_classified() {
+    let classify = |status| match ClientError::from_status(status, None, None) {
+        ClientError::ServerError { retryable, .. } => Some(retryable),
+        _ => None,
+    };
+    assert_eq!(classify(StatusCode::SERVICE_UNAVAILABLE), Some(true));
+    assert_eq!(classify(StatusCode::GATEWAY_TIMEOUT), Some(true));
+    assert_eq!(classify(StatusCode::INTERNAL_SERVER_ERROR), Some(false));
+    assert_eq!(classify(StatusCode::NOT_IMPLEMENTED), Some(false));
+    assert_eq!(classify(StatusCode::UNAUTHORIZED), None);
+}
+
+#[tokio::test]
+async fn http_errors_typed() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    let respond = |status, body: &'static str| {
+        hyper::Response::builder()
+            .status(status)
+            .body(Body::from(body))
+            .unwrap()
+    };
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        respond(StatusCode::NOT_FOUND, ""),
+        respond(StatusCode::BAD_REQUEST, ""),
+        respond(StatusCode::IM_A_TEAPOT, ""),
+        respond(StatusCode::FOUND, ""),
+        respond(StatusCode::OK, "<DeviceCapability><pollRate>x</pollRate>"),
+        respond(
+            StatusCode::BAD_REQUEST,
+            r#"<Error xmlns="urn:ieee:std:2030.5:ns"><reasonCode>1</reasonCode></Error>"#,
+        ),
+        respond(StatusCode::NOT_FOUND, ""),
+    ]);
+    let client = Client::with_transport("http://fake", transport, None);
+    let get = || async { client.get::<DeviceCapability>("/dcap").await.unwrap_err() };
+    assert!(matches!(get().await, ClientError::NotFound { .. }));
+    assert!(matches!(get().await, ClientError::BadRequest { .. }));
+    assert!(matches!(
+        get().await,
+        ClientError::Unexpected {
+            status: StatusCode::IM_A_TEAPOT,
+            ..
+        }
+    ));
+    assert!(matches!(get().await, ClientError::MissingLocationHeader));
+    assert!(matches!(
+        get().await,
+        ClientError::Deserialize { resource, .. } if resource == "DeviceCapability"
+    ));
+    // Requests that send a resource are classified in the same manner
+    let err = client
+        .put("/dcap", &DeviceCapability::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::BadRequest { .. }));
+    assert!(matches!(
+        err.body().and_then(ErrorBody::error).map(|e| e.reason_code),
+        Some(sep2_common::packages::objects::ErrorReason::InvalidRequestValues)
+    ));
+    let err = client.delete("/dcap").await.unwrap_err();
+    assert!(matches!(err, ClientError::NotFound { .. }));
+}
+
+#[tokio::test]
+async fn transport_errors_typed() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    // Nothing listening
+    let client = Client::new("http://127.0.0.1:1336", None, None).unwrap();
+    let err = client.get::<DeviceCapability>("/dcap").await.unwrap_err();
+    assert!(matches!(err, ClientError::Transport(e) if e.is_connect()));
+    // A custom transport with no responses left
+    let client =
+        Client::with_transport("http://fake", Arc::new(ScriptedTransport::default()), None);
+    let err = client.get::<DeviceCapability>("/dcap").await.unwrap_err();
+    assert!(matches!(err, ClientError::CustomTransport(_)));
+    let err = client.get::<DeviceCapability>("/ dcap").await.unwrap_err();
+    assert!(matches!(err, ClientError::InvalidRequest(_)));
+}
+
+#[test]
+fn poll_rate_clamped() {
+    assert_eq!(clamp_poll_rate(Uint32(0), Uint32(1)), Uint32(1));
+    assert_eq!(clamp_poll_rate(Uint32(30), Uint32(60)), Uint32(60));
+    assert_eq!(clamp_poll_rate(Uint32(900), Uint32(60)), Uint32(900));
+}
+
+#[test]
+fn ignored_elements_detected() {
+    let received = r#"<?xml version="1.0"?><Time href="/tm" xmlns="urn:ieee:std:2030.5:ns"><currentTime>1</currentTime><sep:futureElement>2</sep:futureElement><quality>7</quality><another/></Time>"#;
+    let reserialized = r#"<Time href="/tm" xmlns="urn:ieee:std:2030.5:ns"><currentTime>1</currentTime><quality>7</quality></Time>"#;
+    assert_eq!(
+        ignored_elements(received, reserialized),
+        vec!["futureElement".to_owned(), "another".to_owned()]
+    );
+    assert!(ignored_elements(reserialized, reserialized).is_empty());
+}
+
+#[cfg(all(feature = "der", feature = "pricing"))]
+#[test]
+fn response_required_per_table_27() {
+    let rr = Some(ResponseRequired::MessageReceived);
+    assert!(der_response_required(ResponseStatus::EventReceived, rr));
+    assert!(!der_response_required(ResponseStatus::EventStarted, rr));
+    assert!(!der_response_required(ResponseStatus::EventReceived, None));
+    let rr = Some(ResponseRequired::SpecificResponse);
+    assert!(der_response_required(ResponseStatus::EventOptOut, rr));
+    assert!(!der_response_required(ResponseStatus::EventNoDisplay, rr));
+    assert!(!pricing_response_required(
+        ResponseStatus::EventNoDisplay,
+        rr
+    ));
+    assert!(pricing_response_required(ResponseStatus::EventExpired, rr));
+    assert!(!pricing_response_required(ResponseStatus::EventOptOut, rr));
+}
+
+#[test]
+fn href_resolution() {
+    let base = "https://127.0.0.1:1337";
+    assert_eq!(resolve_href(base, "/edev/3").unwrap(), "/edev/3");
+    assert_eq!(resolve_href(base, "edev/3").unwrap(), "/edev/3");
+    assert_eq!(
+        resolve_href(base, "https://127.0.0.1:1337/edev/3?l=1").unwrap(),
+        "/edev/3?l=1"
+    );
+    let err = resolve_href(base, "https://127.0.0.1:1338/edev/3").unwrap_err();
+    assert!(matches!(err, ClientError::CrossOrigin(_)));
+    assert!(resolve_href(base, "http://127.0.0.1:1337/edev/3").is_err());
+}
+
+#[test]
+fn location_normalized() {
+    let base = "https://127.0.0.1:1337";
+    assert_eq!(normalize_location(base, "edev/3"), "/edev/3");
+    assert_eq!(
+        normalize_location(base, "https://127.0.0.1:1337/edev/3"),
+        "/edev/3"
+    );
+    assert_eq!(
+        normalize_location(base, "https://127.0.0.1:1338/edev/3"),
+        "https://127.0.0.1:1338/edev/3"
+    );
+}
+
+// Responds to every request with the given status and body
+#[cfg(test)]
+struct FakeTransport(StatusCode, &'static str);
+
+#[cfg(test)]
+impl Transport for FakeTransport {
+    fn request(&self, _: Request<Body>) -> TransportFuture {
+        let res = hyper::Response::builder()
+            .status(self.0)
+            .body(Body::from(self.1))
+            .map_err(Into::into);
+        Box::pin(async move { res })
+    }
+}
+
+#[tokio::test]
+async fn fake_transport() {
+    use sep2_common::packages::identification::Resource;
+    let xml =
+        r#"<DeviceCapability href="/dcap" xmlns="urn:ieee:std:2030.5:ns"></DeviceCapability>"#;
+    let client = Client::with_transport("http://fake", FakeTransport(StatusCode::OK, xml), None);
+    let dcap: sep2_common::packages::dcap::DeviceCapability = client.get("/dcap").await.unwrap();
+    assert_eq!(dcap.href.as_deref(), Some("/dcap"));
+    assert!(format!("{client:?}").contains("Custom"));
+
+    let client = Client::with_transport(
+        "http://fake",
+        FakeTransport(StatusCode::SERVICE_UNAVAILABLE, ""),
+        None,
+    );
+    let err = client.get::<Resource>("/dcap").await.unwrap_err();
+    assert!(matches!(
+        err,
+        ClientError::ServerError {
+            retryable: true,
+            ..
+        }
+    ));
+}
+
+// Responds to each request with the next of the given responses, recording the requests received
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct ScriptedTransport {
+    pub(crate) responses: std::sync::Mutex<std::collections::VecDeque<hyper::Response<Body>>>,
+    pub(crate) requests: std::sync::Mutex<Vec<Request<Body>>>,
+}
+
+#[cfg(test)]
+impl Transport for Arc<ScriptedTransport> {
+    fn request(&self, req: Request<Body>) -> TransportFuture {
+        self.requests.lock().unwrap().push(req);
+        let res = self
+            .responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .context("No more responses");
+        Box::pin(async move { res })
+    }
+}
+
+#[tokio::test]
+async fn conditional_polls() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    let xml =
+        r#"<DeviceCapability href="/dcap" xmlns="urn:ieee:std:2030.5:ns"></DeviceCapability>"#;
+    let modified = "Wed, 21 Oct 2015 07:28:00 GMT";
+    let transport = Arc::new(ScriptedTransport::default());
+    let ok = || {
+        hyper::Response::builder()
+            .header(LAST_MODIFIED, modified)
+            .body(Body::from(xml))
+            .unwrap()
+    };
+    let not_modified = || {
+        hyper::Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap()
+    };
+    transport
+        .responses
+        .lock()
+        .unwrap()
+        .extend([not_modified(), ok(), not_modified()]);
+    let client = Client::with_transport(
+        "http://fake",
+        transport.clone(),
+        Some(Duration::from_millis(10)),
+    );
+    let since = SEPTime::from(httpdate::parse_http_date(modified).unwrap());
+    let res = client
+        .get_if_modified_since::<DeviceCapability>("/dcap", since)
+        .await
+        .unwrap();
+    assert!(res.is_none());
+
+    // The first poll is unconditional, and the second uses the Last-Modified time of the first
+    let polled = Arc::new(AtomicUsize::new(0));
+    client
+        .start_poll_if_modified("/dcap", Some(Uint32(1)), {
+            let polled = polled.clone();
+            move |_: DeviceCapability| {
+                let polled = polled.clone();
+                async move {
+                    polled.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        })
+        .await;
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    assert_eq!(polled.load(Ordering::Relaxed), 1);
+    let requests = transport.requests.lock().unwrap();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0].headers()[IF_MODIFIED_SINCE], modified);
+    assert!(requests[1].headers().get(IF_MODIFIED_SINCE).is_none());
+    assert_eq!(requests[2].headers()[IF_MODIFIED_SINCE], modified);
+}
+
+#[tokio::test]
+async fn lists_paginated() {
+    let list = |body: &'static str| hyper::Response::builder().body(Body::from(body)).unwrap();
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        list(
+            r#"<EndDeviceList xmlns="urn:ieee:std:2030.5:ns" href="/edev" all="3" results="2"><EndDevice href="/edev/1"><sFDI>1</sFDI><changedTime>0</changedTime></EndDevice><EndDevice href="/edev/2"><sFDI>2</sFDI><changedTime>0</changedTime></EndDevice></EndDeviceList>"#,
+        ),
+        list(
+            r#"<EndDeviceList xmlns="urn:ieee:std:2030.5:ns" href="/edev" all="3" results="1"><EndDevice href="/edev/3"><sFDI>3</sFDI><changedTime>0</changedTime></EndDevice></EndDeviceList>"#,
+        ),
+    ]);
+    // A server that ignores the start index, and always returns the same page
+    for _ in 0..3 {
+        transport.responses.lock().unwrap().push_back(list(
+            r#"<EndDeviceList xmlns="urn:ieee:std:2030.5:ns" href="/edev" all="100" results="1"><EndDevice href="/edev/1"><sFDI>1</sFDI><changedTime>0</changedTime></EndDevice></EndDeviceList>"#,
+        ));
+    }
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    let items = client.get_all::<EndDeviceList>("/edev").await.unwrap();
+    let hrefs: Vec<_> = items
+        .iter()
+        .filter_map(|edev| edev.href.as_deref())
+        .collect();
+    assert_eq!(hrefs, vec!["/edev/1", "/edev/2", "/edev/3"]);
+    assert_eq!(
+        transport.requests.lock().unwrap()[1].uri().query(),
+        Some("s=2&l=255")
+    );
+
+    client.set_max_list_requests(3);
+    let err = client.get_all::<EndDeviceList>("/edev").await.unwrap_err();
+    assert!(matches!(err, ClientError::TooManyListRequests));
+    assert_eq!(transport.requests.lock().unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn update_with_if_match() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    let xml =
+        r#"<DeviceCapability href="/dcap" xmlns="urn:ieee:std:2030.5:ns"></DeviceCapability>"#;
+    let get = || {
+        hyper::Response::builder()
+            .header(ETAG, "\"v1\"")
+            .body(Body::from(xml))
+            .unwrap()
+    };
+    let put = |status| {
+        hyper::Response::builder()
+            .status(status)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        get(),
+        put(StatusCode::NO_CONTENT),
+        get(),
+        put(StatusCode::PRECONDITION_FAILED),
+    ]);
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    let res = client
+        .update_with("/dcap", |dcap: &mut DeviceCapability| {
+            dcap.poll_rate = Some(Uint32(60))
+        })
+        .await
+        .unwrap();
+    assert!(matches!(res, SEPResponse::NoContent));
+    let Err(err) = client
+        .update_with("/dcap", |_: &mut DeviceCapability| ())
+        .await
+    else {
+        panic!("Expected a failed precondition")
+    };
+    assert!(matches!(err, ClientError::PreconditionFailed { .. }));
+
+    let requests = transport.requests.lock().unwrap();
+    assert_eq!(requests.len(), 4);
+    assert_eq!(requests[1].method(), Method::PUT);
+    assert_eq!(requests[1].headers()[IF_MATCH], "\"v1\"");
+    assert!(!requests[0].headers().contains_key(IF_MATCH));
+}
+
+#[tokio::test]
+async fn idempotent_post_not_duplicated() {
+    use sep2_common::packages::edev::EndDeviceList;
+    let status = |status| {
+        hyper::Response::builder()
+            .status(status)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let list = |xml: &'static str| hyper::Response::builder().body(Body::from(xml)).unwrap();
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        // The first POST reaches the server, but times out at a gateway
+        status(StatusCode::GATEWAY_TIMEOUT),
+        list(
+            r#"<EndDeviceList xmlns="urn:ieee:std:2030.5:ns" href="/edev" all="1" results="1"><EndDevice href="/edev/4"><sFDI>1</sFDI><changedTime>0</changedTime></EndDevice></EndDeviceList>"#,
+        ),
+        status(StatusCode::SERVICE_UNAVAILABLE),
+        list(
+            r#"<EndDeviceList xmlns="urn:ieee:std:2030.5:ns" href="/edev" all="2" results="2"><EndDevice href="/edev/4"><sFDI>1</sFDI><changedTime>0</changedTime></EndDevice><EndDevice href="/edev/5"><sFDI>2</sFDI><changedTime>0</changedTime></EndDevice></EndDeviceList>"#,
+        ),
+    ]);
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    let edev = EndDevice {
+        sfdi: SFDIType::new(2).unwrap(),
+        ..Default::default()
+    };
+    let ctx = RequestCtx {
+        retries: 3,
+        ..Default::default()
+    };
+    let res = client
+        .post_idempotent::<EndDeviceList, _>("/edev", &edev, &ctx, |other| other.sfdi == edev.sfdi)
+        .await
+        .unwrap();
+    assert!(matches!(res, SEPResponse::Created(Some(href)) if href == "/edev/5"));
+
+    let requests = transport.requests.lock().unwrap();
+    let methods: Vec<_> = requests.iter().map(|req| req.method().clone()).collect();
+    assert_eq!(
+        methods,
+        vec![Method::POST, Method::GET, Method::POST, Method::GET]
+    );
+}
+
+#[tokio::test]
+async fn host_overridden() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().push_back(
+        hyper::Response::builder()
+            .body(Body::from(
+                r#"<DeviceCapability href="/dcap" xmlns="urn:ieee:std:2030.5:ns"></DeviceCapability>"#,
+            ))
+            .unwrap(),
+    );
+    let client = Client::from_inner(
+        "http://192.168.1.2",
+        ClientInner::Custom(Arc::new(transport.clone())),
+        Arc::default(),
+        None,
+        None,
+        Some(HeaderValue::from_static("gateway.example.com")),
+        None,
+    );
+    client.get::<DeviceCapability>("/dcap").await.unwrap();
+    let requests = transport.requests.lock().unwrap();
+    assert_eq!(requests[0].headers()[HOST], "gateway.example.com");
+    assert_eq!(requests[0].uri(), "http://192.168.1.2/dcap");
+
+    assert!(Client::builder("http://192.168.1.2")
+        .with_host("invalid\nhost")
+        .build()
+        .is_err());
+}
+
+#[tokio::test]
+async fn function_set_polls() {
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().push_back(
+        hyper::Response::builder()
+            .body(Body::from(
+                r#"<EndDeviceList xmlns="urn:ieee:std:2030.5:ns" href="/edev" all="0" results="0"></EndDeviceList>"#,
+            ))
+            .unwrap(),
+    );
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let link = ListLink {
+        href: "edev".to_owned(),
+        ..Default::default()
+    };
+    client
+        .poll_end_device_list(&link, None, move |list: EndDeviceList| {
+            let tx = tx.clone();
+            async move { tx.send(list.href).unwrap() }
+        })
+        .await
+        .unwrap();
+    client.force_polls().await;
+    assert_eq!(rx.recv().await, Some(Some("/edev".to_owned())));
+    assert_eq!(
+        transport.requests.lock().unwrap()[0].uri(),
+        "http://fake/edev"
+    );
+
+    let link = ListLink {
+        href: "https://elsewhere/edev".to_owned(),
+        ..Default::default()
+    };
+    assert!(client
+        .poll_end_device_list(&link, None, |_| async {})
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn redirects_followed() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    let xml =
+        r#"<DeviceCapability href="/dcap" xmlns="urn:ieee:std:2030.5:ns"></DeviceCapability>"#;
+    let redirect = |to: &str| {
+        hyper::Response::builder()
+            .status(StatusCode::MOVED_PERMANENTLY)
+            .header(LOCATION, to)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        redirect("/a"),
+        redirect("http://fake/b"),
+        hyper::Response::new(Body::from(xml)),
+        // Loop
+        redirect("/a"),
+        redirect("/dcap"),
+        // Max hops
+        redirect("/a"),
+        redirect("/b"),
+    ]);
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    client.get::<DeviceCapability>("/dcap").await.unwrap();
+    let paths: Vec<_> = transport
+        .requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|r| r.uri().path().to_owned())
+        .collect();
+    assert_eq!(paths, ["/dcap", "/a", "/b"]);
+
+    let err = client.get::<DeviceCapability>("/dcap").await.unwrap_err();
+    assert!(matches!(err, ClientError::TooManyRedirects));
+    client.set_max_redirects(1);
+    let err = client.get::<DeviceCapability>("/dcap").await.unwrap_err();
+    assert!(matches!(err, ClientError::TooManyRedirects));
+    assert_eq!(transport.requests.lock().unwrap().len(), 7);
+}
+
+#[tokio::test]
+async fn default_poll_rate() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    let client = Client::with_transport("http://fake", FakeTransport(StatusCode::OK, ""), None);
+    assert_eq!(client.default_poll_rate(), Uint32(900));
+    client.clone().set_default_poll_rate(Uint32(300));
+    assert_eq!(client.default_poll_rate(), Uint32(300));
+
+    // Polls started without a rate use the one set by the builder
+    let client = Client::builder("http://fake")
+        .with_default_poll_rate(Uint32(60))
+        .build()
+        .unwrap();
+    assert_eq!(client.default_poll_rate(), Uint32(60));
+    let handle = client
+        .start_poll("/dcap", None, |_: DeviceCapability| async {})
+        .await;
+    let until = handle.time_until_next().unwrap();
+    assert!(until > Duration::from_secs(50) && until <= Duration::from_secs(60));
+}
+
+#[tokio::test]
+async fn advertised_rates() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    let client = Client::with_transport("http://fake", FakeTransport(StatusCode::OK, ""), None);
+    assert_eq!(
+        client.use_advertised_poll_rate(&DeviceCapability::default()),
+        Uint32(900)
+    );
+    let dcap = DeviceCapability {
+        poll_rate: Some(Uint32(60)),
+        ..Default::default()
+    };
+    assert_eq!(client.use_advertised_poll_rate(&dcap), Uint32(60));
+    assert_eq!(client.default_poll_rate(), Uint32(60));
+    let edev = EndDevice {
+        post_rate: Some(Uint32(300)),
+        ..Default::default()
+    };
+    assert_eq!(client.use_advertised_post_rate(&edev), Uint32(300));
+    assert_eq!(client.default_post_rate(), Uint32(300));
+}
+
+#[tokio::test]
+async fn scheduled_report() {
+    use sep2_common::packages::identification::Resource;
+    for status in [StatusCode::CREATED, StatusCode::BAD_REQUEST] {
+        let tickrate = Some(Duration::from_millis(10));
+        let client = Client::with_transport("http://fake", FakeTransport(status, ""), tickrate);
+        client
+            .start_report("/report", Some(Uint32(60)), Resource::default)
+            .await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let stats = client.clone();
+        client.shutdown().await;
+        // Neither success nor rejection are retried
+        assert_eq!(stats.connection_stats().requests, 1);
+    }
+}
+
+#[cfg(feature = "der")]
+#[tokio::test]
+async fn der_reports() {
+    use sep2_common::packages::links::{DERAvailabilityLink, DERStatusLink};
+    let client =
+        Client::with_transport("http://fake", FakeTransport(StatusCode::CREATED, ""), None);
+    let mut der = DER::default();
+    assert!(client
+        .start_der_status_report(&der, None, DERStatus::default)
+        .await
+        .is_err());
+    der.der_status_link = Some(DERStatusLink {
+        href: "/der/1/ders".to_owned(),
+    });
+    der.der_availability_link = Some(DERAvailabilityLink {
+        href: "/der/1/dera".to_owned(),
+    });
+    let status = client
+        .start_der_status_report(&der, Some(Uint32(60)), DERStatus::default)
+        .await
+        .unwrap();
+    let availability = client
+        .start_der_availability_report(&der, Some(Uint32(60)), DERAvailability::default)
+        .await
+        .unwrap();
+    assert!(status.next_fire().is_some());
+    assert!(availability.next_fire().is_some());
+    client.cancel_polls().await;
+    assert!(status.next_fire().is_none());
+}
+
+#[tokio::test]
+async fn force_polls_once() {
+    use sep2_common::packages::identification::Resource;
+    let client = Client::with_transport("http://fake", FakeTransport(StatusCode::OK, ""), None);
+    client
+        .start_poll("/a", Some(Uint32(60)), |_: Resource| async {})
+        .await;
+    client
+        .start_poll("/b", Some(Uint32(60)), |_: Resource| async {})
+        .await;
+    // Previously, force_polls never returned, as each rescheduled poll was popped again
+    tokio::time::timeout(Duration::from_secs(1), client.force_polls())
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(client.connection_stats().requests, 2);
+    assert_eq!(client.polls.lock().await.len(), 2);
+}
+
+#[tokio::test]
+async fn poll_handle_next_fire() {
+    use sep2_common::packages::identification::Resource;
+    let client = Client::with_transport("http://fake", FakeTransport(StatusCode::OK, ""), None);
+    let before = Instant::now();
+    let handle = client
+        .start_poll("/poll", Some(Uint32(60)), |_: Resource| async {})
+        .await;
+    let next = handle.next_fire().unwrap();
+    assert!(next >= before + Duration::from_secs(60));
+    assert!(handle.time_until_next().unwrap() <= Duration::from_secs(60));
+
+    // Forcing polls resets the next fire time
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    client.force_polls().await;
+    assert!(handle.next_fire().unwrap() > next);
+
+    client.cancel_polls().await;
+    assert!(handle.next_fire().is_none());
+    assert!(handle.time_until_next().is_none());
+}
+
+#[tokio::test]
+async fn poll_ref_callback() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    use std::sync::atomic::AtomicBool;
+    let xml =
+        r#"<DeviceCapability href="/dcap" xmlns="urn:ieee:std:2030.5:ns"></DeviceCapability>"#;
+    let client = Client::with_transport("http://fake", FakeTransport(StatusCode::OK, xml), None);
+    let called = Arc::new(AtomicBool::new(false));
+    client
+        .start_poll_ref("/dcap", None, {
+            let called = called.clone();
+            move |dcap: &DeviceCapability| {
+                assert_eq!(dcap.href.as_deref(), Some("/dcap"));
+                called.store(true, Ordering::SeqCst);
+            }
+        })
+        .await;
+    client.force_polls().await;
+    client.clone().shutdown().await;
+    assert!(called.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn keep_alive_probe() {
+    let tickrate = Some(Duration::from_millis(10));
+    let client = Client::with_transport("http://fake", FakeTransport(StatusCode::OK, ""), tickrate);
+    client.start_keep_alive(Duration::from_millis(30)).await;
+    tokio::time::sleep(Duration::from_millis(110)).await;
+    let stats = client.clone();
+    client.shutdown().await;
+    assert!(stats.connection_stats().requests >= 2);
+}
+
+#[tokio::test]
+async fn cert_expiry_monitored() {
+    let tickrate = Some(Duration::from_millis(10));
+    let mut client =
+        Client::with_transport("http://fake", FakeTransport(StatusCode::OK, ""), tickrate);
+    assert!(client
+        .start_cert_expiry_monitor(Duration::ZERO, |_| ())
         .await
+        .is_err());
+
+    let expiry = current_time_with_offset() + 60 * 60;
+    client.cert_expiry = Some(expiry);
+    let warnings = Arc::new(AtomicU32::new(0));
+    let day = Duration::from_secs(60 * 60 * 24);
+    for warn_before in [day, Duration::from_secs(60)] {
+        let warnings = warnings.clone();
+        client
+            .start_cert_expiry_monitor(warn_before, move |not_after| {
+                assert!(not_after == expiry);
+                warnings.fetch_add(1, Ordering::SeqCst);
+            })
+            .await
+            .unwrap();
+    }
+    // The first check is performed before the monitor is started
+    assert_eq!(warnings.load(Ordering::SeqCst), 1);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    client.force_polls().await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    client.shutdown().await;
+    // Only the monitor warning a day before expiry is called, and only once
+    assert_eq!(warnings.load(Ordering::SeqCst), 1);
+}
+
+#[cfg(feature = "der")]
+#[tokio::test]
+async fn poll_changes() {
+    use sep2_common::packages::{
+        der::{DERProgram, DERProgramList},
+        primitives::{HexBinary128, Uint16},
+    };
+    type Events = Arc<std::sync::Mutex<Vec<(Vec<u128>, Vec<u128>)>>>;
+    struct Recorder(Events);
+    impl ListChangeCallback<DERProgram> for Recorder {
+        async fn changed(&self, items: Vec<DERProgram>) {
+            let mrids = items.iter().map(|p| p.mrid.0).collect();
+            self.0.lock().unwrap().push((mrids, vec![]));
+        }
+        async fn removed(&self, mrids: Vec<MRIDType>) {
+            let mrids = mrids.iter().map(|m| m.0).collect();
+            self.0.lock().unwrap().push((vec![], mrids));
+        }
+    }
+    let list = |programs: &[(u128, u16)]| {
+        let mut list = DERProgramList::default();
+        for &(mrid, version) in programs {
+            list.add(DERProgram {
+                mrid: HexBinary128(mrid),
+                version: Some(Uint16(version)),
+                ..Default::default()
+            });
+        }
+        hyper::Response::new(Body::from(serialize(&list).unwrap()))
+    };
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        list(&[(1, 0), (2, 0)]),
+        // Unchanged
+        list(&[(1, 0), (2, 0)]),
+        // 1 updated, 2 removed, 3 added
+        list(&[(1, 1), (3, 0)]),
+    ]);
+    let client = Client::with_transport("http://fake", transport, None);
+    let events: Events = Arc::default();
+    client
+        .start_poll_changes::<DERProgramList>("/derp", None, Recorder(events.clone()))
+        .await;
+    for _ in 0..3 {
+        client.force_polls().await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let mut events = events.lock().unwrap().clone();
+    events.iter_mut().for_each(|(c, _)| c.sort());
+    assert_eq!(
+        events,
+        [
+            (vec![1, 2], vec![]),
+            (vec![1, 3], vec![]),
+            (vec![], vec![2])
+        ]
+    );
+}
+
+#[tokio::test]
+async fn error_body_captured() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    let client = Client::with_transport(
+        "http://fake",
+        FakeTransport(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "<html>Internal Error</html>",
+        ),
+        None,
+    );
+    let Err(err) = client.get::<DeviceCapability>("/dcap").await else {
+        panic!("Expected an error")
+    };
+    assert!(matches!(err, ClientError::ServerError { .. }));
+    assert_eq!(
+        err.body(),
+        Some(&ErrorBody {
+            body: "<html>Internal Error</html>".to_owned(),
+            truncated: false
+        })
+    );
+    // The body is displayed as the source of the error
+    assert!(format!("{:#}", anyhow::Error::from(err)).ends_with("<html>Internal Error</html>"));
+
+    client.set_error_body_limit(6);
+    let Err(err) = client.delete("/dcap").await else {
+        panic!("Expected an error")
+    };
+    let body = err.body().unwrap();
+    assert_eq!(body.body, "<html>");
+    assert!(body.truncated);
+
+    client.set_error_body_limit(0);
+    let Err(err) = client.delete("/dcap").await else {
+        panic!("Expected an error")
+    };
+    assert!(err.body().is_none());
+}
+
+#[tokio::test]
+async fn schema_profile_applied() {
+    let transport = Arc::new(ScriptedTransport::default());
+    let no_content = || {
+        hyper::Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Body::empty())
+            .unwrap()
+    };
+    transport
+        .responses
+        .lock()
+        .unwrap()
+        .extend([no_content(), no_content(), no_content()]);
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    let edev = EndDevice {
+        enabled: Some(true),
+        ..Default::default()
+    };
+    let last_body = || async {
+        let req = transport.requests.lock().unwrap().pop().unwrap();
+        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    };
+
+    client.post("/edev", &edev).await.unwrap();
+    assert!(last_body().await.contains("enabled"));
+
+    client.set_schema_profile(Some(
+        SchemaProfile::new("test").omit::<EndDevice>("enabled"),
+    ));
+    client.post("/edev", &edev).await.unwrap();
+    let posted = last_body().await;
+    assert!(!posted.contains("enabled"));
+    // Streamed resources are serialized identically
+    client.post_streaming("/edev", edev).await.unwrap();
+    assert_eq!(last_body().await, posted);
+}
+
+#[tokio::test]
+async fn lenient_namespaces_deserialized() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    const DCAP: &str = r#"<sep:DeviceCapability href="/dcap"><sep:EndDeviceListLink all="1" href="/edev"/></sep:DeviceCapability>"#;
+    let client = Client::with_transport("http://fake", FakeTransport(StatusCode::OK, DCAP), None);
+    assert!(client.get::<DeviceCapability>("/dcap").await.is_err());
+    client.set_lenient_namespaces(true);
+    let dcap: DeviceCapability = client.get("/dcap").await.unwrap();
+    assert_eq!(dcap.end_device_list_link.unwrap().href, "/edev");
+    // The body is still returned as it was received
+    let (_, xml) = client.get_raw::<DeviceCapability>("/dcap").await.unwrap();
+    assert_eq!(xml, DCAP);
+}
+
+#[tokio::test]
+async fn panicked_polls_restarted() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    const DCAP: &str =
+        r#"<DeviceCapability href="/dcap" xmlns="urn:ieee:std:2030.5:ns"></DeviceCapability>"#;
+    // A callback that panics on it's first run only
+    let panics_once = |polled: Arc<AtomicUsize>| {
+        move |_: DeviceCapability| {
+            let polled = polled.clone();
+            async move {
+                if polled.fetch_add(1, Ordering::Relaxed) == 0 {
+                    panic!("First poll");
+                }
+            }
+        }
+    };
+
+    let client = Client::with_transport(
+        "http://fake",
+        FakeTransport(StatusCode::OK, DCAP),
+        Some(Duration::from_millis(10)),
+    );
+    client.set_poll_watchdog(1, Duration::from_millis(100));
+    let polled = Arc::new(AtomicUsize::new(0));
+    let handle = client
+        .start_poll("/dcap", Some(Uint32(1)), panics_once(polled.clone()))
+        .await;
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    assert_eq!(polled.load(Ordering::Relaxed), 2);
+    assert!(handle.next_fire().is_some());
+
+    // Without the watchdog, the poll is stopped
+    let client = Client::with_transport(
+        "http://fake",
+        FakeTransport(StatusCode::OK, DCAP),
+        Some(Duration::from_millis(10)),
+    );
+    let polled = Arc::new(AtomicUsize::new(0));
+    let handle = client
+        .start_poll("/dcap", Some(Uint32(1)), panics_once(polled.clone()))
+        .await;
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    assert_eq!(polled.load(Ordering::Relaxed), 1);
+    assert!(handle.next_fire().is_none());
+}
+
+#[tokio::test]
+async fn path_rules_checked() {
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        hyper::Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Body::empty())
+            .unwrap(),
+        hyper::Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Body::empty())
+            .unwrap(),
+    ]);
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    let edev = EndDevice::default();
+
+    // Unexpected paths are only logged by default
+    client.set_path_rules(Some(PathRules::new().allow::<EndDevice>("/edev")));
+    client.put("/mup/1", &edev).await.unwrap();
+
+    client.set_path_rules(Some(
+        PathRules::new().allow::<EndDevice>("/edev").strict(true),
+    ));
+    client.post("/edev", &edev).await.unwrap();
+    let err = client.put("/mup/1", &edev).await.unwrap_err();
+    assert!(matches!(
+        err,
+        ClientError::UnexpectedPath { resource, path } if resource == "EndDevice" && path == "/mup/1"
+    ));
+    // Rejected resources are never sent
+    assert_eq!(transport.requests.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn poll_backoff_widens() {
+    use std::sync::atomic::AtomicBool;
+    // Run the job to completion, returning when it ran
+    async fn run(job: &mut PollJob) -> Instant {
+        let tasks = TaskTracker::new();
+        job.execute(&tasks, None, usize::MAX).await;
+        tasks.close();
+        tasks.wait().await;
+        job.last_run.unwrap()
+    }
+    let succeed = Arc::new(AtomicBool::new(false));
+    let handler: PollHandler = Box::new({
+        let succeed = succeed.clone();
+        move || {
+            let succeed = succeed.load(Ordering::Relaxed);
+            Box::pin(async move { succeed })
+        }
+    });
+    let interval = Duration::from_secs(10);
+    let (mut job, _handle) = PollJob::new(handler, interval, Instant::now());
+    let max = Some(Duration::from_secs(60));
+
+    let last = run(&mut job).await;
+    assert_eq!(job.backoff_until(max), Some(last + interval * 2));
+    assert_eq!(job.backoff_until(None), None);
+    let last = run(&mut job).await;
+    assert_eq!(job.backoff_until(max), Some(last + interval * 4));
+    // Capped at the maximum interval
+    let last = run(&mut job).await;
+    assert_eq!(job.backoff_until(max), Some(last + Duration::from_secs(60)));
+    // Reset on success
+    succeed.store(true, Ordering::Relaxed);
+    run(&mut job).await;
+    assert_eq!(job.backoff_until(max), None);
+}
+
+#[tokio::test]
+async fn concurrent_polls_limited() {
+    let running = Arc::new(AtomicU32::new(0));
+    let max_running = Arc::new(AtomicU32::new(0));
+    let permits = Some(Arc::new(Semaphore::new(2)));
+    let tasks = TaskTracker::new();
+    for _ in 0..5 {
+        let handler: PollHandler = Box::new({
+            let running = running.clone();
+            let max_running = max_running.clone();
+            move || {
+                let running = running.clone();
+                let max_running = max_running.clone();
+                Box::pin(async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    true
+                })
+            }
+        });
+        let (mut job, _) = PollJob::new(handler, Duration::from_secs(60), Instant::now());
+        job.execute(&tasks, permits.clone(), usize::MAX).await;
+    }
+    tasks.close();
+    tasks.wait().await;
+    assert_eq!(max_running.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn overlapping_polls_skipped() {
+    let runs = Arc::new(AtomicU32::new(0));
+    let release = Arc::new(tokio::sync::Notify::new());
+    let handler: PollHandler = Box::new({
+        let runs = runs.clone();
+        let release = release.clone();
+        move || {
+            let runs = runs.clone();
+            let release = release.clone();
+            Box::pin(async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                release.notified().await;
+                true
+            })
+        }
+    });
+    let tasks = TaskTracker::new();
+    let (mut job, _) = PollJob::new(handler, Duration::from_secs(60), Instant::now());
+    // At most two runs in progress
+    for _ in 0..4 {
+        job.execute(&tasks, None, 2).await;
     }
+    tokio::task::yield_now().await;
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+    assert_eq!(job.running.load(Ordering::SeqCst), 2);
+    release.notify_waiters();
+    tasks.close();
+    tasks.wait().await;
+    assert_eq!(job.running.load(Ordering::SeqCst), 0);
+    // Skipped runs are not retried, the job runs again once the previous runs complete
+    let tasks = TaskTracker::new();
+    job.execute(&tasks, None, 1).await;
+    tokio::task::yield_now().await;
+    release.notify_waiters();
+    tasks.close();
+    tasks.wait().await;
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn retry_after_honoured() {
+    use sep2_common::packages::{dcap::DeviceCapability, identification::Resource};
+    let respond = |status, retry_after: Option<&str>, body: &'static str| {
+        let mut res = hyper::Response::builder().status(status);
+        if let Some(retry_after) = retry_after {
+            res = res.header(RETRY_AFTER, retry_after);
+        }
+        res.body(Body::from(body)).unwrap()
+    };
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        respond(StatusCode::TOO_MANY_REQUESTS, Some("120"), ""),
+        respond(
+            StatusCode::BAD_REQUEST,
+            None,
+            r#"<Error xmlns="urn:ieee:std:2030.5:ns"><maxRetryDuration>30</maxRetryDuration><reasonCode>4</reasonCode></Error>"#,
+        ),
+        // Only 429 & 503 responses carry a Retry-After hint
+        respond(StatusCode::INTERNAL_SERVER_ERROR, Some("120"), ""),
+        respond(StatusCode::SERVICE_UNAVAILABLE, Some("600"), ""),
+    ]);
+    let client = Client::with_transport(
+        "http://fake",
+        transport.clone(),
+        Some(Duration::from_millis(10)),
+    );
+    let retry = || async {
+        let err = client.get::<DeviceCapability>("/dcap").await.unwrap_err();
+        err.retry_after()
+    };
+    assert_eq!(retry().await, Some(Duration::from_secs(120)));
+    assert_eq!(retry().await, Some(Duration::from_secs(30)));
+    assert_eq!(retry().await, None);
+
+    // Once next due, the poll is deferred until the hint has elapsed, rather than running at it's interval
+    let handle = client
+        .start_poll("/poll", Some(Uint32(1)), |_: Resource| async {})
+        .await;
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    assert_eq!(transport.requests.lock().unwrap().len(), 4);
+    assert!(handle.time_until_next().unwrap() > Duration::from_secs(500));
+}
+
+#[tokio::test]
+async fn retry_after_http_date() {
+    use sep2_common::packages::{dcap::DeviceCapability, identification::Resource};
+    // Dates are given by the server's clock, which may be offset from the system time
+    let date = |secs| httpdate::fmt_http_date((current_time_with_offset() + secs).into());
+    let respond = |retry_after: String, body: String| {
+        hyper::Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .header(RETRY_AFTER, retry_after)
+            .body(Body::from(body))
+            .unwrap()
+    };
+    let error = |secs: u32| {
+        format!(
+            r#"<Error xmlns="urn:ieee:std:2030.5:ns"><maxRetryDuration>{secs}</maxRetryDuration><reasonCode>0</reasonCode></Error>"#
+        )
+    };
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        respond(date(300), String::new()),
+        // The longer of the header & maxRetryDuration is used
+        respond(date(300), error(30)),
+        respond(date(300), error(600)),
+    ]);
+    let client = Client::with_transport(
+        "http://fake",
+        transport.clone(),
+        Some(Duration::from_millis(10)),
+    );
+    let retry = || async {
+        let err = client.get::<DeviceCapability>("/dcap").await.unwrap_err();
+        err.retry_after().unwrap()
+    };
+    // HTTP-dates have a resolution of one second
+    let hint = retry().await;
+    assert!(hint > Duration::from_secs(298) && hint <= Duration::from_secs(300));
+    let hint = retry().await;
+    assert!(hint > Duration::from_secs(298) && hint <= Duration::from_secs(300));
+
+    let before = Instant::now();
+    let handle = client
+        .start_poll("/poll", Some(Uint32(1)), |_: Resource| async {})
+        .await;
+    // Once next due, the poll is deferred until the hint has elapsed
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    assert_eq!(transport.requests.lock().unwrap().len(), 3);
+    let next = handle.next_fire().unwrap();
+    assert!(next >= before + Duration::from_secs(600));
+    assert!(next <= Instant::now() + Duration::from_secs(600));
+}
+
+#[tokio::test]
+async fn invalid_location_header() {
+    use sep2_common::packages::edev::EndDevice;
+    let created = || {
+        hyper::Response::builder()
+            .status(StatusCode::CREATED)
+            .header(LOCATION, HeaderValue::from_bytes(b"/edev/\xe9").unwrap())
+            .body(Body::empty())
+            .unwrap()
+    };
+    let transport = Arc::new(ScriptedTransport::default());
+    transport
+        .responses
+        .lock()
+        .unwrap()
+        .extend([created(), created()]);
+    let client = Client::with_transport("http://fake", transport, None);
+    let err = client
+        .post("/edev", &EndDevice::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ClientError::InvalidLocationHeader(raw) if raw == b"/edev/\xe9"
+    ));
+
+    // When lenient, the successful create is still returned
+    client.set_lenient_location(true);
+    let res = client.post("/edev", &EndDevice::default()).await.unwrap();
+    assert!(matches!(res, SEPResponse::Created(Some(href)) if href == "/edev/\u{FFFD}"));
+}
+
+#[tokio::test]
+async fn shutdown_closes_idle_connections() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = Client::builder(&format!("http://{addr}")).build().unwrap();
+    let req = tokio::spawn({
+        let client = client.clone();
+        async move { client.delete("/edev/3").await }
+    });
+    let (mut conn, _) = listener.accept().await.unwrap();
+    let mut buf = [0; 1024];
+    assert!(conn.read(&mut buf).await.unwrap() > 0);
+    conn.write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+        .await
+        .unwrap();
+    req.await.unwrap().unwrap();
+
+    // The connection is kept alive in the pool, until shutdown
+    let idle = tokio::time::timeout(Duration::from_millis(100), conn.read(&mut buf)).await;
+    assert!(idle.is_err());
+    let stats = client.clone();
+    client.shutdown().await;
+    let closed = tokio::time::timeout(Duration::from_secs(1), conn.read(&mut buf)).await;
+    assert_eq!(closed.unwrap().unwrap(), 0);
+    assert!(matches!(
+        stats.delete("/edev/3").await.unwrap_err(),
+        ClientError::Shutdown
+    ));
 }
@@ -0,0 +1,323 @@
+//! Device Capability Function Set
+//!
+//! Determines which function sets a server supports, as advertised by the links of it's [`DeviceCapability`].
+//!
+//! A [`Session`] retrieves the resources those links refer to, such as during the onboarding of a client.
+
+use std::{
+    any::{Any, TypeId},
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use sep2_common::{
+    packages::{
+        dcap::DeviceCapability,
+        edev::{EndDeviceList, SelfDevice},
+        identification::Link,
+        time::Time,
+    },
+    traits::SEResource,
+};
+
+use crate::client::Client;
+
+/// An IEEE 2030.5 function set that is discoverable via a [`DeviceCapability`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FunctionSet {
+    /// Billing, via the CustomerAccountListLink
+    Billing,
+    /// Distributed Energy Resources, via the DERProgramListLink
+    Der,
+    /// Demand Response & Load Control, via the DemandResponseProgramListLink
+    Drlc,
+    /// End Device, via the EndDeviceListLink or SelfDeviceLink
+    EndDevice,
+    /// Messaging, via the MessagingProgramListLink
+    Messaging,
+    /// Metering, via the UsagePointListLink
+    Metering,
+    /// Metering Mirror, via the MirrorUsagePointListLink
+    MeteringMirror,
+    /// Prepayment, via the PrepaymentListLink
+    Prepayment,
+    /// Pricing, via the TariffProfileListLink
+    Pricing,
+    /// Response, via the ResponseSetListLink
+    Response,
+    /// Software Download, via the FileListLink
+    SoftwareDownload,
+    /// Time, via the TimeLink
+    Time,
+}
+
+/// Extension methods for determining the function sets supported by a server from it's [`DeviceCapability`].
+pub trait DeviceCapabilityExt {
+    /// Return the function sets the server supports, being those whose links are present.
+    fn supported_function_sets(&self) -> BTreeSet<FunctionSet>;
+
+    /// Whether the server supports the given function set, as per [`DeviceCapabilityExt::supported_function_sets`].
+    fn supports(&self, function_set: FunctionSet) -> bool {
+        self.supported_function_sets().contains(&function_set)
+    }
+}
+
+impl DeviceCapabilityExt for DeviceCapability {
+    fn supported_function_sets(&self) -> BTreeSet<FunctionSet> {
+        [
+            (
+                self.customer_account_list_link.is_some(),
+                FunctionSet::Billing,
+            ),
+            (self.der_program_list_link.is_some(), FunctionSet::Der),
+            (
+                self.demand_response_program_list_link.is_some(),
+                FunctionSet::Drlc,
+            ),
+            (
+                self.end_device_list_link.is_some() || self.self_device_link.is_some(),
+                FunctionSet::EndDevice,
+            ),
+            (
+                self.messaging_program_list_link.is_some(),
+                FunctionSet::Messaging,
+            ),
+            (self.usage_point_list_link.is_some(), FunctionSet::Metering),
+            (
+                self.mirror_usage_point_list_link.is_some(),
+                FunctionSet::MeteringMirror,
+            ),
+            (self.prepayment_list_link.is_some(), FunctionSet::Prepayment),
+            (
+                self.tariff_profile_list_link.is_some(),
+                FunctionSet::Pricing,
+            ),
+            (self.response_set_list_link.is_some(), FunctionSet::Response),
+            (self.file_list_link.is_some(), FunctionSet::SoftwareDownload),
+            (self.time_link.is_some(), FunctionSet::Time),
+        ]
+        .into_iter()
+        .filter_map(|(present, fs)| present.then_some(fs))
+        .collect()
+    }
+}
+
+/// A [`Client`], alongside the [`DeviceCapability`] retrieved from it's server,
+/// providing typed access to the resources it links to.
+///
+/// Each resource is retrieved from the href advertised by the DeviceCapability when first accessed,
+/// and, if caching is enabled, is cached thereafter until [`Session::invalidate`] is called.
+/// The [`Time`] resource is never cached, as it's value is only accurate when retrieved.
+///
+/// Clones share the same cache.
+#[derive(Clone)]
+pub struct Session {
+    client: Client,
+    dcap: Arc<DeviceCapability>,
+    caching: bool,
+    cache: Arc<std::sync::Mutex<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>>,
+}
+
+impl Session {
+    /// Create a session from a previously retrieved DeviceCapability.
+    pub fn new(client: Client, dcap: DeviceCapability) -> Self {
+        Session {
+            client,
+            dcap: Arc::new(dcap),
+            caching: true,
+            cache: Arc::default(),
+        }
+    }
+
+    /// Retrieve the DeviceCapability at `/dcap`, and create a session from it.
+    pub async fn fetch(client: Client) -> Result<Self> {
+        let dcap: DeviceCapability = client.get("/dcap").await?;
+        Ok(Self::new(client, dcap))
+    }
+
+    /// Set whether retrieved resources are cached.
+    ///
+    /// Defaults to true, if this function is not called.
+    pub fn with_caching(mut self, caching: bool) -> Self {
+        self.caching = caching;
+        self
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn device_capability(&self) -> &DeviceCapability {
+        &self.dcap
+    }
+
+    /// Clear all cached resources, such that they are retrieved again when next accessed.
+    pub fn invalidate(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Retrieve the [`Time`] resource, via the TimeLink. Never cached.
+    pub async fn time(&self) -> Result<Time> {
+        let link = self
+            .dcap
+            .time_link
+            .as_ref()
+            .context("DeviceCapability does not contain a TimeLink")?;
+        Ok(self
+            .client
+            .get_link(&Link {
+                href: link.href.clone(),
+            })
+            .await?)
+    }
+
+    /// Retrieve the [`EndDeviceList`], via the EndDeviceListLink.
+    pub async fn end_device_list(&self) -> Result<EndDeviceList> {
+        let href = self.dcap.end_device_list_link.as_ref().map(|l| &l.href);
+        self.cached(href, "EndDeviceListLink").await
+    }
+
+    /// Retrieve the [`SelfDevice`], via the SelfDeviceLink.
+    pub async fn self_device(&self) -> Result<SelfDevice> {
+        let href = self.dcap.self_device_link.as_ref().map(|l| &l.href);
+        self.cached(href, "SelfDeviceLink").await
+    }
+
+    /// Retrieve the [`DERProgramList`](sep2_common::packages::der::DERProgramList), via the DERProgramListLink.
+    #[cfg(feature = "der")]
+    pub async fn der_program_list(&self) -> Result<sep2_common::packages::der::DERProgramList> {
+        let href = self.dcap.der_program_list_link.as_ref().map(|l| &l.href);
+        self.cached(href, "DERProgramListLink").await
+    }
+
+    /// Retrieve the [`UsagePointList`](sep2_common::packages::metering::UsagePointList), via the UsagePointListLink.
+    #[cfg(feature = "metering")]
+    pub async fn usage_point_list(
+        &self,
+    ) -> Result<sep2_common::packages::metering::UsagePointList> {
+        let href = self.dcap.usage_point_list_link.as_ref().map(|l| &l.href);
+        self.cached(href, "UsagePointListLink").await
+    }
+
+    /// Retrieve the [`MirrorUsagePointList`](sep2_common::packages::metering_mirror::MirrorUsagePointList), via the MirrorUsagePointListLink.
+    #[cfg(feature = "metering")]
+    pub async fn mirror_usage_point_list(
+        &self,
+    ) -> Result<sep2_common::packages::metering_mirror::MirrorUsagePointList> {
+        let href = self
+            .dcap
+            .mirror_usage_point_list_link
+            .as_ref()
+            .map(|l| &l.href);
+        self.cached(href, "MirrorUsagePointListLink").await
+    }
+
+    // Retrieve the resource at the href advertised by the named link, unless it is already cached
+    async fn cached<R>(&self, href: Option<&String>, link: &str) -> Result<R>
+    where
+        R: SEResource + Clone + Send + Sync + 'static,
+    {
+        let href = href.with_context(|| format!("DeviceCapability does not contain a {link}"))?;
+        if self.caching {
+            let cache = self.cache.lock().unwrap();
+            if let Some(rsrc) = cache
+                .get(&TypeId::of::<R>())
+                .and_then(|rsrc| rsrc.downcast_ref::<R>())
+            {
+                return Ok(rsrc.clone());
+            }
+        }
+        let rsrc: R = self.client.get_link(&Link { href: href.clone() }).await?;
+        if self.caching {
+            self.cache
+                .lock()
+                .unwrap()
+                .insert(TypeId::of::<R>(), Arc::new(rsrc.clone()));
+        }
+        Ok(rsrc)
+    }
+}
+
+#[test]
+fn supported_function_sets() {
+    use sep2_common::packages::links::{DERProgramListLink, SelfDeviceLink, TimeLink};
+    let dcap = DeviceCapability {
+        der_program_list_link: Some(DERProgramListLink {
+            all: None,
+            href: "/derp".to_owned(),
+        }),
+        self_device_link: Some(SelfDeviceLink {
+            href: "/sdev".to_owned(),
+        }),
+        time_link: Some(TimeLink {
+            href: "/tm".to_owned(),
+        }),
+        ..Default::default()
+    };
+    assert_eq!(
+        dcap.supported_function_sets(),
+        BTreeSet::from([FunctionSet::Der, FunctionSet::EndDevice, FunctionSet::Time])
+    );
+    assert!(dcap.supports(FunctionSet::Der));
+    assert!(!dcap.supports(FunctionSet::Pricing));
+    assert!(DeviceCapability::default()
+        .supported_function_sets()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn session_resources_cached() {
+    use crate::client::ScriptedTransport;
+    use hyper::Body;
+    use sep2_common::{
+        packages::{
+            links::{EndDeviceListLink, TimeLink},
+            primitives::Uint32,
+        },
+        serialize,
+    };
+
+    let dcap = DeviceCapability {
+        end_device_list_link: Some(EndDeviceListLink {
+            all: Some(Uint32(0)),
+            href: "/edev".to_owned(),
+        }),
+        time_link: Some(TimeLink {
+            href: "/tm".to_owned(),
+        }),
+        ..Default::default()
+    };
+    let edevs = EndDeviceList::default();
+    let respond = |xml: String| hyper::Response::new(Body::from(xml));
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        respond(serialize(&dcap).unwrap()),
+        respond(serialize(&edevs).unwrap()),
+        respond(serialize(&Time::default()).unwrap()),
+        respond(serialize(&Time::default()).unwrap()),
+        respond(serialize(&edevs).unwrap()),
+    ]);
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    let session = Session::fetch(client).await.unwrap();
+    assert_eq!(session.device_capability(), &dcap);
+
+    // Cached after the first access
+    assert_eq!(session.end_device_list().await.unwrap(), edevs);
+    assert_eq!(session.end_device_list().await.unwrap(), edevs);
+    // Time is never cached
+    session.time().await.unwrap();
+    session.time().await.unwrap();
+    // Missing links are reported
+    let err = session.self_device().await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "DeviceCapability does not contain a SelfDeviceLink"
+    );
+    session.invalidate();
+    session.end_device_list().await.unwrap();
+
+    let requests = transport.requests.lock().unwrap();
+    let paths: Vec<_> = requests.iter().map(|r| r.uri().path()).collect();
+    assert_eq!(paths, vec!["/dcap", "/edev", "/tm", "/tm", "/edev"]);
+}
@@ -4,27 +4,250 @@
 //!
 //! The interface for a DERControl Schedule can be found in [`Schedule`]
 //!
+//! The individual controls of a [`DERControl`] can be retrieved as [`DerControlAction`]s using [`DERControlExt::actions`].
+//!
+//! While no DERControl is active, the [`DefaultDERControl`] of the schedule is applied, as per [`Schedule::update_default`].
 //!
 
 use std::{
-    sync::{atomic::AtomicI64, Arc},
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     time::Duration,
 };
 
+use anyhow::Result;
 use sep2_common::packages::{
-    der::{DERControl, DERProgram},
+    der::{
+        ActivePower, DERControl, DERControlBase, DERProgram, DefaultDERControl, FixedVar,
+        FreqDroopType, PowerFactorWithExcitation, ReactivePower,
+    },
     identification::ResponseStatus,
+    links::DERCurveLink,
     objects::EventStatusType as EventStatus,
-    types::MRIDType,
+    primitives::Uint32,
+    types::{MRIDType, Percent, SignedPercent},
 };
 use tokio::sync::{broadcast::Receiver, RwLock};
 
 use crate::{
-    client::{Client, SEPResponse},
+    client::{der_response_required, Client, PollHandle, SEPResponse},
     device::SEDevice,
-    event::{EIPair, EIStatus, EventCallback, EventInstance, Events, Schedule, Scheduler},
+    event::{
+        declined, EIPair, EIStatus, EventCallback, EventInstance, Events, Schedule, Scheduler,
+    },
 };
 
+/// A single control mode of a [`DERControlBase`], alongside it's value.
+///
+/// Each variant corresponds to one of the optional `opMod` fields of a [`DERControlBase`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DerControlAction {
+    /// opModConnect
+    Connect(bool),
+    /// opModEnergize
+    Energize(bool),
+    /// opModFixedPFAbsorbW
+    FixedPFAbsorbW(PowerFactorWithExcitation),
+    /// opModFixedPFInjectW
+    FixedPFInjectW(PowerFactorWithExcitation),
+    /// opModFixedVar
+    FixedVar(FixedVar),
+    /// opModFixedW
+    FixedW(SignedPercent),
+    /// opModFreqDroop
+    FreqDroop(FreqDroopType),
+    /// opModFreqWatt
+    FreqWatt(DERCurveLink),
+    /// opModHFRTMayTrip
+    HFRTMayTrip(DERCurveLink),
+    /// opModHFRTMustTrip
+    HFRTMustTrip(DERCurveLink),
+    /// opModHVRTMayTrip
+    HVRTMayTrip(DERCurveLink),
+    /// opModHVRTMomentaryCessation
+    HVRTMomentaryCessation(DERCurveLink),
+    /// opModHVRTMustTrip
+    HVRTMustTrip(DERCurveLink),
+    /// opModLFRTMayTrip
+    LFRTMayTrip(DERCurveLink),
+    /// opModLFRTMustTrip
+    LFRTMustTrip(DERCurveLink),
+    /// opModLVRTMayTrip
+    LVRTMayTrip(DERCurveLink),
+    /// opModLVRTMomentaryCessation
+    LVRTMomentaryCessation(DERCurveLink),
+    /// opModLVRTMustTrip
+    LVRTMustTrip(DERCurveLink),
+    /// opModMaxLimW
+    MaxLimW(Percent),
+    /// opModTargetVar
+    TargetVar(ReactivePower),
+    /// opModTargetW
+    TargetW(ActivePower),
+    /// opModVoltVar
+    VoltVar(DERCurveLink),
+    /// opModVoltWatt
+    VoltWatt(DERCurveLink),
+    /// opModWattPF
+    WattPF(DERCurveLink),
+    /// opModWattVar
+    WattVar(DERCurveLink),
+    /// opModImpLimW
+    #[cfg(feature = "csip_aus")]
+    ImpLimW(ActivePower),
+    /// opModExpLimW
+    #[cfg(feature = "csip_aus")]
+    ExpLimW(ActivePower),
+    /// opModGenLimW
+    #[cfg(feature = "csip_aus")]
+    GenLimW(ActivePower),
+    /// opModLoadLimW
+    #[cfg(feature = "csip_aus")]
+    LoadLimW(ActivePower),
+}
+
+/// Extension methods for decomposing a DER control into it's individual [`DerControlAction`]s.
+pub trait DERControlExt {
+    /// Return an action for each control mode that is set, in the order they appear in [`DERControlBase`].
+    ///
+    /// `rampTms` is not a control mode, and is not included.
+    fn actions(&self) -> Vec<DerControlAction>;
+}
+
+impl DERControlExt for DERControlBase {
+    fn actions(&self) -> Vec<DerControlAction> {
+        use DerControlAction as A;
+        let b = self.clone();
+        [
+            b.op_mod_connect.map(A::Connect),
+            b.op_mod_energize.map(A::Energize),
+            b.op_mod_fixed_pf_absorb_w.map(A::FixedPFAbsorbW),
+            b.op_mod_fixed_pf_inject_w.map(A::FixedPFInjectW),
+            b.op_mod_fixed_var.map(A::FixedVar),
+            b.op_mod_fixed_w.map(A::FixedW),
+            b.op_mod_freq_droop.map(A::FreqDroop),
+            b.op_mod_freq_watt.map(A::FreqWatt),
+            b.op_mod_hfrt_may_trip.map(A::HFRTMayTrip),
+            b.op_mod_hfrt_must_trip.map(A::HFRTMustTrip),
+            b.op_mod_hvrt_may_trip.map(A::HVRTMayTrip),
+            b.op_mod_hvrt_momentary_cessation
+                .map(A::HVRTMomentaryCessation),
+            b.op_mod_hvrt_must_trip.map(A::HVRTMustTrip),
+            b.op_mod_lfrt_may_trip.map(A::LFRTMayTrip),
+            b.op_mod_lfrt_must_trip.map(A::LFRTMustTrip),
+            b.op_mod_lvrt_may_trip.map(A::LVRTMayTrip),
+            b.op_mod_lvrt_momentary_cessation
+                .map(A::LVRTMomentaryCessation),
+            b.op_mod_lvrt_must_trip.map(A::LVRTMustTrip),
+            b.op_mod_max_lim_w.map(A::MaxLimW),
+            b.op_mod_target_var.map(A::TargetVar),
+            b.op_mod_target_w.map(A::TargetW),
+            b.op_mod_volt_var.map(A::VoltVar),
+            b.op_mod_volt_watt.map(A::VoltWatt),
+            b.op_mod_watt_pf.map(A::WattPF),
+            b.op_mod_watt_var.map(A::WattVar),
+            #[cfg(feature = "csip_aus")]
+            b.op_mod_imp_lim_w.map(A::ImpLimW),
+            #[cfg(feature = "csip_aus")]
+            b.op_mod_exp_lim_w.map(A::ExpLimW),
+            #[cfg(feature = "csip_aus")]
+            b.op_mod_gen_lim_w.map(A::GenLimW),
+            #[cfg(feature = "csip_aus")]
+            b.op_mod_load_lim_w.map(A::LoadLimW),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+impl DERControlExt for DERControl {
+    fn actions(&self) -> Vec<DerControlAction> {
+        self.der_control_base.actions()
+    }
+}
+
+impl DERControlExt for DefaultDERControl {
+    fn actions(&self) -> Vec<DerControlAction> {
+        self.der_control_base.actions()
+    }
+}
+
+/// A Trait specifying a callback for the [`DefaultDERControl`] of a DERControl [`Schedule`], as set using [`Schedule::set_default_handler`]
+pub trait DefaultControlCallback: Clone + Send + Sync + 'static {
+    /// Called whenever the client device should apply the given DefaultDERControl, as no DERControl is active.
+    ///
+    /// This occurs when the DefaultDERControl is first set, or changes, while no DERControl is active,
+    /// and whenever the last active DERControl is no longer active.
+    fn default_update(&self, default: &DefaultDERControl) -> impl Future<Output = ()> + Send;
+}
+
+impl<F, R> DefaultControlCallback for F
+where
+    F: Fn(&DefaultDERControl) -> R + Clone + Send + Sync + 'static,
+    R: Future<Output = ()> + Send + 'static,
+{
+    fn default_update(&self, default: &DefaultDERControl) -> impl Future<Output = ()> + Send {
+        self(default)
+    }
+}
+
+type DefaultHandler = Arc<
+    dyn Fn(&DefaultDERControl) -> Pin<Box<dyn Future<Output = ()> + Send + '_>>
+        + Send
+        + Sync
+        + 'static,
+>;
+
+/// The most recent DefaultDERControl of a DERControl Schedule, and the callback it is applied with.
+#[derive(Default)]
+struct DefaultControl {
+    control: RwLock<Option<DefaultDERControl>>,
+    handler: std::sync::RwLock<Option<DefaultHandler>>,
+}
+
+/// Shared state used to defer informing the client that an active event is no longer active,
+/// such that it can be coalesced with another event starting shortly after.
+#[derive(Default)]
+struct Debounce {
+    // Length of the debounce window, in milliseconds. Zero if disabled.
+    window: AtomicU64,
+    // Incremented whenever an event is started
+    starts: AtomicU64,
+}
+
+impl Debounce {
+    fn window(&self) -> Duration {
+        Duration::from_millis(self.window.load(Ordering::Relaxed))
+    }
+
+    fn set_window(&self, window: Duration) {
+        self.window
+            .store(window.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    fn starts(&self) -> u64 {
+        self.starts.load(Ordering::Relaxed)
+    }
+
+    fn started(&self) {
+        self.starts.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// State specific to DERControl schedules, shared between clones of the schedule.
+#[derive(Default)]
+struct DERState {
+    // Debounce state for coalescing DERControl reverts
+    debounce: Debounce,
+    // Control applied while no DERControl is active
+    default_control: DefaultControl,
+}
+
 impl EventInstance<DERControl> {
     // Check if two DERControls have the same base
     fn has_same_target(&self, other: &Self) -> bool {
@@ -51,6 +274,13 @@ fn der_supersedes<'a>(
 }
 
 impl Schedule<DERControl> {
+    // The DERControl specific state of the schedule, as created by `Scheduler::new`
+    fn state(&self) -> &DERState {
+        self.ext
+            .downcast_ref()
+            .expect("DERControl schedules are created with DERState")
+    }
+
     async fn der_start_task(self, mut rx: Receiver<()>) {
         loop {
             // Intermittently sleep until next event start time
@@ -68,19 +298,19 @@ impl Schedule<DERControl> {
                 _ => continue,
             };
 
-            // Mark event as complete
+            // Mark event as active
             events.update_event(&mrid, EIStatus::Active);
 
             // Notify client and server
             let target = events.get(&mrid).unwrap();
             let resp = (self.handler)(target).await;
             self.auto_der_response(target.event(), resp).await;
-            // If the device opts-out or if the event cannot be active, we update it's internal status.
-            match resp {
-                ResponseStatus::EventOptOut
-                | ResponseStatus::EventNotApplicable
-                | ResponseStatus::EventInvalid => events.update_event(&mrid, EIStatus::Cancelled),
-                _ => (),
+            // If the device opts-out or cannot comply with the event, we update it's internal status,
+            // such that it is never completed or reverted.
+            if declined(resp) {
+                events.update_event(&mrid, EIStatus::Cancelled);
+            } else {
+                self.state().debounce.started();
             }
         }
     }
@@ -108,8 +338,125 @@ impl Schedule<DERControl> {
             // Notify client and server
             let events = events.downgrade();
             let target = events.get(&mrid).unwrap();
-            let resp = (self.handler)(target).await;
+            let resp = self.der_revert(target).await;
             self.auto_der_response(target.event(), resp).await;
+            self.default_after_revert(&events).await;
+        }
+    }
+
+    /// Set a debounce window for reverting DERControls.
+    ///
+    /// When an active DERControl completes, is cancelled, or is superseded, informing the [`EventCallback`] is deferred by the given window.
+    /// If another DERControl starts within that window, the client is never informed of the revert, and can move directly to the new DERControl,
+    /// avoiding repeatedly reverting and applying controls at the device-level.
+    ///
+    /// Responses are still sent to the server for every transition. For deferred transitions, the response is determined by the schedule,
+    /// and the response returned by the [`EventCallback`] is ignored.
+    ///
+    /// A window of zero disables debouncing, which is the default.
+    ///
+    /// Debouncing is only supported by DERControl schedules, the schedules of other function sets always inform the client immediately.
+    pub fn set_debounce(&mut self, window: Duration) {
+        self.state().debounce.set_window(window);
+    }
+
+    /// Inform the client that an event is no longer active, or defer it if a debounce window is set.
+    ///
+    /// Returns the response from the client, or the response as determined by the event's status if deferred.
+    async fn der_revert(&self, ei: &EventInstance<DERControl>) -> ResponseStatus {
+        let window = self.state().debounce.window();
+        if window.is_zero() {
+            return (self.handler)(ei).await;
+        }
+        let mrid = ei.event().mrid;
+        let starts = self.state().debounce.starts();
+        tokio::spawn({
+            let this = self.clone();
+            async move {
+                tokio::time::sleep(window).await;
+                if this.state().debounce.starts() != starts {
+                    log::info!("DERControlSchedule: DERControl ({mrid}) revert was coalesced with a newly started DERControl");
+                    return;
+                }
+                let events = this.events.read().await;
+                if let Some(ei) = events.get(&mrid) {
+                    (this.handler)(ei).await;
+                }
+                this.apply_default(&events).await;
+            }
+        });
+        ei.status().into()
+    }
+
+    /// Set the callback used to apply the [`DefaultDERControl`] of this schedule, whenever no DERControl is active.
+    ///
+    /// The DefaultDERControl is set using [`Schedule::update_default`], or by polling it using [`Schedule::poll_default`].
+    pub fn set_default_handler(&mut self, handler: impl DefaultControlCallback) {
+        *self.state().default_control.handler.write().unwrap() = Some(Arc::new(move |default| {
+            let handler = handler.clone();
+            Box::pin(async move { handler.default_update(default).await })
+        }));
+    }
+
+    /// Update the [`DefaultDERControl`] applied whenever no DERControl is active, such as when it is received in a notification.
+    ///
+    /// If the DefaultDERControl differs from the current one, and no DERControl is active, it is applied immediately,
+    /// otherwise it is applied once the last active DERControl is no longer active.
+    pub async fn update_default(&self, default: DefaultDERControl) {
+        // Hold the events lock, such that no DERControl can start while the default is applied
+        let events = self.events.read().await;
+        {
+            let mut current = self.state().default_control.control.write().await;
+            if current.as_ref() == Some(&default) {
+                return;
+            }
+            *current = Some(default);
+        }
+        self.apply_default(&events).await;
+    }
+
+    /// Return the most recent [`DefaultDERControl`] given to [`Schedule::update_default`], if any.
+    pub async fn default_control(&self) -> Option<DefaultDERControl> {
+        self.state().default_control.control.read().await.clone()
+    }
+
+    /// Begin polling the [`DefaultDERControl`] of the given [`DERProgram`], as per [`Client::poll_default_der_control`],
+    /// passing each retrieved DefaultDERControl to [`Schedule::update_default`].
+    ///
+    /// Returns an error if the DERProgram does not contain a DefaultDERControlLink.
+    pub async fn poll_default(
+        &self,
+        program: &DERProgram,
+        poll_rate: Option<Uint32>,
+    ) -> Result<PollHandle> {
+        let this = self.clone();
+        self.client
+            .poll_default_der_control(program, poll_rate, move |default: DefaultDERControl| {
+                let this = this.clone();
+                async move { this.update_default(default).await }
+            })
+            .await
+    }
+
+    // Inform the client of the DefaultDERControl, if one is set, and no DERControl is active
+    async fn apply_default(&self, events: &Events<DERControl>) {
+        if events.has_active() {
+            return;
+        }
+        let Some(handler) = self.state().default_control.handler.read().unwrap().clone() else {
+            return;
+        };
+        if let Some(default) = self.state().default_control.control.read().await.as_ref() {
+            log::info!("DERControlSchedule: No DERControl is active, applying DefaultDERControl");
+            handler(default).await;
+        }
+    }
+
+    // Apply the DefaultDERControl after a DERControl was reverted.
+    // If reverts are debounced, the default is instead applied alongside the deferred revert.
+    async fn default_after_revert(&self, events: &Events<DERControl>) {
+        if self.state().debounce.window().is_zero() {
+            self.apply_default(events).await;
         }
     }
 
@@ -132,15 +479,34 @@ impl Schedule<DERControl> {
         let ei = events.get(target_mrid).unwrap();
         let resp = if current_status == EIStatus::Active {
             // If the event was active, let the client know it is over
-            (self.handler)(ei).await
+            self.der_revert(ei).await
         } else {
             // If it's not active, the client doesn't even know about this event
             ResponseStatus::EventCancelled
         };
         self.auto_der_response(ei.event(), resp).await;
+        if current_status == EIStatus::Active {
+            self.default_after_revert(&events).await;
+        }
     }
 
     async fn auto_der_response(&self, event: &DERControl, status: ResponseStatus) {
+        // Only POST the responses the server has asked for, to the location it has asked for
+        if event.reply_to.is_none() {
+            log::debug!(
+                "DERControlSchedule: Not sending DERControlResponse for event {} as it has no replyTo field",
+                event.mrid
+            );
+            return;
+        }
+        if !der_response_required(status, event.response_required) {
+            log::debug!(
+                "DERControlSchedule: Not sending DERControlResponse for event {} as a response with status {:?} was not required",
+                event.mrid,
+                status
+            );
+            return;
+        }
         match self
             .client
             .send_der_response(
@@ -188,22 +554,13 @@ impl Scheduler<DERControl> for Schedule<DERControl> {
         handler: impl EventCallback<DERControl>,
         tickrate: Duration,
     ) -> Self {
-        let (tx, rx) = tokio::sync::broadcast::channel::<()>(1);
         let out = Schedule {
-            client,
-            device,
-            events: Arc::new(RwLock::new(Events::new())),
-            handler: Arc::new(move |ei| {
-                let handler = handler.clone();
-                Box::pin(async move { handler.event_update(ei).await })
-            }),
-            bc_sd: tx.clone(),
-            tickrate,
-            time_offset: Arc::new(AtomicI64::new(0)),
+            ext: Arc::new(DERState::default()),
+            ..Schedule::base(client, device, handler, tickrate)
         };
-        tokio::spawn(out.clone().clean_events(rx));
-        tokio::spawn(out.clone().der_start_task(tx.subscribe()));
-        tokio::spawn(out.clone().der_end_task(tx.subscribe()));
+        tokio::spawn(out.clone().clean_events(out.bc_sd.subscribe()));
+        tokio::spawn(out.clone().der_start_task(out.bc_sd.subscribe()));
+        tokio::spawn(out.clone().der_end_task(out.bc_sd.subscribe()));
         out
     }
 
@@ -298,6 +655,7 @@ impl Scheduler<DERControl> for Schedule<DERControl> {
 
             // Determine what events this supersedes
             let mut target = ei;
+            let mut reverted = false;
             for (o_mrid, other) in events.iter_mut() {
                 if let Some(((superseded, _), (superseding, superseding_mrid))) =
                     der_supersedes((&mut target, &mrid), (other, o_mrid))
@@ -311,7 +669,8 @@ impl Scheduler<DERControl> for Schedule<DERControl> {
                     let status = if prev_status == EIStatus::Active {
                         // Since the newly superseded event is over, tell the client it's finished
                         // We override whatever response the client provides to the more correct one
-                        (self.handler)(superseded).await;
+                        self.der_revert(superseded).await;
+                        reverted = true;
                         if superseded.program_mrid() != superseding.program_mrid() {
                             // If the two events come from different programs
                             ResponseStatus::EventAbortedProgram
@@ -333,6 +692,27 @@ impl Scheduler<DERControl> for Schedule<DERControl> {
 
             // Add it to our schedule
             events.insert(&mrid, target);
+
+            // The superseding event may not have started yet
+            if reverted {
+                self.default_after_revert(&events).await;
+            }
         };
     }
 }
+
+#[test]
+fn der_control_actions() {
+    let mut control = DERControl::default();
+    assert!(control.actions().is_empty());
+    control.der_control_base.op_mod_connect = Some(false);
+    control.der_control_base.op_mod_max_lim_w = Some(Percent::default());
+    control.der_control_base.ramp_tms = Some(sep2_common::packages::primitives::Uint16(10));
+    assert_eq!(
+        control.actions(),
+        vec![
+            DerControlAction::Connect(false),
+            DerControlAction::MaxLimW(Percent::default())
+        ]
+    );
+}
@@ -13,22 +13,15 @@ use sep2_common::packages::{
 };
 
 use crate::{
-    client::SEPResponse,
-    event::{EIPair, EIStatus, EventCallback, EventInstance, Schedule},
+    client::{drlc_response_required, SEPResponse},
+    event::{declined, EIPair, EIStatus, EventCallback, EventInstance, Schedule},
 };
 
-use std::{
-    sync::{atomic::AtomicI64, Arc},
-    time::Duration,
-};
+use std::{sync::Arc, time::Duration};
 
 use tokio::sync::{broadcast::Receiver, RwLock};
 
-use crate::{
-    client::Client,
-    device::SEDevice,
-    event::{Events, Scheduler},
-};
+use crate::{client::Client, device::SEDevice, event::Scheduler};
 
 /// Given two EndDeviceControls, determine which is superseded, and which is superseding, or None if neither supersede one another
 fn drlc_supersedes<'a>(
@@ -70,6 +63,11 @@ impl Schedule<EndDeviceControl> {
             let target = events.get(&mrid).unwrap();
             let resp = (self.handler)(target).await;
             self.auto_drlc_response(target.event(), resp).await;
+            // If the device opts-out or cannot comply with the event, it is never completed
+            if declined(resp) {
+                drop(events);
+                self.cancel_declined(&mrid).await;
+            }
         }
     }
 
@@ -118,6 +116,22 @@ impl Schedule<EndDeviceControl> {
     }
 
     async fn auto_drlc_response(&self, event: &EndDeviceControl, status: ResponseStatus) {
+        // Only POST the responses the server has asked for, to the location it has asked for
+        if event.reply_to.is_none() {
+            log::debug!(
+                "Client: Not sending DRLC response for event {} as it has no replyTo field",
+                event.mrid
+            );
+            return;
+        }
+        if !drlc_response_required(status, event.response_required) {
+            log::debug!(
+                "Client: Not sending DRLC response for event {} as a response with status {:?} was not required",
+                event.mrid,
+                status
+            );
+            return;
+        }
         match self
             .client
             .send_drlc_response(
@@ -160,22 +174,10 @@ impl Scheduler<EndDeviceControl> for Schedule<EndDeviceControl> {
         handler: impl EventCallback<EndDeviceControl>,
         tickrate: Duration,
     ) -> Self {
-        let (tx, rx) = tokio::sync::broadcast::channel::<()>(1);
-        let out = Schedule {
-            client,
-            device,
-            events: Arc::new(RwLock::new(Events::new())),
-            handler: Arc::new(move |ei| {
-                let handler = handler.clone();
-                Box::pin(async move { handler.event_update(ei).await })
-            }),
-            bc_sd: tx.clone(),
-            tickrate,
-            time_offset: Arc::new(AtomicI64::new(0)),
-        };
-        tokio::spawn(out.clone().clean_events(rx));
-        tokio::spawn(out.clone().drlc_start_task(tx.subscribe()));
-        tokio::spawn(out.clone().drlc_end_task(tx.subscribe()));
+        let out = Schedule::base(client, device, handler, tickrate);
+        tokio::spawn(out.clone().clean_events(out.bc_sd.subscribe()));
+        tokio::spawn(out.clone().drlc_start_task(out.bc_sd.subscribe()));
+        tokio::spawn(out.clone().drlc_end_task(out.bc_sd.subscribe()));
         out
     }
 
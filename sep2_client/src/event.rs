@@ -4,17 +4,21 @@
 //! providing a black box for working with [`SEEvent`] resources.
 
 use std::{
+    any::Any,
     collections::{hash_map, HashMap},
     future::Future,
     pin::Pin,
-    sync::{atomic::AtomicI64, Arc},
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
 use crate::{
     client::Client,
     device::SEDevice,
-    time::{current_time, SEPTime},
+    time::{current_time, DateTimeIntervalExt, SEPTime},
 };
 use rand::Rng;
 use sep2_common::packages::{
@@ -59,6 +63,29 @@ where
     server_id: u8,
 }
 
+/// A copy of an event tracked by a [`Schedule`], as returned by [`Schedule::snapshot`].
+#[derive(Debug, Clone)]
+pub struct ScheduledEvent<E>
+where
+    E: SEEvent,
+{
+    pub mrid: MRIDType,
+    /// The state of the event in the schedule
+    pub status: EIStatus,
+    /// Event start time, after randomisation, in seconds since the unix epoch
+    pub start: i64,
+    /// Event end time, after randomisation, in seconds since the unix epoch
+    pub end: i64,
+    pub primacy: PrimacyType,
+    /// The MRID of the program this event belongs to, as per [`EventInstance::program_mrid`]
+    pub program_mrid: MRIDType,
+    /// Which server this event was sourced from
+    pub server_id: u8,
+    /// The MRIDs of the events that superseded this event, if any
+    pub superseded_by: Vec<MRIDType>,
+    pub event: E,
+}
+
 pub(crate) type EIPair<'a, E> = (&'a mut EventInstance<E>, &'a MRIDType);
 
 /// The current state of an [`EventInstance`] in the schedule.
@@ -112,7 +139,7 @@ impl<E: SEEvent> EventInstance<E> {
         server_id: u8,
     ) -> Self {
         let start: i64 = event.interval().start.get();
-        let end: i64 = start + i64::from(event.interval().duration.get());
+        let end: i64 = event.interval().end().into();
         EventInstance {
             status: event.event_status().current_status.into(),
             event: Box::new(event),
@@ -228,11 +255,15 @@ pub trait EventCallback<E: SEEvent>: Clone + Send + Sync + 'static {
     ///
     /// When determining the ResponseStatus to return, refer to Table 27 of IEEE 2030.5-2018
     ///
+    /// If the client device cannot comply with a newly Active event, such as when the hardware is offline,
+    /// it should return the failure status to be sent to the server, rather than [`ResponseStatus::EventStarted`].
     /// If the returned ResponseStatus for a newly Active event is one of:
     ///
-    /// [`ResponseStatus::EventOptOut`] ||  [`ResponseStatus::EventNotApplicable`] ||  [`ResponseStatus::EventInvalid`]
+    /// [`ResponseStatus::EventOptOut`] ||  [`ResponseStatus::EventNoDisplay`] ||  [`ResponseStatus::EventAbortedProgram`] ||
+    /// [`ResponseStatus::EventNotApplicable`] ||  [`ResponseStatus::EventInvalid`]
     ///
     /// The event will internally be marked as cancelled, and the client will no longer receive updates on it.
+    /// No further transitions are attempted for that event, so no completion response is sent to the server.
     ///
     /// Currently, calling this function acquires a global lock on the scheduler, stopping it from making progress.
     /// This may be changed in the future.
@@ -240,6 +271,24 @@ pub trait EventCallback<E: SEEvent>: Clone + Send + Sync + 'static {
         -> impl Future<Output = ResponseStatus> + Send;
 }
 
+/// Whether the given response to a newly Active event indicates the client device will not apply it.
+#[cfg(any(
+    feature = "der",
+    feature = "drlc",
+    feature = "messaging",
+    feature = "pricing"
+))]
+pub(crate) fn declined(status: ResponseStatus) -> bool {
+    matches!(
+        status,
+        ResponseStatus::EventOptOut
+            | ResponseStatus::EventNoDisplay
+            | ResponseStatus::EventAbortedProgram
+            | ResponseStatus::EventNotApplicable
+            | ResponseStatus::EventInvalid
+    )
+}
+
 impl<F, R, E: SEEvent> EventCallback<E> for F
 where
     F: Fn(&EventInstance<E>) -> R + Clone + Send + Sync + 'static,
@@ -316,6 +365,12 @@ where
         self.map.get(mrid)
     }
 
+    /// Whether any event is currently active
+    #[cfg_attr(not(feature = "der"), allow(dead_code))]
+    pub(crate) fn has_active(&self) -> bool {
+        self.map.values().any(|ei| ei.status() == EIStatus::Active)
+    }
+
     pub(crate) fn update_event(&mut self, event: &MRIDType, status: EIStatus) {
         let event = self.map.get_mut(event).unwrap();
         event.update_status(status);
@@ -434,6 +489,8 @@ where
     pub(crate) tickrate: Duration,
     // Schedule-specific time offset, as set by a Time resource
     pub(crate) time_offset: Arc<AtomicI64>,
+    // Function set specific state, shared between clones, such as that of DERControl schedules
+    pub(crate) ext: Arc<dyn Any + Send + Sync>,
 }
 
 // Manual clone implementation since H doesn't need to be clone
@@ -450,6 +507,7 @@ where
             bc_sd: self.bc_sd.clone(),
             tickrate: self.tickrate,
             time_offset: Arc::new(AtomicI64::new(0)),
+            ext: self.ext.clone(),
         }
     }
 }
@@ -458,14 +516,36 @@ impl<E> Schedule<E>
 where
     E: SEEvent,
 {
+    // Create a schedule without any function set specific state, nor any background tasks
+    pub(crate) fn base(
+        client: Client,
+        device: Arc<RwLock<SEDevice>>,
+        handler: impl EventCallback<E>,
+        tickrate: Duration,
+    ) -> Self {
+        let (tx, _) = tokio::sync::broadcast::channel::<()>(1);
+        Schedule {
+            client,
+            device,
+            events: Arc::new(RwLock::new(Events::new())),
+            handler: Arc::new(move |ei| {
+                let handler = handler.clone();
+                Box::pin(async move { handler.event_update(ei).await })
+            }),
+            bc_sd: tx,
+            tickrate,
+            time_offset: Arc::new(AtomicI64::new(0)),
+            ext: Arc::new(()),
+        }
+    }
+
     /// Updates the schedule-specific time offset.
     /// "If FunctionSetAssignments contain both Event-based function sets (e.g., DRLC, pricing, message) and a
     /// Time resource, then devices SHALL use the Time resource from the same FunctionSetAssignments when
     /// executing the events from the associated Event-based function set."
     pub fn update_time(&mut self, time: Time) {
         let offset = time.current_time.get() - i64::from(current_time());
-        self.time_offset
-            .store(offset, std::sync::atomic::Ordering::Relaxed);
+        self.time_offset.store(offset, Ordering::Relaxed);
     }
 
     pub fn shutdown(&mut self) {
@@ -475,8 +555,48 @@ where
         };
     }
 
+    // Cancel an event the client declined, such that it is never completed.
+    // The event is left as is if it is no longer active, such as if the server cancelled it while the client was being informed.
+    #[cfg(any(feature = "drlc", feature = "messaging", feature = "pricing"))]
+    pub(crate) async fn cancel_declined(&self, mrid: &MRIDType) {
+        let mut events = self.events.write().await;
+        if events
+            .get(mrid)
+            .is_some_and(|ei| ei.status() == EIStatus::Active)
+        {
+            events.update_event(mrid, EIStatus::Cancelled);
+        }
+    }
+
+    /// Return a copy of every event tracked by the schedule, in order of start time.
+    ///
+    /// All events are copied under a single lock, such that the snapshot reflects the schedule at one point in time.
+    pub async fn snapshot(&self) -> Vec<ScheduledEvent<E>>
+    where
+        E: Clone,
+    {
+        let events = self.events.read().await;
+        let mut out: Vec<ScheduledEvent<E>> = events
+            .map
+            .iter()
+            .map(|(mrid, ei)| ScheduledEvent {
+                mrid: *mrid,
+                status: ei.status,
+                start: ei.start,
+                end: ei.end,
+                primacy: ei.primacy,
+                program_mrid: ei.program_mrid,
+                server_id: ei.server_id,
+                superseded_by: ei.superseded_by.clone(),
+                event: (*ei.event).clone(),
+            })
+            .collect();
+        out.sort_by_key(|ev| (ev.start, ev.mrid));
+        out
+    }
+
     pub(crate) fn schedule_time(&self) -> SEPTime {
-        current_time() + self.time_offset.load(std::sync::atomic::Ordering::Relaxed)
+        current_time() + self.time_offset.load(Ordering::Relaxed)
     }
 
     pub(crate) async fn clean_events(self, mut rx: Receiver<()>) {
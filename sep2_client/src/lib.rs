@@ -1,11 +1,17 @@
 pub mod client;
+pub mod dcap;
 pub mod device;
+pub mod paths;
+pub mod persist;
+pub mod profile;
+pub mod query;
+pub mod response;
 pub mod security;
 pub mod time;
 pub mod tls;
 
 #[cfg(feature = "der")]
-mod der;
+pub mod der;
 #[cfg(feature = "drlc")]
 mod drlc;
 #[cfg(feature = "event")]
@@ -13,7 +19,9 @@ pub mod event;
 #[cfg(feature = "flow_reservation")]
 mod flow_reservation;
 #[cfg(feature = "messaging")]
-mod messaging;
+pub mod messaging;
+#[cfg(feature = "metering")]
+pub mod metering;
 #[cfg(feature = "pricing")]
 mod pricing;
 #[cfg(feature = "pubsub")]
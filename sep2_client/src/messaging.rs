@@ -2,24 +2,28 @@
 //!
 //! This module is primarily an implementation of a Schedule for TextMessage events.
 //!
+//! Clients that instead display the active messages of a program at their own convenience can use a [`MessagingClient`],
+//! which holds the most recently retrieved messages, and sends the required Responses when a message is confirmed by a user.
 
-use std::{
-    sync::{atomic::AtomicI64, Arc},
-    time::Duration,
-};
+use std::{sync::Arc, time::Duration};
 
+use anyhow::{Context, Result};
 use sep2_common::packages::{
     identification::ResponseStatus,
-    messaging::{MessagingProgram, TextMessage},
+    messaging::{MessagingProgram, TextMessage, TextMessageList},
     objects::EventStatusType as EventStatus,
+    primitives::{HexBinary160, Uint32},
     types::MRIDType,
 };
+use sep2_common::traits::{SEList, SEResource, Validate};
+use sepserde::{YaDeserialize, YaSerialize};
 use tokio::sync::{broadcast::Receiver, RwLock};
 
 use crate::{
-    client::{Client, SEPResponse},
+    client::{msg_response_required, Client, PollHandle, SEPResponse},
     device::SEDevice,
-    event::{EIStatus, EventCallback, EventInstance, Events, Schedule, Scheduler},
+    event::{declined, EIStatus, EventCallback, EventInstance, Schedule, Scheduler},
+    time::{current_time_with_offset, DateTimeIntervalExt, SEPTime},
 };
 
 // Messaging Function Set
@@ -45,6 +49,11 @@ impl Schedule<TextMessage> {
             let target = events.get(&mrid).unwrap();
             let resp = (self.handler)(target).await;
             self.auto_msg_response(target.event(), resp).await;
+            // If the device opts-out or will not display the message, it is never completed
+            if declined(resp) {
+                drop(events);
+                self.cancel_declined(&mrid).await;
+            }
         }
     }
 
@@ -97,6 +106,22 @@ impl Schedule<TextMessage> {
     }
 
     async fn auto_msg_response(&self, event: &TextMessage, status: ResponseStatus) {
+        // Only POST the responses the server has asked for, to the location it has asked for
+        if event.reply_to.is_none() {
+            log::debug!(
+                "Client: Not sending Messaging response for event {} as it has no replyTo field",
+                event.mrid
+            );
+            return;
+        }
+        if !msg_response_required(status, event.response_required) {
+            log::debug!(
+                "Client: Not sending Messaging response for event {} as a response with status {:?} was not required",
+                event.mrid,
+                status
+            );
+            return;
+        }
         match self
             .client
             .send_msg_response(
@@ -145,22 +170,10 @@ impl Scheduler<TextMessage> for Schedule<TextMessage> {
         handler: impl EventCallback<TextMessage>,
         tickrate: Duration,
     ) -> Self {
-        let (tx, rx) = tokio::sync::broadcast::channel::<()>(1);
-        let out = Schedule {
-            client,
-            device,
-            events: Arc::new(RwLock::new(Events::new())),
-            handler: Arc::new(move |ei| {
-                let handler = handler.clone();
-                Box::pin(async move { handler.event_update(ei).await })
-            }),
-            bc_sd: tx.clone(),
-            tickrate,
-            time_offset: Arc::new(AtomicI64::new(0)),
-        };
-        tokio::spawn(out.clone().clean_events(rx));
-        tokio::spawn(out.clone().msg_start_task(tx.subscribe()));
-        tokio::spawn(out.clone().msg_end_task(tx.subscribe()));
+        let out = Schedule::base(client, device, handler, tickrate);
+        tokio::spawn(out.clone().clean_events(out.bc_sd.subscribe()));
+        tokio::spawn(out.clone().msg_start_task(out.bc_sd.subscribe()));
+        tokio::spawn(out.clone().msg_end_task(out.bc_sd.subscribe()));
         out
     }
 
@@ -234,3 +247,320 @@ impl Scheduler<TextMessage> for Schedule<TextMessage> {
         }
     }
 }
+
+// sep2_common does not implement `SEResource` or `SEList` for `TextMessageList`, so it cannot be retrieved using a `Client`.
+// This wrapper delegates to it, implementing both.
+#[derive(Default, PartialEq, Eq, Debug, Clone)]
+struct TextMessagePage(TextMessageList);
+
+impl YaSerialize for TextMessagePage {
+    fn name() -> &'static str {
+        TextMessageList::name()
+    }
+
+    fn serialize<W: std::io::Write>(
+        &self,
+        writer: &mut sepserde::ser::Serializer<W>,
+    ) -> Result<(), String> {
+        self.0.serialize(writer)
+    }
+
+    fn serialize_attributes(
+        &self,
+        attributes: Vec<sepserde::xml::attribute::OwnedAttribute>,
+        namespace: sepserde::xml::namespace::Namespace,
+    ) -> Result<
+        (
+            Vec<sepserde::xml::attribute::OwnedAttribute>,
+            sepserde::xml::namespace::Namespace,
+        ),
+        String,
+    > {
+        self.0.serialize_attributes(attributes, namespace)
+    }
+}
+
+impl YaDeserialize for TextMessagePage {
+    fn deserialize<R: std::io::Read>(
+        reader: &mut sepserde::de::Deserializer<R>,
+    ) -> Result<Self, String> {
+        TextMessageList::deserialize(reader).map(TextMessagePage)
+    }
+}
+
+impl Validate for TextMessagePage {
+    fn validate(&self) -> Result<(), String> {
+        self.0.validate()
+    }
+}
+
+impl SEResource for TextMessagePage {
+    fn href(&self) -> Option<&str> {
+        self.0.href.as_deref()
+    }
+}
+
+impl SEList for TextMessagePage {
+    type Inner = TextMessage;
+
+    fn all(&self) -> Uint32 {
+        self.0.all
+    }
+
+    fn all_mut(&mut self) -> &mut Uint32 {
+        &mut self.0.all
+    }
+
+    fn results(&self) -> Uint32 {
+        self.0.results
+    }
+
+    fn results_mut(&mut self) -> &mut Uint32 {
+        &mut self.0.results
+    }
+
+    fn list_as_slice(&self) -> &[TextMessage] {
+        &self.0.text_message
+    }
+
+    fn list_mut(&mut self) -> &mut Vec<TextMessage> {
+        &mut self.0.text_message
+    }
+}
+
+/// Holds the [`TextMessage`]s of a [`MessagingProgram`], and sends the Responses required when they are confirmed by a user.
+///
+/// Unlike [`Schedule<TextMessage>`], which calls a handler as each message starts & ends,
+/// a MessagingClient is queried for the messages that are currently active, such that they can be displayed until confirmed.
+#[derive(Clone)]
+pub struct MessagingClient {
+    client: Client,
+    lfdi: HexBinary160,
+    page_size: u8,
+    messages: Arc<std::sync::RwLock<Vec<TextMessage>>>,
+}
+
+impl MessagingClient {
+    const DEFAULT_PAGE_SIZE: u8 = 25;
+
+    /// Create a MessagingClient, that sends Responses on behalf of the device with the given LFDI.
+    pub fn new(client: Client, lfdi: HexBinary160) -> Self {
+        MessagingClient {
+            client,
+            lfdi,
+            page_size: Self::DEFAULT_PAGE_SIZE,
+            messages: Arc::default(),
+        }
+    }
+
+    /// Set the number of items requested in each page of a TextMessageList.
+    ///
+    /// Defaults to 25, if this function is not called.
+    pub fn with_page_size(mut self, page_size: u8) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Retrieve every page of the TextMessageList of the given program, replacing the messages currently held.
+    pub async fn refresh(&self, program: &MessagingProgram) -> Result<()> {
+        let link = program
+            .text_message_list_link
+            .as_ref()
+            .context("MessagingProgram does not contain a TextMessageListLink")?;
+        let messages = self
+            .client
+            .get_list_link_all::<TextMessagePage>(link, self.page_size)
+            .await?;
+        self.update(messages);
+        Ok(())
+    }
+
+    /// Poll the TextMessageList of the given program, replacing the messages currently held each time it is retrieved.
+    ///
+    /// If the polled list does not contain every message, the remaining pages are retrieved as per [`MessagingClient::refresh`].
+    pub async fn start_poll(
+        &self,
+        program: &MessagingProgram,
+        poll_rate: Option<Uint32>,
+    ) -> Result<PollHandle> {
+        let link = program
+            .text_message_list_link
+            .clone()
+            .context("MessagingProgram does not contain a TextMessageListLink")?;
+        let this = self.clone();
+        let handle = self
+            .client
+            .start_poll(link.href.clone(), poll_rate, move |TextMessagePage(list)| {
+                let this = this.clone();
+                let link = link.clone();
+                async move {
+                    if list.results >= list.all {
+                        this.update(list.text_message);
+                        return;
+                    }
+                    match this
+                        .client
+                        .get_list_link_all::<TextMessagePage>(&link, this.page_size)
+                        .await
+                    {
+                        Ok(messages) => this.update(messages),
+                        Err(e) => log::warn!(
+                            "MessagingClient: Failed to retrieve TextMessageList ({}) with reason: {}",
+                            link.href,
+                            e
+                        ),
+                    }
+                }
+            })
+            .await;
+        Ok(handle)
+    }
+
+    /// Replace the messages currently held with the given messages.
+    ///
+    /// Used to apply the contents of a TextMessageList received by other means, such as the items of a subscription's [`ListNotification`].
+    ///
+    /// [`ListNotification`]: crate::pubsub::ListNotification
+    pub fn update(&self, messages: Vec<TextMessage>) {
+        *self.messages.write().unwrap() = messages;
+    }
+
+    /// Return the messages held that are active at the current time, with the global time offset applied.
+    ///
+    /// Messages are in order of descending priority.
+    pub fn active_messages(&self) -> Vec<TextMessage> {
+        self.active_messages_at(current_time_with_offset())
+    }
+
+    /// Return the messages held whose `interval` contains the given time, and that have not been cancelled or superseded.
+    ///
+    /// Messages are in order of descending priority.
+    pub fn active_messages_at(&self, time: SEPTime) -> Vec<TextMessage> {
+        let mut out: Vec<TextMessage> = self
+            .messages
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|msg| {
+                msg.interval.contains(time)
+                    && !matches!(
+                        msg.event_status.current_status,
+                        EventStatus::Cancelled
+                            | EventStatus::CancelledRandom
+                            | EventStatus::Superseded
+                    )
+            })
+            .cloned()
+            .collect();
+        out.sort_by_key(|msg| std::cmp::Reverse(msg.priority as u8));
+        out
+    }
+
+    /// Confirm that the held message with the given mRID has been acknowledged by a user.
+    ///
+    /// If the message requires it, a Response with the status [`ResponseStatus::EventAcknowledge`] is sent to the message's `replyTo`.
+    /// Returns `None` if no response was required.
+    pub async fn confirm(&self, mrid: &MRIDType) -> Result<Option<SEPResponse>> {
+        let msg = self
+            .messages
+            .read()
+            .unwrap()
+            .iter()
+            .find(|msg| &msg.mrid == mrid)
+            .cloned()
+            .with_context(|| format!("No TextMessage with mRID {mrid} is held"))?;
+        let status = ResponseStatus::EventAcknowledge;
+        if msg.reply_to.is_none() || !msg_response_required(status, msg.response_required) {
+            log::debug!(
+                "MessagingClient: Not sending Messaging response for event {} as a response with status {:?} was not required",
+                msg.mrid,
+                status
+            );
+            return Ok(None);
+        }
+        self.client
+            .send_msg_response(self.lfdi, &msg, status, current_time_with_offset())
+            .await
+            .map(Some)
+    }
+}
+
+#[tokio::test]
+async fn messaging_client_active_confirm() {
+    use crate::client::ScriptedTransport;
+    use hyper::{Body, StatusCode};
+    use sep2_common::{
+        packages::{
+            identification::{ListLink, ResponseRequired},
+            messaging::PriorityType,
+            primitives::{HexBinary128, Int64},
+            types::DateTimeInterval,
+        },
+        serialize,
+    };
+
+    let message = |mrid: u128, start: i64, priority, status| TextMessage {
+        mrid: HexBinary128(mrid),
+        priority,
+        interval: DateTimeInterval {
+            start: Int64(start),
+            duration: Uint32(100),
+        },
+        event_status: sep2_common::packages::objects::EventStatus {
+            current_status: status,
+            ..Default::default()
+        },
+        reply_to: Some("/rsps/0/rsp".to_owned()),
+        response_required: Some(ResponseRequired::ResponseRequired),
+        ..Default::default()
+    };
+    let list = TextMessageList {
+        all: Uint32(4),
+        results: Uint32(4),
+        text_message: vec![
+            message(1, 0, PriorityType::Low, EventStatus::Active),
+            message(2, 0, PriorityType::Critical, EventStatus::Active),
+            message(3, 500, PriorityType::High, EventStatus::Scheduled),
+            message(4, 0, PriorityType::High, EventStatus::Cancelled),
+        ],
+        ..Default::default()
+    };
+    let program = MessagingProgram {
+        text_message_list_link: Some(ListLink {
+            all: Some(Uint32(4)),
+            href: "/msg/0/txt".to_owned(),
+        }),
+        ..Default::default()
+    };
+
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        hyper::Response::new(Body::from(serialize(&list).unwrap())),
+        hyper::Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Body::empty())
+            .unwrap(),
+    ]);
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    let msgs = MessagingClient::new(client, HexBinary160::default());
+    msgs.refresh(&program).await.unwrap();
+
+    let active: Vec<_> = msgs
+        .active_messages_at(Int64(50).into())
+        .into_iter()
+        .map(|msg| msg.mrid)
+        .collect();
+    assert_eq!(active, vec![HexBinary128(2), HexBinary128(1)]);
+    assert!(msgs.active_messages_at(Int64(150).into()).is_empty());
+
+    let resp = msgs.confirm(&HexBinary128(2)).await.unwrap();
+    assert!(matches!(resp, Some(SEPResponse::NoContent)));
+    {
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].uri().path(), "/rsps/0/rsp");
+    }
+
+    // Messages that were never retrieved cannot be confirmed
+    assert!(msgs.confirm(&HexBinary128(5)).await.is_err());
+}
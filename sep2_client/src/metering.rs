@@ -0,0 +1,426 @@
+//! Metering Function Set
+//!
+//! Retrieving readings requires walking from a [`UsagePoint`], through it's [`MeterReading`]s, to their [`Reading`]s,
+//! and applying the [`ReadingType`] of each MeterReading to interpret the values of it's Readings.
+//! A [`MeteringReader`] performs this walk, returning readings with their values scaled, and their unit of measure.
+//!
+//! Conversely, a [`MirrorUploader`] POSTs readings to a [`MirrorUsagePoint`] at the `postRate` it advertises.
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use anyhow::{Context, Result};
+use sep2_common::packages::{
+    metering::{
+        MeterReading, MeterReadingList, Reading, ReadingList, ReadingSetList, ReadingType,
+        UsagePoint,
+    },
+    metering_mirror::{MirrorMeterReading, MirrorUsagePoint},
+    primitives::Uint32,
+    types::{AccumulationBehaviourType, DateTimeInterval, UomType},
+};
+
+use crate::client::{AdvertisedPostRate, Client, PollHandle};
+
+/// A [`Reading`], with it's value scaled as per it's [`ReadingType`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedReading {
+    /// The value of the reading, multiplied by the ReadingType's `powerOfTenMultiplier`, or `None` if the reading has no value.
+    pub value: Option<f64>,
+    /// The unit of measure of the value, as per the ReadingType
+    pub uom: Option<UomType>,
+    /// The time interval the reading applies to, if any
+    pub time_period: Option<DateTimeInterval>,
+    /// How the value accumulates over time, as per the ReadingType, defaulting to `NotApplicable`
+    pub accumulation: AccumulationBehaviourType,
+    /// The length of the interval of interval data, in seconds, as per the ReadingType's `intervalLength`
+    pub interval_length: Option<u32>,
+    /// The reading as retrieved from the server
+    pub reading: Reading,
+}
+
+impl DecodedReading {
+    /// Scale the given reading as per the given ReadingType.
+    pub fn new(reading: Reading, reading_type: &ReadingType) -> Self {
+        let multiplier = reading_type.power_of_ten_multiplier.unwrap_or_default() as i32;
+        DecodedReading {
+            value: reading
+                .value
+                .map(|value| value.0 as f64 * 10f64.powi(multiplier)),
+            uom: reading_type.uom,
+            time_period: reading.time_period.clone(),
+            accumulation: reading_type.accumulation_behaviour.unwrap_or_default(),
+            interval_length: reading_type.interval_length.map(|len| len.get()),
+            reading,
+        }
+    }
+
+    /// Whether the value is the quantity accumulated within the reading's interval, such that the values of consecutive readings can be summed,
+    /// rather than a running total, or a value measured at a single point in time.
+    pub fn is_delta(&self) -> bool {
+        self.accumulation == AccumulationBehaviourType::DeltaData
+    }
+}
+
+/// Scale the given reading as per the given ReadingType, as per [`DecodedReading::new`].
+pub fn decode_reading(reading: &Reading, reading_type: &ReadingType) -> DecodedReading {
+    DecodedReading::new(reading.clone(), reading_type)
+}
+
+/// The readings of a single [`MeterReading`] of a [`UsagePoint`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeterReadings {
+    pub meter_reading: MeterReading,
+    pub reading_type: ReadingType,
+    pub readings: Vec<DecodedReading>,
+}
+
+/// Retrieves & decodes the readings of a [`UsagePoint`], using a [`Client`].
+#[derive(Clone)]
+pub struct MeteringReader {
+    client: Client,
+    page_size: u8,
+}
+
+impl MeteringReader {
+    const DEFAULT_PAGE_SIZE: u8 = 25;
+
+    pub fn new(client: Client) -> Self {
+        MeteringReader {
+            client,
+            page_size: Self::DEFAULT_PAGE_SIZE,
+        }
+    }
+
+    /// Set the number of items requested in each page of a list.
+    ///
+    /// Defaults to 25, if this function is not called.
+    pub fn with_page_size(mut self, page_size: u8) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Retrieve the readings of every [`MeterReading`] of the [`UsagePoint`] at the given relative path.
+    ///
+    /// The readings of a MeterReading are those in each of it's ReadingSets, or, if it has none, it's most recent Reading.
+    /// All pages of each list are retrieved.
+    pub async fn readings(&self, usage_point_path: &str) -> Result<Vec<MeterReadings>> {
+        let usage_point: UsagePoint = self.client.get(usage_point_path).await?;
+        let link = usage_point
+            .meter_reading_list_link
+            .context("UsagePoint does not contain a MeterReadingListLink")?;
+        let meter_readings = self
+            .client
+            .get_list_link_all::<MeterReadingList>(&link, self.page_size)
+            .await?;
+        let mut out = Vec::with_capacity(meter_readings.len());
+        for meter_reading in meter_readings {
+            out.push(self.meter_readings(meter_reading).await?);
+        }
+        Ok(out)
+    }
+
+    /// Retrieve the readings of the given [`MeterReading`], as per [`MeteringReader::readings`].
+    pub async fn meter_readings(&self, meter_reading: MeterReading) -> Result<MeterReadings> {
+        let reading_type: ReadingType = self
+            .client
+            .get_link(&meter_reading.reading_type_link)
+            .await?;
+        let mut readings = vec![];
+        if let Some(link) = &meter_reading.reading_set_list_link {
+            let sets = self
+                .client
+                .get_list_link_all::<ReadingSetList>(link, self.page_size)
+                .await?;
+            for link in sets.iter().filter_map(|set| set.reading_list_link.as_ref()) {
+                readings.extend(
+                    self.client
+                        .get_list_link_all::<ReadingList>(link, self.page_size)
+                        .await?,
+                );
+            }
+        } else if let Some(link) = &meter_reading.reading_link {
+            readings.push(self.client.get_link(link).await?);
+        }
+        let readings = readings
+            .into_iter()
+            .map(|reading| DecodedReading::new(reading, &reading_type))
+            .collect();
+        Ok(MeterReadings {
+            meter_reading,
+            reading_type,
+            readings,
+        })
+    }
+}
+
+/// POSTs [`MirrorMeterReading`]s to a registered [`MirrorUsagePoint`], at the `postRate` advertised by the MirrorUsagePoint.
+///
+/// If the MirrorUsagePoint does not advertise a `postRate`, [`Client::default_post_rate`] is used.
+/// Changes to the `postRate`, such as those retrieved by [`MirrorUploader::start_refresh`], apply from the next upload onwards.
+#[derive(Clone)]
+pub struct MirrorUploader {
+    client: Client,
+    path: String,
+    // The post rate currently in use, in seconds
+    post_rate: Arc<AtomicU32>,
+}
+
+impl MirrorUploader {
+    /// Create an uploader for the MirrorUsagePoint at the given relative path, as registered with the server.
+    pub fn new(client: Client, mup_path: impl Into<String>, mup: &MirrorUsagePoint) -> Self {
+        let uploader = MirrorUploader {
+            post_rate: Arc::new(AtomicU32::new(client.default_post_rate().get())),
+            client,
+            path: mup_path.into(),
+        };
+        uploader.update(mup);
+        uploader
+    }
+
+    /// Retrieve the MirrorUsagePoint at the given relative path, and create an uploader for it.
+    pub async fn fetch(client: Client, mup_path: impl Into<String>) -> Result<Self> {
+        let path = mup_path.into();
+        let mup: MirrorUsagePoint = client.get(&path).await?;
+        Ok(Self::new(client, path, &mup))
+    }
+
+    /// The rate at which readings are currently uploaded
+    pub fn post_rate(&self) -> Uint32 {
+        Uint32(self.post_rate.load(Ordering::Relaxed))
+    }
+
+    /// Use the `postRate` of the given MirrorUsagePoint, such as one retrieved after the server has updated it.
+    ///
+    /// If it does not advertise a `postRate`, the current rate is kept.
+    pub fn update(&self, mup: &MirrorUsagePoint) {
+        let Some(rate) = mup.post_rate() else {
+            return;
+        };
+        let rate = rate.get().max(1);
+        let prev = self.post_rate.swap(rate, Ordering::Relaxed);
+        if prev != rate {
+            log::info!(
+                "Client: Uploading readings to {} every {rate} seconds, as per it's postRate",
+                self.path
+            );
+        }
+    }
+
+    /// Begin periodically POSTing the reading produced by `reading` to the MirrorUsagePoint, using the [`Client`]'s report infrastructure.
+    ///
+    /// The first reading is sent on the next tick of the poll task, and subsequent readings every `postRate` seconds thereafter,
+    /// with failed uploads retried as per [`Client::start_report`].
+    pub async fn start(
+        &self,
+        reading: impl Fn() -> MirrorMeterReading + Send + Sync + 'static,
+    ) -> PollHandle {
+        self.client
+            .start_report_with_rate(self.path.clone(), self.post_rate.clone(), reading)
+            .await
+    }
+
+    /// Begin polling the MirrorUsagePoint, updating the rate at which readings are uploaded whenever it's `postRate` changes.
+    ///
+    /// If a poll rate is not specified, [`Client::default_poll_rate`] is used.
+    pub async fn start_refresh(&self, poll_rate: Option<Uint32>) -> PollHandle {
+        let uploader = self.clone();
+        self.client
+            .start_poll(
+                self.path.clone(),
+                poll_rate,
+                move |mup: MirrorUsagePoint| {
+                    let uploader = uploader.clone();
+                    async move { uploader.update(&mup) }
+                },
+            )
+            .await
+    }
+}
+
+#[tokio::test]
+async fn paged_readings_scaled() {
+    use crate::client::ScriptedTransport;
+    use hyper::Body;
+    use sep2_common::{
+        packages::{
+            identification::{Link, ListLink},
+            metering::ReadingSet,
+            primitives::{Int48, Uint32},
+            types::PowerOfTenMultiplierType,
+        },
+        serialize,
+    };
+    use std::sync::Arc;
+
+    let respond = |xml: String| hyper::Response::new(Body::from(xml));
+    let list_link = |href: &str, all: u32| ListLink {
+        all: Some(Uint32(all)),
+        href: href.to_owned(),
+    };
+    let reading = |value| Reading {
+        value: Some(Int48(value)),
+        ..Default::default()
+    };
+    let usage_point = UsagePoint {
+        meter_reading_list_link: Some(list_link("/upt/0/mr", 1)),
+        href: Some("/upt/0".to_owned()),
+        ..Default::default()
+    };
+    let meter_reading = MeterReading {
+        reading_type_link: Link {
+            href: "/rt/0".to_owned(),
+        },
+        reading_set_list_link: Some(list_link("/upt/0/mr/0/rs", 1)),
+        ..Default::default()
+    };
+    let meter_readings = MeterReadingList {
+        all: Uint32(1),
+        results: Uint32(1),
+        meter_reading: vec![meter_reading],
+        ..Default::default()
+    };
+    let reading_type = ReadingType {
+        power_of_ten_multiplier: Some(PowerOfTenMultiplierType::Kilo),
+        uom: Some(UomType::W),
+        ..Default::default()
+    };
+    let reading_sets = ReadingSetList {
+        all: Uint32(1),
+        results: Uint32(1),
+        reading_set: vec![ReadingSet {
+            reading_list_link: Some(list_link("/upt/0/mr/0/rs/0/r", 3)),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let first_page = ReadingList {
+        all: Uint32(3),
+        results: Uint32(2),
+        reading: vec![reading(1), reading(-2)],
+        ..Default::default()
+    };
+    let second_page = ReadingList {
+        all: Uint32(3),
+        results: Uint32(1),
+        reading: vec![reading(3)],
+        ..Default::default()
+    };
+
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        respond(serialize(&usage_point).unwrap()),
+        respond(serialize(&meter_readings).unwrap()),
+        respond(serialize(&reading_type).unwrap()),
+        respond(serialize(&reading_sets).unwrap()),
+        respond(serialize(&first_page).unwrap()),
+        respond(serialize(&second_page).unwrap()),
+    ]);
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    let reader = MeteringReader::new(client).with_page_size(2);
+    let out = reader.readings("/upt/0").await.unwrap();
+    assert_eq!(out.len(), 1);
+    let values: Vec<_> = out[0].readings.iter().map(|r| r.value).collect();
+    assert_eq!(values, vec![Some(1000.0), Some(-2000.0), Some(3000.0)]);
+    assert!(out[0].readings.iter().all(|r| r.uom == Some(UomType::W)));
+
+    let requests = transport.requests.lock().unwrap();
+    assert_eq!(requests.len(), 6);
+    assert_eq!(requests[4].uri().query(), Some("s=0&l=2"));
+    assert_eq!(requests[5].uri().query(), Some("s=2&l=2"));
+}
+
+#[test]
+fn reading_decoded() {
+    use sep2_common::packages::{
+        primitives::{Int48, Uint32},
+        types::PowerOfTenMultiplierType,
+    };
+    let reading = Reading {
+        value: Some(Int48(1234)),
+        ..Default::default()
+    };
+    let reading_type = ReadingType {
+        accumulation_behaviour: Some(AccumulationBehaviourType::DeltaData),
+        interval_length: Some(Uint32(300)),
+        power_of_ten_multiplier: Some(PowerOfTenMultiplierType::Milli),
+        uom: Some(UomType::Wh),
+        ..Default::default()
+    };
+    let decoded = decode_reading(&reading, &reading_type);
+    assert_eq!(decoded.value, Some(1.234));
+    assert_eq!(decoded.uom, Some(UomType::Wh));
+    assert_eq!(decoded.interval_length, Some(300));
+    assert!(decoded.is_delta());
+
+    // Readings without a ReadingType's optional fields are unscaled
+    let decoded = decode_reading(&reading, &ReadingType::default());
+    assert_eq!(decoded.value, Some(1234.0));
+    assert_eq!(
+        decoded.accumulation,
+        AccumulationBehaviourType::NotApplicable
+    );
+    assert!(!decoded.is_delta());
+}
+
+#[tokio::test]
+async fn mirror_post_rate_followed() {
+    use crate::client::ScriptedTransport;
+    use hyper::{Body, StatusCode};
+    use sep2_common::serialize;
+
+    let mup = MirrorUsagePoint {
+        post_rate: Some(Uint32(60)),
+        ..Default::default()
+    };
+    let transport = Arc::new(ScriptedTransport::default());
+    transport
+        .responses
+        .lock()
+        .unwrap()
+        .push_back(hyper::Response::new(Body::from(serialize(&mup).unwrap())));
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    let uploader = MirrorUploader::fetch(client.clone(), "/mup/0")
+        .await
+        .unwrap();
+    assert_eq!(uploader.post_rate(), Uint32(60));
+
+    // Unadvertised rates keep the current rate
+    uploader.update(&MirrorUsagePoint::default());
+    assert_eq!(uploader.post_rate(), Uint32(60));
+
+    // Uploads are sent to the MirrorUsagePoint
+    let created = || {
+        let mut res = hyper::Response::new(Body::empty());
+        *res.status_mut() = StatusCode::CREATED;
+        res
+    };
+    transport
+        .responses
+        .lock()
+        .unwrap()
+        .extend([created(), created()]);
+    let handle = uploader.start(MirrorMeterReading::default).await;
+    client.force_polls().await;
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    {
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].uri().path(), "/mup/0");
+    }
+    let next = handle.time_until_next().unwrap();
+    assert!(next > std::time::Duration::from_secs(55));
+
+    // A changed rate applies from the next upload
+    uploader.update(&MirrorUsagePoint {
+        post_rate: Some(Uint32(5)),
+        ..Default::default()
+    });
+    assert_eq!(uploader.post_rate(), Uint32(5));
+    client.force_polls().await;
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    let next = handle.time_until_next().unwrap();
+    assert!(next <= std::time::Duration::from_secs(5));
+}
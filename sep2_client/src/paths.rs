@@ -0,0 +1,157 @@
+//! Resource Paths
+//!
+//! Sending a resource to the wrong endpoint, such as a `DERCapability` to a metering path, is an easy integration mistake,
+//! that is only caught when the server responds with 400 Bad Request.
+//!
+//! A [`PathRules`] associates each resource type with the patterns of the paths it may be sent to.
+//! When set on a [`Client`] using [`Client::set_path_rules`], resources sent in PUT & POST requests to a path matching none of their patterns are logged,
+//! or, if the rules are strict, rejected with [`ClientError::UnexpectedPath`] before the request is made.
+//!
+//! Servers are free to choose the hrefs of their resources, so [`PathRules::sample`] is only appropriate for servers using the sample URIs of IEEE 2030.5.
+//!
+//! [`Client`]: crate::client::Client
+//! [`Client::set_path_rules`]: crate::client::Client::set_path_rules
+//! [`ClientError::UnexpectedPath`]: crate::client::ClientError::UnexpectedPath
+
+use std::collections::HashMap;
+
+use sep2_common::traits::SEResource;
+
+use crate::client::ClientError;
+
+/// The paths each resource type may be sent to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathRules {
+    // Path patterns, by the name of the resource they apply to
+    patterns: HashMap<String, Vec<String>>,
+    strict: bool,
+}
+
+impl PathRules {
+    /// Create a set of rules that initially permits every resource to be sent to any path.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a set of rules matching the sample URIs used throughout IEEE 2030.5, for the resources a client commonly sends.
+    pub fn sample() -> Self {
+        const RESPONSE: &str = "/rsps/*/rsp";
+        Self::new()
+            .allow_for("EndDevice", "/edev")
+            .allow_for("EndDevice", "/edev/*")
+            .allow_for("DeviceInformation", "/edev/*/di")
+            .allow_for("PowerStatus", "/edev/*/ps")
+            .allow_for("Subscription", "/edev/*/sub")
+            .allow_for("Subscription", "/edev/*/sub/*")
+            .allow_for("LogEvent", "/edev/*/lel")
+            .allow_for("DERCapability", "/edev/*/der/*/dercap")
+            .allow_for("DERSettings", "/edev/*/der/*/derg")
+            .allow_for("DERStatus", "/edev/*/der/*/ders")
+            .allow_for("DERAvailability", "/edev/*/der/*/dera")
+            .allow_for("FlowReservationRequest", "/edev/*/frq")
+            .allow_for("FlowReservationRequest", "/edev/*/frq/*")
+            .allow_for("MirrorUsagePoint", "/mup")
+            .allow_for("MirrorUsagePoint", "/mup/*")
+            .allow_for("MirrorMeterReading", "/mup/*")
+            .allow_for("DERControlResponse", RESPONSE)
+            .allow_for("DrResponse", RESPONSE)
+            .allow_for("FlowReservationResponseResponse", RESPONSE)
+            .allow_for("PriceResponse", RESPONSE)
+            .allow_for("TextResponse", RESPONSE)
+            .allow_for("Response", RESPONSE)
+    }
+
+    /// Permit resources of type `R` to be sent to paths matching the given pattern.
+    ///
+    /// Patterns are `/` separated path segments, where a `*` segment matches any single segment.
+    /// i.e. `/edev/*/der/*/dercap` for the `DERCapability` of any `DER` of any `EndDevice`.
+    ///
+    /// Once a pattern has been added for a resource type, that type may only be sent to paths matching one of it's patterns.
+    pub fn allow<R: SEResource>(self, pattern: &str) -> Self {
+        self.allow_for(R::name(), pattern)
+    }
+
+    /// Permit resources with the given element name to be sent to paths matching the given pattern, as per [`PathRules::allow`].
+    pub fn allow_for(mut self, resource: &str, pattern: &str) -> Self {
+        self.patterns
+            .entry(resource.to_owned())
+            .or_default()
+            .push(pattern.trim_matches('/').to_owned());
+        self
+    }
+
+    /// Set whether resources sent to an unexpected path are rejected, rather than logged.
+    ///
+    /// Defaults to false, if this function is not called.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Whether a resource with the given element name may be sent to the given path.
+    ///
+    /// Resources without any patterns may be sent to any path.
+    pub fn permits(&self, resource: &str, path: &str) -> bool {
+        let Some(patterns) = self.patterns.get(resource) else {
+            return true;
+        };
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        patterns
+            .iter()
+            .any(|pattern| matches_pattern(pattern, path.trim_matches('/')))
+    }
+
+    /// Check that a resource of type `R` may be sent to the given path.
+    ///
+    /// Returns [`ClientError::UnexpectedPath`] if it may not, and the rules are strict, otherwise a warning is logged.
+    pub(crate) fn check<R: SEResource>(&self, path: &str) -> Result<(), ClientError> {
+        if self.permits(R::name(), path) {
+            return Ok(());
+        }
+        if self.strict {
+            return Err(ClientError::UnexpectedPath {
+                resource: R::name().to_owned(),
+                path: path.to_owned(),
+            });
+        }
+        log::warn!(
+            "Client: {} is being sent to {}, which does not match any of the paths it is expected at",
+            R::name(),
+            path
+        );
+        Ok(())
+    }
+}
+
+// Whether the given path, without leading or trailing slashes, matches the given pattern
+fn matches_pattern(pattern: &str, path: &str) -> bool {
+    let mut pattern = pattern.split('/');
+    let mut path = path.split('/');
+    loop {
+        match (pattern.next(), path.next()) {
+            (None, None) => return true,
+            (Some("*"), Some(segment)) if !segment.is_empty() => (),
+            (Some(expected), Some(segment)) if expected == segment => (),
+            _ => return false,
+        }
+    }
+}
+
+#[test]
+fn sample_paths() {
+    let rules = PathRules::sample();
+    assert!(rules.permits("DERCapability", "/edev/3/der/1/dercap"));
+    assert!(rules.permits("DERCapability", "/edev/3/der/1/dercap/"));
+    assert!(!rules.permits("DERCapability", "/mup/1"));
+    assert!(!rules.permits("DERCapability", "/edev/3/der/dercap"));
+    assert!(!rules.permits("DERCapability", "/edev/3/der/1/dercap/2"));
+    assert!(rules.permits("TextResponse", "/rsps/0/rsp?s=1"));
+    assert!(rules.permits("MirrorMeterReading", "/mup/1"));
+    // Resources without patterns may be sent anywhere
+    assert!(rules.permits("Reading", "/mup/1"));
+    assert!(PathRules::new().permits("DERCapability", "/mup/1"));
+}
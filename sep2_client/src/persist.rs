@@ -0,0 +1,602 @@
+//! Persistent Client State
+//!
+//! A device that restarts loses every poll started on it's [`Client`], and every Subscription created by a [`SubscriptionManager`],
+//! requiring the DeviceCapability to be walked again to re-establish them.
+//!
+//! When a [`StateStore`] is set using [`Client::set_state_store`], the path, resource type & rate of each poll started using [`Client::start_poll`] & it's variants,
+//! and the route, resource type & Location of each Subscription created by a [`SubscriptionManager`] sharing that client, are saved to it as a [`PersistedState`].
+//!
+//! On startup, polls are re-established using [`Client::resume_polls`], and Subscriptions re-validated using [`SubscriptionManager::resume`].
+//! As callbacks cannot be persisted, they are supplied again as [`ResumeCallbacks`], by resource type.
+//!
+//! [`SubscriptionManager`]: crate::pubsub::SubscriptionManager
+//! [`SubscriptionManager::resume`]: crate::pubsub::SubscriptionManager::resume
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use anyhow::{bail, Context, Result};
+use sep2_common::{
+    packages::primitives::Uint32,
+    traits::{SEIdentifiedObject, SEList, SEResource},
+};
+
+use crate::client::{Client, ListChangeCallback, PollCallback, PollHandle, PollRefCallback};
+#[cfg(feature = "pubsub")]
+use crate::pubsub::{ListRouteCallback, RouteCallback, RouteTable};
+
+// First line of an encoded PersistedState, identifying the version of the encoding
+const HEADER: &str = "sep2_client state 1";
+
+/// A poll started using [`Client::start_poll`], or one of it's variants.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollRecord {
+    /// The relative path polled
+    pub path: String,
+    /// The element name of the resource type polled
+    pub resource: String,
+    /// The poll rate, in seconds
+    pub poll_rate: u32,
+    /// How the poll was started
+    pub kind: PollKind,
+}
+
+/// The function a persisted poll was started using, and is resumed using.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PollKind {
+    /// [`Client::start_poll`]
+    #[default]
+    Resource,
+    /// [`Client::start_poll_ref`]
+    Ref,
+    /// [`Client::start_poll_if_modified`]
+    IfModified,
+    /// [`Client::start_poll_changes`]
+    Changes,
+}
+
+impl PollKind {
+    // The first field of an encoded record of a poll of this kind
+    fn tag(self) -> &'static str {
+        match self {
+            PollKind::Resource => "poll",
+            PollKind::Ref => "poll_ref",
+            PollKind::IfModified => "poll_if_modified",
+            PollKind::Changes => "poll_changes",
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        [
+            PollKind::Resource,
+            PollKind::Ref,
+            PollKind::IfModified,
+            PollKind::Changes,
+        ]
+        .into_iter()
+        .find(|kind| kind.tag() == tag)
+    }
+}
+
+/// A Subscription created using a [`SubscriptionManager`](crate::pubsub::SubscriptionManager).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionRecord {
+    /// The route the notifications of the Subscription are received on
+    pub route: String,
+    /// The element name of the resource type received on the route
+    pub resource: String,
+    /// The relative path of the SubscriptionList the Subscription was created on
+    pub subscription_list: String,
+    /// The href of the subscribed resource
+    pub subscribed_resource: String,
+    /// The Location of the Subscription
+    pub location: String,
+    /// The `limit` of the Subscription
+    pub limit: u32,
+}
+
+/// The polls & Subscriptions of a [`Client`], as saved to a [`StateStore`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PersistedState {
+    pub polls: Vec<PollRecord>,
+    pub subscriptions: Vec<SubscriptionRecord>,
+}
+
+impl PersistedState {
+    /// Encode the state as text, with one record per line.
+    ///
+    /// Records containing a tab or newline character, which no valid href contains, are omitted with a warning.
+    pub fn encode(&self) -> String {
+        let mut out = format!("{HEADER}\n");
+        let polls = self.polls.iter().map(|poll| {
+            vec![
+                poll.kind.tag().to_owned(),
+                poll.resource.clone(),
+                poll.poll_rate.to_string(),
+                poll.path.clone(),
+            ]
+        });
+        let subs = self.subscriptions.iter().map(|sub| {
+            vec![
+                "subscription".to_owned(),
+                sub.resource.clone(),
+                sub.limit.to_string(),
+                sub.route.clone(),
+                sub.subscription_list.clone(),
+                sub.subscribed_resource.clone(),
+                sub.location.clone(),
+            ]
+        });
+        for fields in polls.chain(subs) {
+            if fields.iter().any(|field| field.contains(['\t', '\n'])) {
+                log::warn!("StateStore: Omitting record that cannot be encoded: {fields:?}");
+                continue;
+            }
+            out.push_str(&fields.join("\t"));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Decode a state encoded using [`PersistedState::encode`].
+    pub fn decode(encoded: &str) -> Result<Self> {
+        let mut lines = encoded.lines();
+        if lines.next() != Some(HEADER) {
+            bail!("Persisted state does not begin with \"{HEADER}\"");
+        }
+        let mut out = PersistedState::default();
+        for line in lines.filter(|line| !line.is_empty()) {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let kind = fields.first().and_then(|tag| PollKind::from_tag(tag));
+            match (kind, fields.as_slice()) {
+                (Some(kind), [_, resource, rate, path]) => out.polls.push(PollRecord {
+                    path: path.to_string(),
+                    resource: resource.to_string(),
+                    poll_rate: rate
+                        .parse()
+                        .context("Invalid poll rate in persisted state")?,
+                    kind,
+                }),
+                (_, ["subscription", resource, limit, route, list, subscribed, location]) => {
+                    out.subscriptions.push(SubscriptionRecord {
+                        route: route.to_string(),
+                        resource: resource.to_string(),
+                        subscription_list: list.to_string(),
+                        subscribed_resource: subscribed.to_string(),
+                        location: location.to_string(),
+                        limit: limit.parse().context("Invalid limit in persisted state")?,
+                    })
+                }
+                _ => bail!("Invalid record in persisted state: {line}"),
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// A caller-supplied store for the [`PersistedState`] of a [`Client`], set using [`Client::set_state_store`].
+///
+/// The state is saved whenever it changes, so implementations should be cheap, and must not block for long.
+pub trait StateStore: Send + Sync + 'static {
+    fn save(&self, state: &PersistedState) -> Result<()>;
+    /// Load the most recently saved state, or `None` if no state has been saved.
+    fn load(&self) -> Result<Option<PersistedState>>;
+}
+
+/// A [`StateStore`] that saves the encoded state to a file, replacing it atomically.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    path: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileStore { path: path.into() }
+    }
+}
+
+impl StateStore for FileStore {
+    fn save(&self, state: &PersistedState) -> Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, state.encode())
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to replace {}", self.path.display()))
+    }
+
+    fn load(&self) -> Result<Option<PersistedState>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(encoded) => PersistedState::decode(&encoded).map(Some),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("Failed to read {}", self.path.display())),
+        }
+    }
+}
+
+// The state of a Client, and the store it is saved to, if any
+#[derive(Default)]
+pub(crate) struct StateRecorder {
+    state: Mutex<PersistedState>,
+    // Records loaded from the store that have not yet been resumed, which are saved alongside the current state
+    pending: Mutex<PersistedState>,
+    store: RwLock<Option<Arc<dyn StateStore>>>,
+}
+
+impl StateRecorder {
+    pub(crate) fn set_store(&self, store: Arc<dyn StateStore>) -> Result<()> {
+        let loaded = store.load()?.unwrap_or_default();
+        *self.pending.lock().unwrap() = loaded;
+        *self.store.write().unwrap() = Some(store);
+        Ok(())
+    }
+
+    pub(crate) fn snapshot(&self) -> PersistedState {
+        self.state.lock().unwrap().clone()
+    }
+
+    // Take the persisted polls that have not yet been resumed
+    pub(crate) fn take_polls(&self) -> Result<Vec<PollRecord>> {
+        if self.store.read().unwrap().is_none() {
+            bail!("Client has no StateStore to resume from");
+        }
+        Ok(std::mem::take(&mut self.pending.lock().unwrap().polls))
+    }
+
+    // Take the persisted Subscriptions on the given SubscriptionList that have not yet been resumed
+    #[cfg_attr(not(feature = "pubsub"), allow(dead_code))]
+    pub(crate) fn take_subscriptions(
+        &self,
+        subscription_list: &str,
+    ) -> Result<Vec<SubscriptionRecord>> {
+        if self.store.read().unwrap().is_none() {
+            bail!("Client has no StateStore to resume from");
+        }
+        let mut pending = self.pending.lock().unwrap();
+        let (out, rest) = std::mem::take(&mut pending.subscriptions)
+            .into_iter()
+            .partition(|sub| sub.subscription_list == subscription_list);
+        pending.subscriptions = rest;
+        Ok(out)
+    }
+
+    pub(crate) fn record_poll(&self, record: PollRecord) {
+        self.state.lock().unwrap().polls.push(record);
+        self.save();
+    }
+
+    pub(crate) fn clear_polls(&self) {
+        self.state.lock().unwrap().polls.clear();
+        self.save();
+    }
+
+    #[cfg_attr(not(feature = "pubsub"), allow(dead_code))]
+    pub(crate) fn record_subscription(&self, record: SubscriptionRecord) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.subscriptions.retain(|sub| sub.route != record.route);
+            state.subscriptions.push(record);
+        }
+        self.save();
+    }
+
+    #[cfg_attr(not(feature = "pubsub"), allow(dead_code))]
+    pub(crate) fn remove_subscription(&self, route: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .subscriptions
+            .retain(|sub| sub.route != route);
+        self.save();
+    }
+
+    // Save the current state, and any records not yet resumed, to the store, if one is set, logging any failure
+    pub(crate) fn save(&self) {
+        let Some(store) = self.store.read().unwrap().clone() else {
+            return;
+        };
+        let mut state = self.snapshot();
+        {
+            let pending = self.pending.lock().unwrap();
+            state.polls.extend(pending.polls.iter().cloned());
+            state
+                .subscriptions
+                .extend(pending.subscriptions.iter().cloned());
+        }
+        if let Err(err) = store.save(&state) {
+            log::warn!("StateStore: Failed to save client state: {err}");
+        }
+    }
+}
+
+type PollFactory = Box<
+    dyn Fn(&Client, String, Uint32) -> Pin<Box<dyn Future<Output = PollHandle> + Send>>
+        + Send
+        + Sync,
+>;
+
+#[cfg(feature = "pubsub")]
+type RouteFactory = Box<dyn Fn(&RouteTable, &str) -> Result<()> + Send + Sync>;
+
+/// The callbacks used to re-establish persisted polls & Subscriptions, by the element name of their resource type.
+///
+/// Each callback is constructed from the path polled, or route notified, such that records of the same type can be told apart.
+#[derive(Default)]
+pub struct ResumeCallbacks {
+    polls: HashMap<(PollKind, String), PollFactory>,
+    #[cfg(feature = "pubsub")]
+    routes: HashMap<String, RouteFactory>,
+}
+
+impl ResumeCallbacks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resume persisted polls of resources of type `T` with the callback produced by `callback`, as per [`Client::start_poll`].
+    pub fn poll<T, C>(mut self, callback: impl Fn(&str) -> C + Send + Sync + 'static) -> Self
+    where
+        T: SEResource,
+        C: PollCallback<T>,
+    {
+        let factory: PollFactory = Box::new(move |client, path, poll_rate| {
+            let client = client.clone();
+            let callback = callback(&path);
+            Box::pin(async move { client.start_poll(path, Some(poll_rate), callback).await })
+        });
+        self.polls
+            .insert((PollKind::Resource, T::name().to_owned()), factory);
+        self
+    }
+
+    /// Resume persisted polls of resources of type `T` with the callback produced by `callback`, as per [`Client::start_poll_ref`].
+    pub fn poll_ref<T, C>(mut self, callback: impl Fn(&str) -> C + Send + Sync + 'static) -> Self
+    where
+        T: SEResource,
+        C: PollRefCallback<T>,
+    {
+        let factory: PollFactory = Box::new(move |client, path, poll_rate| {
+            let client = client.clone();
+            let callback = callback(&path);
+            Box::pin(async move { client.start_poll_ref(path, Some(poll_rate), callback).await })
+        });
+        self.polls
+            .insert((PollKind::Ref, T::name().to_owned()), factory);
+        self
+    }
+
+    /// Resume persisted polls of resources of type `T` with the callback produced by `callback`, as per [`Client::start_poll_if_modified`].
+    pub fn poll_if_modified<T, C>(
+        mut self,
+        callback: impl Fn(&str) -> C + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: SEResource,
+        C: PollCallback<T>,
+    {
+        let factory: PollFactory = Box::new(move |client, path, poll_rate| {
+            let client = client.clone();
+            let callback = callback(&path);
+            Box::pin(async move {
+                client
+                    .start_poll_if_modified(path, Some(poll_rate), callback)
+                    .await
+            })
+        });
+        self.polls
+            .insert((PollKind::IfModified, T::name().to_owned()), factory);
+        self
+    }
+
+    /// Resume persisted polls of list resources of type `L` with the callback produced by `callback`, as per [`Client::start_poll_changes`].
+    pub fn poll_changes<L, C>(
+        mut self,
+        callback: impl Fn(&str) -> C + Send + Sync + 'static,
+    ) -> Self
+    where
+        L: SEList,
+        L::Inner: SEIdentifiedObject + Send,
+        C: ListChangeCallback<L::Inner>,
+    {
+        let factory: PollFactory = Box::new(move |client, path, poll_rate| {
+            let client = client.clone();
+            let callback = callback(&path);
+            Box::pin(async move {
+                client
+                    .start_poll_changes::<L>(path, Some(poll_rate), callback)
+                    .await
+            })
+        });
+        self.polls
+            .insert((PollKind::Changes, L::name().to_owned()), factory);
+        self
+    }
+
+    /// Resume persisted Subscriptions to resources of type `T` with the callback produced by `callback`,
+    /// as per [`SubscriptionManager::subscribe`](crate::pubsub::SubscriptionManager::subscribe).
+    #[cfg(feature = "pubsub")]
+    pub fn route<T, C>(mut self, callback: impl Fn(&str) -> C + Send + Sync + 'static) -> Self
+    where
+        T: SEResource,
+        C: RouteCallback<T>,
+    {
+        let factory: RouteFactory =
+            Box::new(move |routes, route| routes.add(route, callback(route)));
+        self.routes.insert(T::name().to_owned(), factory);
+        self
+    }
+
+    /// Resume persisted Subscriptions to list resources of type `L` with the callback produced by `callback`,
+    /// as per [`SubscriptionManager::subscribe_list`](crate::pubsub::SubscriptionManager::subscribe_list).
+    #[cfg(feature = "pubsub")]
+    pub fn list_route<L, C>(mut self, callback: impl Fn(&str) -> C + Send + Sync + 'static) -> Self
+    where
+        L: SEList,
+        L::Inner: SEResource,
+        C: ListRouteCallback<L>,
+    {
+        let factory: RouteFactory =
+            Box::new(move |routes, route| routes.add_list(route, callback(route)));
+        self.routes.insert(L::name().to_owned(), factory);
+        self
+    }
+
+    // Add the route of a persisted Subscription, returning false if there is no callback for it's resource type
+    #[cfg(feature = "pubsub")]
+    pub(crate) fn add_route(
+        &self,
+        routes: &RouteTable,
+        record: &SubscriptionRecord,
+    ) -> Result<bool> {
+        match self.routes.get(&record.resource) {
+            Some(factory) => factory(routes, &record.route).map(|_| true),
+            None => Ok(false),
+        }
+    }
+}
+
+impl Client {
+    /// Set the store the polls of this client, and the Subscriptions of any [`SubscriptionManager`](crate::pubsub::SubscriptionManager) using it, are saved to.
+    ///
+    /// The state previously saved to the store is loaded, to be resumed using [`Client::resume_polls`] & [`SubscriptionManager::resume`].
+    /// Until they are resumed, those records are retained whenever the state is saved, which occurs whenever it changes.
+    ///
+    /// Returns an error if the previously saved state could not be loaded.
+    ///
+    /// [`SubscriptionManager::resume`]: crate::pubsub::SubscriptionManager::resume
+    pub fn set_state_store(&self, store: impl StateStore) -> Result<()> {
+        self.state_recorder().set_store(Arc::new(store))
+    }
+
+    /// Return the polls & Subscriptions currently recorded, as they would be saved to a [`StateStore`].
+    pub fn persisted_state(&self) -> PersistedState {
+        self.state_recorder().snapshot()
+    }
+
+    /// Re-establish the polls saved to this client's [`StateStore`], such as by a previous run of the device.
+    ///
+    /// Each poll is started as per [`Client::start_poll`], or the variant it was started using, at it's persisted rate,
+    /// with the callback registered for it's resource type & variant.
+    /// Polls of resource types without a callback are logged, and discarded.
+    ///
+    /// Returns an error if no [`StateStore`] is set.
+    pub async fn resume_polls(&self, callbacks: &ResumeCallbacks) -> Result<Vec<PollHandle>> {
+        let polls = self.state_recorder().take_polls()?;
+        let mut out = vec![];
+        for poll in polls {
+            match callbacks.polls.get(&(poll.kind, poll.resource.clone())) {
+                Some(factory) => {
+                    log::info!(
+                        "Client: Resuming poll for Resource {} at {}",
+                        poll.resource,
+                        poll.path
+                    );
+                    out.push(factory(self, poll.path, Uint32(poll.poll_rate)).await);
+                }
+                None => log::warn!(
+                    "Client: No callback for persisted poll of Resource {} at {}, discarding it",
+                    poll.resource,
+                    poll.path
+                ),
+            }
+        }
+        self.state_recorder().save();
+        Ok(out)
+    }
+}
+
+#[test]
+fn state_encoded() {
+    let state = PersistedState {
+        polls: vec![
+            PollRecord {
+                path: "/dcap".to_owned(),
+                resource: "DeviceCapability".to_owned(),
+                poll_rate: 900,
+                kind: PollKind::Resource,
+            },
+            PollRecord {
+                path: "/edev".to_owned(),
+                resource: "EndDeviceList".to_owned(),
+                poll_rate: 300,
+                kind: PollKind::Changes,
+            },
+        ],
+        subscriptions: vec![SubscriptionRecord {
+            route: "/dcap".to_owned(),
+            resource: "DeviceCapability".to_owned(),
+            subscription_list: "/edev/3/sub".to_owned(),
+            subscribed_resource: "/dcap".to_owned(),
+            location: "/edev/3/sub/1".to_owned(),
+            limit: 1,
+        }],
+    };
+    assert_eq!(PersistedState::decode(&state.encode()).unwrap(), state);
+    assert!(PersistedState::decode("poll\tDeviceCapability\t900\t/dcap").is_err());
+}
+
+#[tokio::test]
+async fn polls_resumed() {
+    use sep2_common::packages::{dcap::DeviceCapability, time::Time};
+
+    #[derive(Clone, Default)]
+    struct MemoryStore(Arc<Mutex<Option<PersistedState>>>);
+
+    impl StateStore for MemoryStore {
+        fn save(&self, state: &PersistedState) -> Result<()> {
+            *self.0.lock().unwrap() = Some(state.clone());
+            Ok(())
+        }
+
+        fn load(&self) -> Result<Option<PersistedState>> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+    }
+
+    let transport = Arc::new(crate::client::ScriptedTransport::default());
+    let store = MemoryStore::default();
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    client.set_state_store(store.clone()).unwrap();
+    client
+        .start_poll("/dcap", Some(Uint32(900)), |_: DeviceCapability| async {})
+        .await;
+    client
+        .start_poll("/tm", Some(Uint32(3600)), |_: Time| async {})
+        .await;
+    client
+        .start_poll_ref("/dcap/tm", Some(Uint32(3600)), |_: &Time| {})
+        .await;
+    assert_eq!(store.load().unwrap().unwrap().polls.len(), 3);
+
+    // After a restart, only resource types with a callback for their poll variant are resumed
+    let client = Client::with_transport("http://fake", transport, None);
+    client.set_state_store(store.clone()).unwrap();
+    let callbacks = ResumeCallbacks::new()
+        .poll(|_| |_: DeviceCapability| async {})
+        .poll_ref(|_| |_: &Time| {});
+    let handles = client.resume_polls(&callbacks).await.unwrap();
+    assert_eq!(handles.len(), 2);
+    assert!(client.resume_polls(&callbacks).await.unwrap().is_empty());
+    assert_eq!(
+        client.persisted_state().polls,
+        vec![
+            PollRecord {
+                path: "/dcap".to_owned(),
+                resource: "DeviceCapability".to_owned(),
+                poll_rate: 900,
+                kind: PollKind::Resource,
+            },
+            PollRecord {
+                path: "/dcap/tm".to_owned(),
+                resource: "Time".to_owned(),
+                poll_rate: 3600,
+                kind: PollKind::Ref,
+            },
+        ]
+    );
+    assert_eq!(store.load().unwrap().unwrap(), client.persisted_state());
+}
@@ -1,9 +1,6 @@
 //! Pricing Function Set
 
-use std::{
-    sync::{atomic::AtomicI64, Arc},
-    time::Duration,
-};
+use std::{sync::Arc, time::Duration};
 
 use sep2_common::packages::{
     identification::ResponseStatus,
@@ -14,9 +11,9 @@ use sep2_common::packages::{
 use tokio::sync::{broadcast::Receiver, RwLock};
 
 use crate::{
-    client::{Client, SEPResponse},
+    client::{pricing_response_required, Client, SEPResponse},
     device::SEDevice,
-    event::{EIPair, EIStatus, EventCallback, EventInstance, Events, Schedule, Scheduler},
+    event::{declined, EIPair, EIStatus, EventCallback, EventInstance, Schedule, Scheduler},
 };
 
 /// Given two TimeTariffIntervals, determine which is superseded, and which is superseding, or None if neither supersede one another
@@ -65,6 +62,11 @@ impl Schedule<TimeTariffInterval> {
             let target = events.get(&mrid).unwrap();
             let resp = (self.handler)(target).await;
             self.auto_pricing_response(target.event(), resp).await;
+            // If the device cannot comply with the event, it is never completed
+            if declined(resp) {
+                drop(events);
+                self.cancel_declined(&mrid).await;
+            }
         }
     }
 
@@ -115,6 +117,22 @@ impl Schedule<TimeTariffInterval> {
     }
 
     async fn auto_pricing_response(&self, event: &TimeTariffInterval, status: ResponseStatus) {
+        // Only POST the responses the server has asked for, to the location it has asked for
+        if event.reply_to.is_none() {
+            log::debug!(
+                "Client: Not sending Pricing response for event {} as it has no replyTo field",
+                event.mrid
+            );
+            return;
+        }
+        if !pricing_response_required(status, event.response_required) {
+            log::debug!(
+                "Client: Not sending Pricing response for event {} as a response with status {:?} was not required",
+                event.mrid,
+                status
+            );
+            return;
+        }
         match self
             .client
             .send_pricing_response(
@@ -155,22 +173,10 @@ impl Scheduler<TimeTariffInterval> for Schedule<TimeTariffInterval> {
         handler: impl EventCallback<TimeTariffInterval>,
         tickrate: Duration,
     ) -> Self {
-        let (tx, rx) = tokio::sync::broadcast::channel::<()>(1);
-        let out = Schedule {
-            client,
-            device,
-            events: Arc::new(RwLock::new(Events::new())),
-            handler: Arc::new(move |ei| {
-                let handler = handler.clone();
-                Box::pin(async move { handler.event_update(ei).await })
-            }),
-            bc_sd: tx.clone(),
-            tickrate,
-            time_offset: Arc::new(AtomicI64::new(0)),
-        };
-        tokio::spawn(out.clone().clean_events(rx));
-        tokio::spawn(out.clone().pricing_start_task(tx.subscribe()));
-        tokio::spawn(out.clone().pricing_end_task(tx.subscribe()));
+        let out = Schedule::base(client, device, handler, tickrate);
+        tokio::spawn(out.clone().clean_events(out.bc_sd.subscribe()));
+        tokio::spawn(out.clone().pricing_start_task(out.bc_sd.subscribe()));
+        tokio::spawn(out.clone().pricing_end_task(out.bc_sd.subscribe()));
         out
     }
 
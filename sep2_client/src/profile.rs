@@ -0,0 +1,297 @@
+//! Serialization Profiles
+//!
+//! Revisions of IEEE 2030.5, and profiles of it such as CSIP-AUS, differ in which elements a resource may contain.
+//! Some servers reject resources containing elements from a newer revision than the one they implement.
+//!
+//! A [`SchemaProfile`] describes the elements of each resource that are not part of a given revision or profile.
+//! When set on a [`Client`] using [`Client::set_schema_profile`], those elements are omitted from all resources sent in PUT & POST requests.
+//!
+//! Conversely, some servers do not conform to the schema's use of XML namespaces, such as by using an undeclared prefix.
+//! When [`Client::set_lenient_namespaces`] is enabled, the namespaces of resources received by a [`Client`] are normalized before they are deserialized.
+//!
+//! [`Client`]: crate::client::Client
+//! [`Client::set_schema_profile`]: crate::client::Client::set_schema_profile
+//! [`Client::set_lenient_namespaces`]: crate::client::Client::set_lenient_namespaces
+
+use std::collections::{HashMap, HashSet};
+
+use sep2_common::traits::SEResource;
+
+/// A revision, or profile, of the IEEE 2030.5 schema to target when serializing resources.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaProfile {
+    name: String,
+    // Paths of omitted elements, by the name of the resource they are within
+    omitted: HashMap<String, HashSet<String>>,
+}
+
+impl SchemaProfile {
+    /// Create a profile with the given name, that initially omits no elements.
+    ///
+    /// The name is used only for logging.
+    pub fn new(name: impl Into<String>) -> Self {
+        SchemaProfile {
+            name: name.into(),
+            omitted: HashMap::new(),
+        }
+    }
+
+    /// Omit the element at the given path from resources of type `R`.
+    ///
+    /// Paths are the `/` separated local names of elements, relative to the resource itself.
+    /// i.e. `DERControlBase/opModFreqDroop` for the `opModFreqDroop` element of a `DERControl`.
+    pub fn omit<R: SEResource>(self, path: &str) -> Self {
+        self.omit_from(R::name(), path)
+    }
+
+    /// Omit the element at the given path from resources with the given element name, as per [`SchemaProfile::omit`].
+    pub fn omit_from(mut self, resource: &str, path: &str) -> Self {
+        self.omitted
+            .entry(resource.to_owned())
+            .or_default()
+            .insert(path.trim_matches('/').to_owned());
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Remove all elements not present in this profile from the given serialized resource.
+    ///
+    /// A warning is logged for every element removed, as they were set on the resource.
+    pub(crate) fn apply(&self, xml: String) -> String {
+        let Some(root) = root_element(&xml) else {
+            return xml;
+        };
+        let Some(omitted) = self.omitted.get(root) else {
+            return xml;
+        };
+        let (out, dropped) = omit_elements(&xml, omitted);
+        for path in &dropped {
+            log::warn!(
+                "Client: Omitting {path} from {root}, as it is not part of the {} profile",
+                self.name
+            );
+        }
+        out
+    }
+}
+
+// The namespace of all IEEE 2030.5 resources
+const SEP_NS: &str = "urn:ieee:std:2030.5:ns";
+
+/// Normalize the namespaces of an XML document received from a server that does not conform to IEEE 2030.5.
+///
+/// Element prefixes that are undeclared, or bound to the IEEE 2030.5 namespace, are removed, alongside their declarations,
+/// and the default namespace of the root element is set to the IEEE 2030.5 namespace.
+/// Prefixes bound to other namespaces, such as that of CSIP-AUS, are left as-is.
+pub(crate) fn normalize_namespaces(xml: &str) -> String {
+    // Prefixes bound to namespaces other than IEEE 2030.5's
+    let mut foreign: HashSet<&str> = HashSet::new();
+    for (tag, _) in tags(xml) {
+        let (_, attrs, _) = split_tag(tag);
+        for (name, value) in attributes(attrs) {
+            if let Some(prefix) = name.strip_prefix("xmlns:") {
+                if value.trim_matches(['"', '\'']) != SEP_NS {
+                    foreign.insert(prefix);
+                }
+            }
+        }
+    }
+    let strip = |name: &'_ str| -> String {
+        match name.split_once(':') {
+            Some((prefix, local)) if !foreign.contains(prefix) => local.to_owned(),
+            _ => name.to_owned(),
+        }
+    };
+    let mut out = String::with_capacity(xml.len());
+    let mut copied = 0;
+    let mut root = true;
+    for (tag, start) in tags(xml) {
+        out.push_str(&xml[copied..start]);
+        copied = start + tag.len() + 2;
+        if tag.starts_with(['?', '!']) {
+            out.push_str(&xml[start..copied]);
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            out.push_str(&format!("</{}>", strip(name.trim())));
+            continue;
+        }
+        let (name, attrs, self_closing) = split_tag(tag);
+        out.push('<');
+        out.push_str(&strip(name));
+        if root {
+            out.push_str(&format!(" xmlns=\"{SEP_NS}\""));
+        }
+        for (attr, value) in attributes(attrs) {
+            let stripped = attr
+                .strip_prefix("xmlns:")
+                .is_some_and(|prefix| !foreign.contains(prefix));
+            if stripped || (root && attr == "xmlns") {
+                continue;
+            }
+            out.push_str(&format!(" {attr}={value}"));
+        }
+        if self_closing {
+            out.push('/');
+        }
+        out.push('>');
+        root = false;
+    }
+    out.push_str(&xml[copied..]);
+    out
+}
+
+// Iterate over the contents of each tag in the given XML document, alongside the offset of it's opening `<`
+fn tags(xml: &str) -> impl Iterator<Item = (&str, usize)> {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        let start = pos + xml[pos..].find('<')?;
+        let len = xml[start..].find('>')?;
+        pos = start + len + 1;
+        Some((&xml[start + 1..start + len], start))
+    })
+}
+
+// Split the contents of a start tag into the element name, it's attributes, and whether it is self-closing
+fn split_tag(tag: &str) -> (&str, &str, bool) {
+    let (body, self_closing) = match tag.strip_suffix('/') {
+        Some(body) => (body, true),
+        None => (tag, false),
+    };
+    let end = body.find(char::is_whitespace).unwrap_or(body.len());
+    (&body[..end], &body[end..], self_closing)
+}
+
+// Parse the attributes of a start tag into their names, and quoted values
+fn attributes(attrs: &str) -> Vec<(&str, &str)> {
+    let mut out = vec![];
+    let mut rest = attrs.trim_start();
+    while let Some(eq) = rest.find('=') {
+        let name = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_start();
+        let Some(quote) = value.chars().next().filter(|c| matches!(c, '"' | '\'')) else {
+            break;
+        };
+        let Some(len) = value[1..].find(quote) else {
+            break;
+        };
+        out.push((name, &value[..len + 2]));
+        rest = value[len + 2..].trim_start();
+    }
+    out
+}
+
+// Return the local name of the root element of the given XML document
+fn root_element(xml: &str) -> Option<&str> {
+    xml.split('<')
+        .skip(1)
+        .find(|tag| !tag.starts_with(['?', '!']))
+        .and_then(|tag| local_name(tag))
+}
+
+// Return the local name of the element whose tag begins with the given string
+fn local_name(tag: &str) -> Option<&str> {
+    let name = tag
+        .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .next()?;
+    name.rsplit(':').next().filter(|name| !name.is_empty())
+}
+
+// Remove the elements at the given paths, relative to the root element, from the given XML document.
+// Returns the resulting document, and the paths of the elements that were removed.
+fn omit_elements(xml: &str, omitted: &HashSet<String>) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(xml.len());
+    let mut dropped: Vec<String> = vec![];
+    // End of the last byte copied to `out`
+    let mut copied = 0;
+    // Depth of the parent of the element currently being omitted
+    let mut skipping: Option<usize> = None;
+    let mut stack: Vec<&str> = vec![];
+    let mut pos = 0;
+    while let Some(offset) = xml[pos..].find('<') {
+        let start = pos + offset;
+        let Some(len) = xml[start..].find('>') else {
+            break;
+        };
+        let end = start + len + 1;
+        let tag = &xml[start + 1..end - 1];
+        pos = end;
+        if tag.starts_with(['?', '!']) {
+            continue;
+        }
+        if tag.starts_with('/') {
+            stack.pop();
+            if skipping == Some(stack.len()) {
+                skipping = None;
+                copied = end;
+            }
+            continue;
+        }
+        stack.push(local_name(tag).unwrap_or_default());
+        let self_closing = tag.ends_with('/');
+        if skipping.is_none() && stack.len() > 1 {
+            let path = stack[1..].join("/");
+            if omitted.contains(&path) {
+                out.push_str(&xml[copied..start]);
+                if !dropped.contains(&path) {
+                    dropped.push(path);
+                }
+                if self_closing {
+                    copied = end;
+                } else {
+                    skipping = Some(stack.len() - 1);
+                }
+            }
+        }
+        if self_closing {
+            stack.pop();
+        }
+    }
+    if skipping.is_none() {
+        out.push_str(&xml[copied..]);
+    }
+    (out, dropped)
+}
+
+#[test]
+fn omitted_elements_removed() {
+    let xml = r#"<?xml version="1.0" encoding="utf-8"?><DERControl xmlns="urn:ieee:std:2030.5:ns"><mRID>01</mRID><DERControlBase><opModConnect>true</opModConnect><opModFreqDroop><dBOF>1</dBOF></opModFreqDroop><opModEnergize/></DERControlBase><csipaus:opModImpLimW xmlns:csipaus="https://csipaus.org/ns"><value>5</value></csipaus:opModImpLimW></DERControl>"#;
+    let profile = SchemaProfile::new("test")
+        .omit_from("DERControl", "DERControlBase/opModFreqDroop")
+        .omit_from("DERControl", "/DERControlBase/opModEnergize/")
+        .omit_from("DERControl", "opModImpLimW");
+    let out = profile.apply(xml.to_owned());
+    assert_eq!(
+        out,
+        r#"<?xml version="1.0" encoding="utf-8"?><DERControl xmlns="urn:ieee:std:2030.5:ns"><mRID>01</mRID><DERControlBase><opModConnect>true</opModConnect></DERControlBase></DERControl>"#
+    );
+
+    // Profiles only apply to the resources they name
+    let other = SchemaProfile::new("other").omit_from("DERProgram", "mRID");
+    assert_eq!(other.apply(xml.to_owned()), xml);
+}
+
+#[test]
+fn namespaces_normalized() {
+    use sep2_common::{deserialize, packages::dcap::DeviceCapability};
+    let expected = r#"<DeviceCapability xmlns="urn:ieee:std:2030.5:ns" href="/dcap"><EndDeviceListLink all="1" href="/edev"/></DeviceCapability>"#;
+    for xml in [
+        // Undeclared prefix
+        r#"<sep:DeviceCapability href="/dcap"><sep:EndDeviceListLink all="1" href="/edev"/></sep:DeviceCapability>"#,
+        // Unexpected default namespace
+        r#"<DeviceCapability xmlns='urn:ieee:std:2030.5:ns/' href="/dcap"><EndDeviceListLink all="1" href="/edev"/></DeviceCapability>"#,
+        // Declared prefix, missing on children
+        r#"<x:DeviceCapability xmlns:x="urn:ieee:std:2030.5:ns" href="/dcap"><EndDeviceListLink all="1" href="/edev"/></x:DeviceCapability>"#,
+    ] {
+        let out = normalize_namespaces(xml);
+        assert_eq!(out, expected);
+        assert!(deserialize::<DeviceCapability>(&out).is_ok());
+    }
+
+    // Prefixes of other namespaces are retained
+    let xml = r#"<?xml version="1.0"?><DERControl xmlns="urn:ieee:std:2030.5:ns" xmlns:csipaus="https://csipaus.org/ns"><csipaus:opModImpLimW><value>5</value></csipaus:opModImpLimW></DERControl>"#;
+    assert_eq!(normalize_namespaces(xml), xml);
+}
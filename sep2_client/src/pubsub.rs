@@ -1,18 +1,51 @@
 //! Subscription/Notification Function Set
 
-use anyhow::{Context, Result};
-use hyper::{server::conn::Http, service::service_fn, Body, Method, Request, Response};
-use openssl::ssl::Ssl;
-use sep2_common::{deserialize, packages::pubsub::Notification, traits::SEResource};
+use anyhow::{bail, Context, Result};
+use hyper::{
+    header::{CONTENT_TYPE, DATE},
+    http::HeaderValue,
+    server::conn::Http,
+    service::service_fn,
+    Body, Method, Request, Response, StatusCode,
+};
+use openssl::{
+    ex_data::Index,
+    ssl::{Ssl, SslRef},
+    x509::{X509NameRef, X509VerifyResult},
+};
+use sep2_common::{
+    deserialize,
+    packages::{
+        primitives::{String16, Uint32},
+        pubsub::{get_notif_type, HTTPEncoding, Notification, Subscription},
+        types::SubscribableType,
+    },
+    traits::{SEList, SEResource, SESubscribableResource},
+};
 use std::collections::HashMap;
 use std::net;
-use std::path::Path;
-use std::{future::Future, net::SocketAddr, pin::Pin, sync::Arc};
-use tokio::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::{
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::net::{TcpListener, TcpSocket};
+use tokio::sync::watch;
 use tokio_openssl::SslStream;
 
-use crate::client::SEPResponse;
-use crate::tls::{create_server_tls_config, TlsServerConfig};
+use crate::client::{Client, ClientError, PollCallback, SEPResponse};
+use crate::persist::{ResumeCallbacks, SubscriptionRecord};
+use crate::time::current_time_with_offset;
+use crate::tls::{
+    create_server_tls_config, create_server_tls_config_from_identity, record_client_hello,
+    ClientHelloParams, ClientIdentity, TlsServerConfig,
+};
 
 /// A trait implemented by types that can be used as a route callback in a [`ClientNotifServer`].
 pub trait RouteCallback<T: SEResource>: Send + Sync + Clone + 'static {
@@ -30,68 +63,1208 @@ where
     }
 }
 
-/// Internal Boxed future version of a RouteCallback
-type RouteHandler = Box<
-    dyn Fn(&str) -> Pin<Box<dyn Future<Output = SEPResponse> + Send + 'static>>
+/// A [`Notification`] of a list resource, as received by a [`ListRouteCallback`].
+#[derive(Debug, Clone)]
+pub struct ListNotification<L: SEList> {
+    /// The items of the list included in the notification, of which there are at most the `limit` of the Subscription.
+    ///
+    /// Empty if the notification did not include the list, such as when the Subscription has been cancelled.
+    pub items: Vec<L::Inner>,
+    /// The notification itself, with the list's items moved into `items`.
+    ///
+    /// `resource` is `None` if the notification contained a single item, rather than a list.
+    pub notification: Notification<L>,
+}
+
+/// A trait implemented by types that can be used as a route callback for list resources, by [`ClientNotifServer::add_list`].
+pub trait ListRouteCallback<L: SEList>: Send + Sync + Clone + 'static {
+    fn callback(&self, notif: ListNotification<L>) -> impl Future<Output = SEPResponse> + Send;
+}
+
+/// Automatically implemented for all [`Fn`] with a matching function signature.
+impl<F, R, L: SEList> ListRouteCallback<L> for F
+where
+    F: Fn(ListNotification<L>) -> R + Send + Sync + Clone + 'static,
+    R: Future<Output = SEPResponse> + Send + 'static,
+{
+    fn callback(&self, notif: ListNotification<L>) -> impl Future<Output = SEPResponse> + Send {
+        self(notif)
+    }
+}
+
+// Deserialize a notification for a list route, that contains either the list, or a single item of it
+fn deserialize_list_notif<L, T>(body: &str) -> Result<ListNotification<L>>
+where
+    L: SEList<Inner = T>,
+    T: SEResource,
+{
+    if get_notif_type(body).is_ok_and(|kind| kind == T::name()) {
+        let notif: Notification<T> = deserialize(body)?;
+        return Ok(ListNotification {
+            items: notif.resource.into_iter().collect(),
+            notification: Notification {
+                new_resource_uri: notif.new_resource_uri,
+                resource: None,
+                status: notif.status,
+                subscription_uri: notif.subscription_uri,
+                subscribed_resource: notif.subscribed_resource,
+                href: notif.href,
+            },
+        });
+    }
+    let mut notification: Notification<L> = deserialize(body)?;
+    let items = notification
+        .resource
+        .as_mut()
+        .map(|list| std::mem::take(list.list_mut()))
+        .unwrap_or_default();
+    Ok(ListNotification {
+        items,
+        notification,
+    })
+}
+
+/// Internal Boxed future version of a RouteCallback, returning an error if the notification could not be deserialized
+type RouteHandler = Arc<
+    dyn Fn(&str) -> Result<Pin<Box<dyn Future<Output = SEPResponse> + Send + 'static>>>
         + Send
         + Sync
         + 'static,
 >;
 
+/// Internal Boxed version of a teardown hook added using [`ClientNotifServer::with_teardown`]
+type TeardownHook = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+
+/// A notification that could not be deserialized, as delivered to the sink set by [`ClientNotifServer::with_error_sink`].
+#[derive(Debug)]
+pub struct DeserializeFailure {
+    /// The path the notification was received on
+    pub path: String,
+    /// The body of the notification. Invalid UTF-8 is replaced with U+FFFD.
+    pub body: String,
+    /// Why the notification could not be deserialized
+    pub error: anyhow::Error,
+}
+
+type ErrorSink = Box<dyn Fn(DeserializeFailure) + Send + Sync + 'static>;
+
+/// Details of a failed TLS handshake, as logged by a [`ClientNotifServer`], and passed to the callback set by [`ClientNotifServer::with_handshake_diagnostics`].
+#[derive(Clone, Debug)]
+pub struct HandshakeDiagnostics {
+    /// The address of the peer
+    pub remote: SocketAddr,
+    /// The error returned by OpenSSL, or why the handshake was abandoned
+    pub error: String,
+    /// The `legacy_version` offered in the peer's ClientHello, if one was received.
+    ///
+    /// Peers offering TLS 1.3 do so in the `supported_versions` extension, and report TLS 1.2 here.
+    pub offered_version: Option<&'static str>,
+    /// The cipher suites offered in the peer's ClientHello, by their standard names
+    pub offered_ciphers: Vec<String>,
+    /// The subject of the certificate presented by the peer, if one was presented
+    pub peer_certificate: Option<String>,
+    /// The result of verifying the peer's certificate.
+    ///
+    /// This is `X509VerifyResult::OK` if the peer did not present a certificate.
+    pub verify_result: X509VerifyResult,
+}
+
+impl HandshakeDiagnostics {
+    fn new(
+        remote: SocketAddr,
+        error: String,
+        ssl: &SslRef,
+        hello: Option<Index<Ssl, ClientHelloParams>>,
+    ) -> Self {
+        let offered = hello
+            .and_then(|index| ssl.ex_data(index))
+            .cloned()
+            .unwrap_or_default();
+        HandshakeDiagnostics {
+            remote,
+            error,
+            offered_version: offered.version,
+            offered_ciphers: offered.ciphers,
+            peer_certificate: ssl
+                .peer_certificate()
+                .map(|cert| fmt_name(cert.subject_name())),
+            verify_result: ssl.verify_result(),
+        }
+    }
+}
+
+impl std::fmt::Display for HandshakeDiagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (remote: {}, offered version: {}, offered ciphers: [{}], peer certificate: {}, verify result: {})",
+            self.error,
+            self.remote,
+            self.offered_version.unwrap_or("none"),
+            self.offered_ciphers.join(", "),
+            self.peer_certificate.as_deref().unwrap_or("none"),
+            self.verify_result.error_string()
+        )
+    }
+}
+
+// Format an X509 name as a comma separated list of `field=value` pairs
+fn fmt_name(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let field = entry.object().nid().short_name().unwrap_or("?");
+            match entry.data().to_string() {
+                Ok(value) => format!("{field}={value}"),
+                Err(_) => format!("{field}=?"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+type DiagnosticsCallback = Arc<dyn Fn(&HandshakeDiagnostics) + Send + Sync + 'static>;
+
+/// Why a [`ClientNotifServer`] rejected a request, as reported to a [`NotifObserver`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NotifRejection {
+    /// No route exists at the requested path, responded to with 404 Not Found.
+    NotFound,
+    /// The request was not a POST, responded to with 405 Method Not Allowed.
+    MethodNotAllowed,
+    /// The Date header was missing, or outside the window set by [`ClientNotifServer::with_replay_protection`], responded to with 400 Bad Request.
+    Stale,
+    /// The notification was EXI encoded, which is not supported, responded to with 415 Unsupported Media Type.
+    UnsupportedEncoding,
+    /// The body could not be read, was empty, or could not be deserialized into the route's [`Notification<T>`], responded to with 400 Bad Request.
+    Malformed,
+}
+
+/// A hook into the handling of connections & notifications by a [`ClientNotifServer`], for the purpose of collecting metrics.
+///
+/// All methods have a no-op default implementation. They are called from the server's accept loop & connection tasks,
+/// and should therefore return quickly.
+///
+/// See [`NotifMetrics`] for an implementation that keeps counters & timings.
+pub trait NotifObserver: Send + Sync + 'static {
+    /// A connection was accepted from `remote`.
+    fn connection_accepted(&self, _remote: SocketAddr) {}
+    /// A connection from `remote` was closed immediately, as it exceeded the limit set by [`ClientNotifServer::with_per_ip_limit`].
+    fn connection_rejected(&self, _remote: SocketAddr) {}
+    /// A TLS handshake with `remote` failed.
+    fn handshake_failed(&self, _remote: SocketAddr) {}
+    /// A notification on `path` was passed to it's route callback, which completed in `duration`.
+    fn notification_handled(&self, _path: &str, _duration: Duration) {}
+    /// A request on `path` was rejected before reaching a route callback.
+    fn notification_rejected(&self, _path: &str, _reason: NotifRejection) {}
+}
+
+impl<T: NotifObserver> NotifObserver for Arc<T> {
+    fn connection_accepted(&self, remote: SocketAddr) {
+        (**self).connection_accepted(remote)
+    }
+    fn connection_rejected(&self, remote: SocketAddr) {
+        (**self).connection_rejected(remote)
+    }
+    fn handshake_failed(&self, remote: SocketAddr) {
+        (**self).handshake_failed(remote)
+    }
+    fn notification_handled(&self, path: &str, duration: Duration) {
+        (**self).notification_handled(path, duration)
+    }
+    fn notification_rejected(&self, path: &str, reason: NotifRejection) {
+        (**self).notification_rejected(path, reason)
+    }
+}
+
+// The default observer
+struct NoopObserver;
+
+impl NotifObserver for NoopObserver {}
+
+/// Notification handling statistics for a single path, as recorded by [`NotifMetrics`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RouteStats {
+    /// The number of notifications passed to the route callback
+    pub handled: u64,
+    /// The number of requests rejected before reaching the route callback, by reason
+    pub rejected: HashMap<NotifRejection, u64>,
+    /// The total time spent in the route callback
+    pub callback_time: Duration,
+    /// The longest time spent in a single invocation of the route callback
+    pub max_callback_time: Duration,
+}
+
+impl RouteStats {
+    /// The mean time spent in the route callback, or zero if no notifications have been handled
+    pub fn mean_callback_time(&self) -> Duration {
+        match self.handled {
+            0 => Duration::ZERO,
+            n => self.callback_time.div_f64(n as f64),
+        }
+    }
+}
+
+/// A [`NotifObserver`] that keeps counters of connections, and per-path counters & callback timings of notifications.
+///
+/// Share it with a [`ClientNotifServer`] via an [`Arc`], and read it from elsewhere, e.g. a metrics exporter:
+/// ```no_run
+/// # use sep2_client::pubsub::{ClientNotifServer, NotifMetrics};
+/// # use std::sync::Arc;
+/// let metrics = Arc::new(NotifMetrics::default());
+/// let server = ClientNotifServer::new("127.0.0.1:1338")
+///     .unwrap()
+///     .with_observer(metrics.clone());
+/// // ...
+/// let dcap_handled = metrics.route("/dcap").map_or(0, |stats| stats.handled);
+/// ```
+#[derive(Debug, Default)]
+pub struct NotifMetrics {
+    connections_accepted: AtomicU64,
+    connections_rejected: AtomicU64,
+    handshake_failures: AtomicU64,
+    routes: std::sync::Mutex<HashMap<String, RouteStats>>,
+}
+
+impl NotifMetrics {
+    /// The number of connections accepted
+    pub fn connections_accepted(&self) -> u64 {
+        self.connections_accepted.load(Ordering::Relaxed)
+    }
+
+    /// The number of connections closed immediately due to the per-IP connection limit
+    pub fn connections_rejected(&self) -> u64 {
+        self.connections_rejected.load(Ordering::Relaxed)
+    }
+
+    /// The number of failed TLS handshakes
+    pub fn handshake_failures(&self) -> u64 {
+        self.handshake_failures.load(Ordering::Relaxed)
+    }
+
+    /// The statistics for the given path, if any requests have been received on it
+    pub fn route(&self, path: &str) -> Option<RouteStats> {
+        self.routes.lock().unwrap().get(path).cloned()
+    }
+
+    /// The statistics for all paths that requests have been received on
+    pub fn routes(&self) -> HashMap<String, RouteStats> {
+        self.routes.lock().unwrap().clone()
+    }
+
+    fn update(&self, path: &str, f: impl FnOnce(&mut RouteStats)) {
+        let mut routes = self.routes.lock().unwrap();
+        match routes.get_mut(path) {
+            Some(stats) => f(stats),
+            None => f(routes.entry(path.to_owned()).or_default()),
+        }
+    }
+}
+
+impl NotifObserver for NotifMetrics {
+    fn connection_accepted(&self, _: SocketAddr) {
+        self.connections_accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn connection_rejected(&self, _: SocketAddr) {
+        self.connections_rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn handshake_failed(&self, _: SocketAddr) {
+        self.handshake_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn notification_handled(&self, path: &str, duration: Duration) {
+        self.update(path, |stats| {
+            stats.handled += 1;
+            stats.callback_time += duration;
+            stats.max_callback_time = stats.max_callback_time.max(duration);
+        });
+    }
+
+    fn notification_rejected(&self, path: &str, reason: NotifRejection) {
+        self.update(path, |stats| {
+            *stats.rejected.entry(reason).or_default() += 1
+        });
+    }
+}
+
 struct Router {
+    routes: RouteTable,
+    // How old a notification may be, as per it's Date header, before it is rejected
+    freshness: Option<Duration>,
+    observer: Arc<dyn NotifObserver>,
+    error_sink: Option<ErrorSink>,
+    success: SuccessResponse,
+}
+
+/// The HTTP response sent for a notification when it's route callback returns [`SEPResponse::NoContent`],
+/// as set by [`ClientNotifServer::with_success_response`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SuccessResponse {
+    /// 204 No Content
+    #[default]
+    NoContent,
+    /// 200 OK, with an empty body
+    Ok,
+}
+
+/// A cloneable handle to the routes of a [`ClientNotifServer`], obtained via [`ClientNotifServer::route_table`].
+///
+/// Unlike [`ClientNotifServer::add`], routes can be added to, and removed from, the table while the server is running,
+/// such as when Subscriptions are created & deleted. Changes apply to all notifications received thereafter.
+#[derive(Clone, Default)]
+pub struct RouteTable {
     // We use ahash::RandomState for performance, any additional hash safety of std::RandomState is useless
-    routes: HashMap<String, RouteHandler, ahash::RandomState>,
+    routes: Arc<std::sync::RwLock<HashMap<String, RouteHandler, ahash::RandomState>>>,
+    // Whether adding a route with the path of an existing route fails, rather than replacing it
+    reject_duplicates: Arc<AtomicBool>,
+}
+
+/// How a route added with the path of an existing route is handled,
+/// as set by [`ClientNotifServer::with_duplicate_routes`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateRoutes {
+    /// Replace the existing route, logging a warning.
+    #[default]
+    Replace,
+    /// Keep the existing route, and fail with a [`DuplicateRoute`] error.
+    Reject,
+}
+
+/// A route was added with the path of an existing route, while [`DuplicateRoutes::Reject`] is set.
+///
+/// Returned within an [`anyhow::Error`], and can be retrieved using [`anyhow::Error::downcast_ref`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateRoute {
+    pub path: String,
+}
+
+impl std::fmt::Display for DuplicateRoute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "A route already exists on {}", self.path)
+    }
+}
+
+impl std::error::Error for DuplicateRoute {}
+
+impl RouteTable {
+    /// Add a route, as per [`ClientNotifServer::add`].
+    ///
+    /// If a route with the same path exists, it is handled as per the server's [`DuplicateRoutes`].
+    pub fn add<T>(&self, path: impl Into<String>, callback: impl RouteCallback<T>) -> Result<()>
+    where
+        T: SEResource,
+    {
+        let path = path.into();
+        let new: RouteHandler = Arc::new({
+            let log_path = path.clone();
+            move |e| {
+                let e = deserialize::<Notification<T>>(e);
+                match e {
+                    Ok(resource) => {
+                        log::debug!(
+                            "NotifServer: Successfully deserialized a resource on {log_path}"
+                        );
+                        let callback = callback.clone();
+                        Ok(Box::pin(async move { callback.callback(resource).await }))
+                    }
+                    Err(err) => {
+                        log::error!(
+                            "NotifServer: Failed to deserialize resource on {log_path}: {err}"
+                        );
+                        Err(anyhow::anyhow!(err))
+                    }
+                }
+            }
+        });
+        self.insert(path, new)
+    }
+
+    /// Add a route for notifications of a list resource, as per [`ClientNotifServer::add_list`].
+    ///
+    /// If a route with the same path exists, it is handled as per the server's [`DuplicateRoutes`].
+    pub fn add_list<L>(
+        &self,
+        path: impl Into<String>,
+        callback: impl ListRouteCallback<L>,
+    ) -> Result<()>
+    where
+        L: SEList,
+        L::Inner: SEResource,
+    {
+        let path = path.into();
+        let new: RouteHandler = Arc::new({
+            let log_path = path.clone();
+            move |e| match deserialize_list_notif::<L, _>(e) {
+                Ok(notif) => {
+                    log::debug!(
+                        "NotifServer: Successfully deserialized {} item(s) of a list on {log_path}",
+                        notif.items.len()
+                    );
+                    let callback = callback.clone();
+                    Ok(Box::pin(async move { callback.callback(notif).await }))
+                }
+                Err(err) => {
+                    log::error!(
+                        "NotifServer: Failed to deserialize list resource on {log_path}: {err}"
+                    );
+                    Err(err)
+                }
+            }
+        });
+        self.insert(path, new)
+    }
+
+    // Insert a route, handling an existing route with the same path as per the DuplicateRoutes
+    fn insert(&self, path: String, handler: RouteHandler) -> Result<()> {
+        let mut routes = self.routes.write().unwrap();
+        if routes.contains_key(&path) {
+            if self.reject_duplicates.load(Ordering::Relaxed) {
+                log::error!("NotifServer: Rejecting a duplicate route on {path}");
+                return Err(DuplicateRoute { path }.into());
+            }
+            log::warn!("NotifServer: Replacing the existing route on {path}");
+        }
+        routes.insert(path, handler);
+        Ok(())
+    }
+
+    /// Remove the route with the given path. Notifications subsequently received on it are responded to with 404 Not Found.
+    ///
+    /// Returns whether the route existed.
+    pub fn remove(&self, path: &str) -> bool {
+        self.routes.write().unwrap().remove(path).is_some()
+    }
+
+    /// Whether a route with the given path exists.
+    pub fn contains(&self, path: &str) -> bool {
+        self.routes.read().unwrap().contains_key(path)
+    }
+
+    /// The paths of all routes.
+    pub fn paths(&self) -> Vec<String> {
+        self.routes.read().unwrap().keys().cloned().collect()
+    }
+
+    fn get(&self, path: &str) -> Option<RouteHandler> {
+        self.routes.read().unwrap().get(path).cloned()
+    }
+
+    // Remove all routes, dropping their callbacks
+    fn clear(&self) {
+        self.routes.write().unwrap().clear();
+    }
+}
+
+// Whether a request with the given Date header was sent within `window` of `now`
+fn is_fresh(date: Option<&HeaderValue>, now: SystemTime, window: Duration) -> bool {
+    let Some(sent) = date
+        .and_then(|date| date.to_str().ok())
+        .and_then(|date| httpdate::parse_http_date(date).ok())
+    else {
+        return false;
+    };
+    match now.duration_since(sent) {
+        Ok(age) => age <= window,
+        // Sent in the future, i.e. the clocks disagree
+        Err(e) => e.duration() <= window,
+    }
+}
+
+// The encoding of a notification body, as per it's Content-Type header
+#[derive(Debug, PartialEq, Eq)]
+enum NotifEncoding {
+    Xml,
+    Exi,
+}
+
+impl NotifEncoding {
+    // Absent or unrecognised content types are assumed to be XML
+    fn from_content_type(content_type: Option<&HeaderValue>) -> Self {
+        match content_type.and_then(|ct| ct.to_str().ok()) {
+            Some(ct) if ct.trim_start().starts_with("application/sep-exi") => NotifEncoding::Exi,
+            _ => NotifEncoding::Xml,
+        }
+    }
+}
+
+impl Router {
+    fn new() -> Self {
+        Router {
+            routes: RouteTable::default(),
+            freshness: None,
+            observer: Arc::new(NoopObserver),
+            error_sink: None,
+            success: SuccessResponse::default(),
+        }
+    }
+
+    // Dispatch a request to the route for it's path, responding as the server would over a connection
+    async fn router(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let path = req.uri().path().to_owned();
+        let Some(handler) = self.routes.get(&path) else {
+            self.observer
+                .notification_rejected(&path, NotifRejection::NotFound);
+            return hyper::Response::try_from(SEPResponse::NotFound);
+        };
+        if req.method() != Method::POST {
+            self.observer
+                .notification_rejected(&path, NotifRejection::MethodNotAllowed);
+            return hyper::Response::try_from(SEPResponse::MethodNotAllowed("POST".to_owned()));
+        }
+        self.notify(&path, &handler, req).await
+    }
+
+    // Handle a notification POSTed to the given route
+    async fn notify(
+        &self,
+        path: &str,
+        handler: &RouteHandler,
+        req: Request<Body>,
+    ) -> Result<Response<Body>> {
+        if let Some(window) = self.freshness {
+            let now = current_time_with_offset().into();
+            if !is_fresh(req.headers().get(DATE), now, window) {
+                log::warn!(
+                    "NotifServer: Rejecting notification on {path}, as it's Date header is missing, or older than {window:?}"
+                );
+                self.observer
+                    .notification_rejected(path, NotifRejection::Stale);
+                return hyper::Response::try_from(SEPResponse::BadRequest(None));
+            }
+        }
+        let encoding = NotifEncoding::from_content_type(req.headers().get(CONTENT_TYPE));
+        let body = req.into_body();
+        let bytes = match hyper::body::to_bytes(body).await {
+            Ok(bytes) if !bytes.is_empty() => bytes,
+            _ => {
+                self.observer
+                    .notification_rejected(path, NotifRejection::Malformed);
+                return hyper::Response::try_from(SEPResponse::BadRequest(None));
+            }
+        };
+        // A well-formed EXI notification is rejected, as EXI is not supported
+        if encoding == NotifEncoding::Exi {
+            log::warn!(
+                "NotifServer: Rejecting EXI encoded notification on {path}, only XML is supported"
+            );
+            self.observer
+                .notification_rejected(path, NotifRejection::UnsupportedEncoding);
+            return Ok(Response::builder()
+                .status(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+                .body(Body::empty())?);
+        }
+        let callback = match String::from_utf8(bytes.to_vec()) {
+            Ok(xml) => handler(&xml).map_err(|err| (xml, err)),
+            Err(err) => Err((String::from_utf8_lossy(&bytes).into_owned(), err.into())),
+        };
+        let callback = match callback {
+            Ok(callback) => callback,
+            Err((body, error)) => {
+                self.observer
+                    .notification_rejected(path, NotifRejection::Malformed);
+                if let Some(sink) = &self.error_sink {
+                    sink(DeserializeFailure {
+                        path: path.to_owned(),
+                        body,
+                        error,
+                    });
+                }
+                return hyper::Response::try_from(SEPResponse::BadRequest(None));
+            }
+        };
+        let start = Instant::now();
+        let res = callback.await;
+        self.observer.notification_handled(path, start.elapsed());
+        let mut res = hyper::Response::try_from(res)?;
+        if res.status() == StatusCode::NO_CONTENT && self.success == SuccessResponse::Ok {
+            *res.status_mut() = StatusCode::OK;
+        }
+        Ok(res)
+    }
+}
+
+/// The lifecycle state of a [`ClientNotifServer`], as reported by a [`ShutdownHandle`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServerState {
+    /// [`ClientNotifServer::run`] has not yet been called, or the server is yet to bind to it's address.
+    NotStarted,
+    /// The server is accepting connections.
+    Running,
+    /// The server is no longer accepting connections, and is waiting for existing connections to finish their in-flight requests,
+    /// for at most the timeout set using [`ClientNotifServer::with_drain_timeout`].
+    Draining,
+    /// The server has shutdown, or failed to start.
+    Stopped,
+}
+
+/// A cloneable handle to a [`ClientNotifServer`], used to signal shutdown and query the state of the server from elsewhere.
+///
+/// Obtained via [`ClientNotifServer::shutdown_handle`].
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    shutdown: Arc<watch::Sender<bool>>,
+    state: watch::Receiver<ServerState>,
+    conns: ConnCounts,
+}
+
+impl ShutdownHandle {
+    /// Signal the server to shutdown gracefully.
+    ///
+    /// If the server has not yet started, it will shutdown immediately after binding.
+    pub fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+
+    /// Return the current state of the server
+    pub fn state(&self) -> ServerState {
+        *self.state.borrow()
+    }
+
+    /// Wait until the server has reached [`ServerState::Stopped`]
+    pub async fn stopped(&self) {
+        let mut state = self.state.clone();
+        // Sender is held by the server, if it's dropped the server is gone regardless
+        let _ = state.wait_for(|s| *s == ServerState::Stopped).await;
+    }
+
+    /// Return the number of connections currently open to the server
+    pub fn active_connections(&self) -> usize {
+        self.conns.lock().unwrap().values().sum()
+    }
+}
+
+/// A [`ClientNotifServer`] running on a background task, as returned by [`ClientNotifServer::spawn`].
+///
+/// Allows the server to be supervised, and restarted should it fail or stall.
+/// Dropping a RunningServer does not stop the server.
+pub struct RunningServer {
+    handle: ShutdownHandle,
+    task: tokio::task::JoinHandle<Result<()>>,
+}
+
+impl RunningServer {
+    /// Return a [`ShutdownHandle`] for the server
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.handle.clone()
+    }
+
+    /// Return the current state of the server
+    pub fn state(&self) -> ServerState {
+        self.handle.state()
+    }
+
+    /// Return the number of connections currently open to the server
+    pub fn active_connections(&self) -> usize {
+        self.handle.active_connections()
+    }
+
+    /// Whether the server task has finished, either due to a shutdown, failure to start, or panic
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Forcibly stop the server, without waiting for open connections to finish.
+    ///
+    /// Open connections are closed once the server task is dropped by the runtime.
+    pub fn abort(&self) {
+        self.task.abort();
+    }
+
+    /// Wait for the server task to finish, returning the result of [`ClientNotifServer::run`].
+    ///
+    /// Returns an error if the task panicked or was aborted.
+    pub async fn join(self) -> Result<()> {
+        self.task.await.context("Notification server task failed")?
+    }
+}
+
+/// A lightweight IEEE 2030.5 Server for receiving [`Notification<T>`] resources from a server for the subscription / notification mechanism.
+pub struct ClientNotifServer {
+    addr: SocketAddr,
+    cfg: Option<TlsServerConfig>,
+    router: Router,
+    shutdown: Arc<watch::Sender<bool>>,
+    state: Arc<watch::Sender<ServerState>>,
+    bind_retries: u32,
+    bind_backoff: Duration,
+    handshake_timeout: Duration,
+    drain_timeout: Duration,
+    backlog: u32,
+    per_ip_limit: Option<usize>,
+    send_buffer_size: Option<usize>,
+    recv_buffer_size: Option<usize>,
+    conns: ConnCounts,
+    teardown: Vec<TeardownHook>,
+    diagnostics: Option<DiagnosticsCallback>,
+    // The first error encountered adding a route, returned when the server is run
+    route_error: Option<anyhow::Error>,
+}
+
+// Bind a listener to the given address, allowing the address to be reused immediately after a restart.
+// Accepted sockets inherit the buffer sizes of the listener.
+fn bind(
+    addr: SocketAddr,
+    backlog: u32,
+    send_buffer_size: Option<usize>,
+    recv_buffer_size: Option<usize>,
+) -> std::io::Result<TcpListener> {
+    let socket = match addr {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+    socket.set_reuseaddr(true)?;
+    // The operating system caps buffer sizes far below u32::MAX, so larger sizes are clamped just the same
+    let clamp = |size: usize| u32::try_from(size).unwrap_or(u32::MAX);
+    if let Some(size) = send_buffer_size {
+        socket.set_send_buffer_size(clamp(size))?;
+    }
+    if let Some(size) = recv_buffer_size {
+        socket.set_recv_buffer_size(clamp(size))?;
+    }
+    socket.bind(addr)?;
+    socket.listen(backlog)
+}
+
+// The number of open connections from each remote IP
+type ConnCounts = Arc<std::sync::Mutex<HashMap<IpAddr, usize>>>;
+
+// Serve a connection, closing it gracefully once shutdown is signalled, after responding to any in-flight request.
+// A macro, as the bounds of hyper's connection executor cannot be named.
+macro_rules! serve_connection {
+    ($stream:expr, $service:expr, $shutdown:expr) => {{
+        let mut shutdown: watch::Receiver<bool> = $shutdown;
+        let conn = Http::new().serve_connection($stream, $service);
+        tokio::pin!(conn);
+        tokio::select! {
+            res = conn.as_mut() => res,
+            // The guard returned by `wait_for` must not be held across an await
+            _ = async { shutdown.wait_for(|sd| *sd).await.is_ok() } => {
+                conn.as_mut().graceful_shutdown();
+                conn.await
+            }
+        }
+    }};
+}
+
+// Reports the server as stopped when dropped, such that it is reported on every exit path of `ClientNotifServer::run`,
+// including if it's task is aborted
+struct StoppedGuard(Arc<watch::Sender<ServerState>>);
+
+impl Drop for StoppedGuard {
+    fn drop(&mut self) {
+        self.0.send_replace(ServerState::Stopped);
+    }
+}
+
+// Held for the lifetime of a connection, decrementing the remote's connection count when dropped
+struct ConnGuard {
+    ip: IpAddr,
+    conns: ConnCounts,
+}
+
+impl ConnGuard {
+    // Returns None if the remote already has `limit` open connections
+    fn acquire(conns: &ConnCounts, ip: IpAddr, limit: Option<usize>) -> Option<Self> {
+        let mut counts = conns.lock().unwrap();
+        let count = counts.entry(ip).or_default();
+        if limit.is_some_and(|limit| *count >= limit) {
+            return None;
+        }
+        *count += 1;
+        Some(ConnGuard {
+            ip,
+            conns: conns.clone(),
+        })
+    }
+}
+
+impl Drop for ConnGuard {
+    fn drop(&mut self) {
+        let mut counts = self.conns.lock().unwrap();
+        if let Some(count) = counts.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&self.ip);
+            }
+        }
+    }
+}
+
+// How a server built by a NotifServerBuilder secures it's connections
+enum NotifSecurity {
+    Http,
+    PemFiles {
+        cert_path: PathBuf,
+        pk_path: PathBuf,
+        rootca_path: PathBuf,
+    },
+    Identity(ClientIdentity, PathBuf),
+}
+
+/// A builder for a [`ClientNotifServer`], obtained via [`ClientNotifServer::builder`].
+///
+/// Certificates & keys are not loaded until [`NotifServerBuilder::build`] is called,
+/// which also returns any error encountered when resolving the server address.
+/// Routes & callbacks are added to the built server.
+///
+/// The built server only accepts XML encoded notifications. EXI is not supported, and EXI encoded notifications,
+/// as per their `application/sep-exi` Content-Type, are rejected with 415 Unsupported Media Type.
+pub struct NotifServerBuilder {
+    // The server being configured, or the error encountered resolving it's address
+    server: Result<ClientNotifServer>,
+    security: NotifSecurity,
+}
+
+impl NotifServerBuilder {
+    fn new(addr: impl net::ToSocketAddrs) -> Self {
+        let server = addr
+            .to_socket_addrs()
+            .map_err(anyhow::Error::from)
+            .and_then(|mut addrs| {
+                addrs
+                    .next()
+                    .context("Given server address did not yield a SocketAddr")
+            })
+            .map(ClientNotifServer::from_addr);
+        NotifServerBuilder {
+            server,
+            security: NotifSecurity::Http,
+        }
+    }
+
+    // Configure the server being built, if it's address was resolved
+    fn map(mut self, f: impl FnOnce(ClientNotifServer) -> ClientNotifServer) -> Self {
+        self.server = self.server.map(f);
+        self
+    }
+
+    /// Use HTTPS, with the certificate, private key & trusted root CA at the given paths, as per [`ClientNotifServer::with_https`].
+    pub fn with_https(
+        mut self,
+        cert_path: impl AsRef<Path>,
+        pk_path: impl AsRef<Path>,
+        rootca_path: impl AsRef<Path>,
+    ) -> Self {
+        self.security = NotifSecurity::PemFiles {
+            cert_path: cert_path.as_ref().to_owned(),
+            pk_path: pk_path.as_ref().to_owned(),
+            rootca_path: rootca_path.as_ref().to_owned(),
+        };
+        self
+    }
+
+    /// Use HTTPS, with an already loaded certificate & private key, as per [`ClientNotifServer::with_https_identity`].
+    pub fn with_https_identity(
+        mut self,
+        identity: &ClientIdentity,
+        rootca_path: impl AsRef<Path>,
+    ) -> Self {
+        self.security = NotifSecurity::Identity(identity.clone(), rootca_path.as_ref().to_owned());
+        self
+    }
+
+    /// Set the maximum number of pending connections, as per [`ClientNotifServer::with_backlog`].
+    pub fn with_backlog(self, backlog: u32) -> Self {
+        self.map(|server| server.with_backlog(backlog))
+    }
+
+    /// Set the size of each connection's send buffer, as per [`ClientNotifServer::with_send_buffer_size`].
+    pub fn with_send_buffer_size(self, size: usize) -> Self {
+        self.map(|server| server.with_send_buffer_size(size))
+    }
+
+    /// Set the size of each connection's receive buffer, as per [`ClientNotifServer::with_recv_buffer_size`].
+    pub fn with_recv_buffer_size(self, size: usize) -> Self {
+        self.map(|server| server.with_recv_buffer_size(size))
+    }
+
+    /// Limit the number of concurrent connections from a single remote IP, as per [`ClientNotifServer::with_per_ip_limit`].
+    pub fn with_per_ip_limit(self, limit: usize) -> Self {
+        self.map(|server| server.with_per_ip_limit(limit))
+    }
+
+    /// Reject notifications sent more than `window` ago, as per [`ClientNotifServer::with_replay_protection`].
+    pub fn with_replay_protection(self, window: Duration) -> Self {
+        self.map(|server| server.with_replay_protection(window))
+    }
+
+    /// Set the response sent for successfully handled notifications, as per [`ClientNotifServer::with_success_response`].
+    pub fn with_success_response(self, success: SuccessResponse) -> Self {
+        self.map(|server| server.with_success_response(success))
+    }
+
+    /// Set how binding to the server address is retried, as per [`ClientNotifServer::with_bind_retry`].
+    pub fn with_bind_retry(self, retries: u32, backoff: Duration) -> Self {
+        self.map(|server| server.with_bind_retry(retries, backoff))
+    }
+
+    /// Set how long a TLS handshake may take before the connection is dropped, as per [`ClientNotifServer::with_handshake_timeout`].
+    pub fn with_handshake_timeout(self, timeout: Duration) -> Self {
+        self.map(|server| server.with_handshake_timeout(timeout))
+    }
+
+    /// Set how long a graceful shutdown waits for open connections to finish, as per [`ClientNotifServer::with_drain_timeout`].
+    pub fn with_drain_timeout(self, timeout: Duration) -> Self {
+        self.map(|server| server.with_drain_timeout(timeout))
+    }
+
+    /// Set how a route added with the path of an existing route is handled, as per [`ClientNotifServer::with_duplicate_routes`].
+    pub fn with_duplicate_routes(self, duplicates: DuplicateRoutes) -> Self {
+        self.map(|server| server.with_duplicate_routes(duplicates))
+    }
+
+    /// Construct the [`ClientNotifServer`].
+    ///
+    /// Returns an error if the server address could not be resolved, or if the certificates or private key could not be loaded.
+    pub fn build(self) -> Result<ClientNotifServer> {
+        let server = self.server?;
+        match self.security {
+            NotifSecurity::Http => Ok(server),
+            NotifSecurity::PemFiles {
+                cert_path,
+                pk_path,
+                rootca_path,
+            } => server.with_https(cert_path, pk_path, rootca_path),
+            NotifSecurity::Identity(identity, rootca_path) => {
+                server.with_https_identity(&identity, rootca_path)
+            }
+        }
+    }
 }
 
-impl Router {
-    fn new() -> Self {
-        Router {
-            routes: HashMap::default(),
-        }
+impl ClientNotifServer {
+    const DEFAULT_BIND_RETRIES: u32 = 3;
+    const DEFAULT_BIND_BACKOFF: Duration = Duration::from_millis(500);
+    const DEFAULT_BACKLOG: u32 = 1024;
+    const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+    const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Create a new Notification server that listens on the given address
+    ///
+    /// Equivalent to `ClientNotifServer::builder(addr).build()`.
+    pub fn new(addr: impl net::ToSocketAddrs) -> Result<Self> {
+        Self::builder(addr).build()
+    }
+
+    /// Create a [`NotifServerBuilder`] for a Notification server that listens on the given address,
+    /// allowing the server's connection handling to be configured before it is constructed.
+    ///
+    /// The server uses HTTP, unless one of the `with_https` methods is called on the builder.
+    /// As with all servers, EXI encoded notifications are not supported, and are rejected, as per [`NotifServerBuilder`].
+    pub fn builder(addr: impl net::ToSocketAddrs) -> NotifServerBuilder {
+        NotifServerBuilder::new(addr)
+    }
+
+    // Create a Notification server with the default configuration, and no routes
+    fn from_addr(addr: SocketAddr) -> Self {
+        ClientNotifServer {
+            addr,
+            cfg: None,
+            router: Router::new(),
+            shutdown: Arc::new(watch::channel(false).0),
+            state: Arc::new(watch::channel(ServerState::NotStarted).0),
+            bind_retries: Self::DEFAULT_BIND_RETRIES,
+            bind_backoff: Self::DEFAULT_BIND_BACKOFF,
+            handshake_timeout: Self::DEFAULT_HANDSHAKE_TIMEOUT,
+            drain_timeout: Self::DEFAULT_DRAIN_TIMEOUT,
+            backlog: Self::DEFAULT_BACKLOG,
+            per_ip_limit: None,
+            send_buffer_size: None,
+            recv_buffer_size: None,
+            conns: ConnCounts::default(),
+            teardown: vec![],
+            diagnostics: None,
+            route_error: None,
+        }
+    }
+
+    /// Create a new Notification server for local testing, that uses HTTPS with self-signed certificates on both ends.
+    ///
+    /// The server presents the certificate at `cert_path`, and requires clients to present the certificate at `peer_cert_path`,
+    /// which need not be issued by a CA.
+    ///
+    /// As with the other constructors, neither certificate is checked against [`check_device_cert`] during the handshake,
+    /// so no device certificate requirements need to be loosened.
+    ///
+    /// **This constructor is insecure, and must not be used outside of tests.**
+    ///
+    /// [`check_device_cert`]: crate::tls::check_device_cert
+    #[cfg(feature = "test_util")]
+    pub fn new_test(
+        addr: impl net::ToSocketAddrs,
+        cert_path: impl AsRef<Path>,
+        pk_path: impl AsRef<Path>,
+        peer_cert_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let mut out = Self::new(addr)?;
+        out.cfg = Some(crate::tls::create_test_server_tls_config(
+            cert_path,
+            pk_path,
+            peer_cert_path,
+        )?);
+        Ok(out)
+    }
+
+    /// Set the maximum number of pending connections the listener will queue before refusing new connections.
+    ///
+    /// Defaults to 1024, if this function is not called.
+    pub fn with_backlog(mut self, backlog: u32) -> Self {
+        self.backlog = backlog;
+        self
+    }
+
+    /// Set the size of the send buffer of each accepted connection's socket, in bytes, via `SO_SNDBUF`.
+    ///
+    /// Defaults to the operating system default.
+    pub fn with_send_buffer_size(mut self, size: usize) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Set the size of the receive buffer of each accepted connection's socket, in bytes, via `SO_RCVBUF`.
+    ///
+    /// Defaults to the operating system default.
+    pub fn with_recv_buffer_size(mut self, size: usize) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Limit the number of concurrent connections from a single remote IP address.
+    ///
+    /// Connections from an IP that already has `limit` open connections are closed immediately after being accepted.
+    ///
+    /// Unlimited by default.
+    pub fn with_per_ip_limit(mut self, limit: usize) -> Self {
+        self.per_ip_limit = Some(limit);
+        self
+    }
+
+    /// Reject notifications that were sent more than `window` ago, to prevent delayed or replayed notifications from being applied.
+    ///
+    /// The time a notification was sent is determined by the Date header of the request, and compared against [`current_time_with_offset`].
+    /// When enabled, notifications without a valid Date header are also rejected. Rejected notifications receive a 400 Bad Request,
+    /// and never reach the route callback.
+    ///
+    /// Disabled by default.
+    pub fn with_replay_protection(mut self, window: Duration) -> Self {
+        self.router.freshness = Some(window);
+        self
+    }
+
+    /// Set the response sent for notifications whose route callback returns the generic success, [`SEPResponse::NoContent`].
+    ///
+    /// Head-ends differ in whether they expect a 204 No Content, or a 200 OK with an empty body, for an accepted notification.
+    /// Other responses returned by callbacks, such as [`SEPResponse::Created`], are sent as-is.
+    ///
+    /// Defaults to [`SuccessResponse::NoContent`], if this function is not called.
+    pub fn with_success_response(mut self, success: SuccessResponse) -> Self {
+        self.router.success = success;
+        self
+    }
+
+    /// Report connection & notification handling events to the given [`NotifObserver`], such as [`NotifMetrics`].
+    ///
+    /// An observer that must also be read from elsewhere can be shared by passing an [`Arc`] of it.
+    pub fn with_observer(mut self, observer: impl NotifObserver) -> Self {
+        self.router.observer = Arc::new(observer);
+        self
+    }
+
+    /// Deliver every notification that could not be deserialized, alongside it's path and raw body, to the given sink.
+    ///
+    /// This allows offending payloads to be captured for debugging, or replayed. The sink is called from the connection's task,
+    /// and should return quickly, such as by forwarding the failure to a channel:
+    /// ```no_run
+    /// # use sep2_client::pubsub::ClientNotifServer;
+    /// let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    /// let server = ClientNotifServer::new("127.0.0.1:1338")
+    ///     .unwrap()
+    ///     .with_error_sink(move |failure| {
+    ///         let _ = tx.send(failure);
+    ///     });
+    /// ```
+    pub fn with_error_sink(
+        mut self,
+        sink: impl Fn(DeserializeFailure) + Send + Sync + 'static,
+    ) -> Self {
+        self.router.error_sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Pass the details of every failed TLS handshake to the given callback, such as for troubleshooting mutual TLS with a server.
+    ///
+    /// These details are always logged, this callback allows them to be collected elsewhere.
+    /// The callback is called from the task handling the connection, and should return quickly.
+    pub fn with_handshake_diagnostics(
+        mut self,
+        callback: impl Fn(&HandshakeDiagnostics) + Send + Sync + 'static,
+    ) -> Self {
+        self.diagnostics = Some(Arc::new(callback));
+        self
+    }
+
+    /// Run the given hook during graceful shutdown, once all connections have been closed, such as to release resources held by route callbacks.
+    ///
+    /// Connections still open once the timeout set by [`ClientNotifServer::with_drain_timeout`] elapses are aborted before the hooks are run.
+    ///
+    /// Hooks are run in the order they were added, after which the routes, and their callbacks, are dropped, before [`ClientNotifServer::run`] returns.
+    /// Hooks are not run if the server could not be started, nor if it's task is aborted.
+    pub fn with_teardown<F>(mut self, hook: impl FnOnce() -> F + Send + 'static) -> Self
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.teardown.push(Box::new(move || Box::pin(hook())));
+        self
+    }
+
+    /// Set how many times [`ClientNotifServer::run`] will retry binding to the server address, if it is in use, before giving up.
+    ///
+    /// The first retry occurs after `backoff`, with the wait doubling after each subsequent failed attempt.
+    ///
+    /// Defaults to 3 retries, with an initial backoff of 500ms, if this function is not called.
+    pub fn with_bind_retry(mut self, retries: u32, backoff: Duration) -> Self {
+        self.bind_retries = retries;
+        self.bind_backoff = backoff;
+        self
     }
 
-    async fn router(&self, req: Request<Body>) -> Result<Response<Body>> {
-        let path = req.uri().path().to_owned();
-        match self.routes.get(&path) {
-            Some(func) => {
-                let method = req.method();
-                match method {
-                    &Method::POST => {
-                        let body = req.into_body();
-                        let bytes = hyper::body::to_bytes(body).await?;
-                        let xml = String::from_utf8(bytes.to_vec())?;
-                        Ok(hyper::Response::try_from(func(&xml).await)?)
-                    }
-                    _ => {
-                        hyper::Response::try_from(SEPResponse::MethodNotAllowed("POST".to_owned()))
-                    }
-                }
-            }
-            None => hyper::Response::try_from(SEPResponse::NotFound),
-        }
+    /// Set how long a TLS handshake may take before the connection is dropped.
+    ///
+    /// Handshakes are performed by the task handling each connection, such that a slow or stalled peer does not delay accepting other connections.
+    /// A handshake that waits on the peer's data is resumed as it arrives, until this timeout elapses.
+    ///
+    /// Defaults to 10 seconds, if this function is not called.
+    pub fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
     }
-}
 
-/// A lightweight IEEE 2030.5 Server for receiving [`Notification<T>`] resources from a server for the subscription / notification mechanism.
-pub struct ClientNotifServer {
-    addr: SocketAddr,
-    cfg: Option<TlsServerConfig>,
-    router: Router,
-}
+    /// Set how long a graceful shutdown waits for open connections to finish their in-flight requests, before they are aborted.
+    ///
+    /// Once shutdown begins, idle connections are closed, and all others are closed once their in-flight request has been responded to.
+    ///
+    /// Defaults to 30 seconds, if this function is not called.
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
 
-impl ClientNotifServer {
-    /// Create a new Notification server that listens on the given address
-    pub fn new(addr: impl net::ToSocketAddrs) -> Result<Self> {
-        Ok(ClientNotifServer {
-            addr: addr
-                .to_socket_addrs()?
-                .next()
-                .context("Given server address did not yield a SocketAddr")?,
-            cfg: None,
-            router: Router::new(),
-        })
+    /// Create a [`ShutdownHandle`] for this server, that can be used to trigger a graceful shutdown,
+    /// and query the state of the server once [`ClientNotifServer::run`] is called.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            shutdown: self.shutdown.clone(),
+            state: self.state.subscribe(),
+            conns: self.conns.clone(),
+        }
     }
 
+    /// Use HTTPS for the server, with the given certificate, private key, and trusted root CA.
+    ///
+    /// `rootca_path` may be either a single PEM file, or a directory of PEM files, in which case all certificates in the directory are trusted.
+    ///
+    /// The private key must be unencrypted, passphrase protected keys can be loaded using [`ClientIdentity::from_encrypted_pem_files`].
     pub fn with_https(
         mut self,
         cert_path: impl AsRef<Path>,
@@ -102,6 +1275,45 @@ impl ClientNotifServer {
         Ok(self)
     }
 
+    /// Use HTTPS for the server, with an already loaded certificate & private key, and the given trusted root CA.
+    ///
+    /// This allows the same [`ClientIdentity`] to be used by both a [`Client`] and it's notification server.
+    pub fn with_https_identity(
+        mut self,
+        identity: &ClientIdentity,
+        rootca_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        self.cfg = Some(create_server_tls_config_from_identity(
+            identity,
+            rootca_path,
+        )?);
+        Ok(self)
+    }
+
+    /// Handle a single request as the server would, without binding to it's address, or performing a TLS handshake.
+    ///
+    /// The request is dispatched to the route for it's path, and the response that would be sent over the connection is returned.
+    /// This allows route dispatch, method handling & deserialization to be tested directly:
+    /// ```no_run
+    /// # use sep2_client::{client::SEPResponse, pubsub::ClientNotifServer};
+    /// # use sep2_common::packages::{dcap::DeviceCapability, pubsub::Notification};
+    /// # use hyper::{Body, Request, StatusCode};
+    /// # async fn test() {
+    /// let server = ClientNotifServer::new("127.0.0.1:1338")
+    ///     .unwrap()
+    ///     .add("/dcap", |_: Notification<DeviceCapability>| async {
+    ///         SEPResponse::Created(None)
+    ///     });
+    /// let body = sep2_common::serialize(&Notification::<DeviceCapability>::default()).unwrap();
+    /// let req = Request::post("/dcap").body(Body::from(body)).unwrap();
+    /// let res = server.handle(req).await.unwrap();
+    /// assert_eq!(res.status(), StatusCode::CREATED);
+    /// # }
+    /// ```
+    pub async fn handle(&self, req: Request<Body>) -> Result<Response<Body>> {
+        self.router.router(req).await
+    }
+
     /// Add a route to the server.
     /// Given:
     /// - A relative URI of the form "/foo"
@@ -109,53 +1321,119 @@ impl ClientNotifServer {
     ///
     /// The `RouteCallback` trait can be implemented on any threadsafe type,
     /// however it is automatically implemented for any applicable 'Fn'
+    ///
+    /// If a route with the same path was already added, it is handled as per [`ClientNotifServer::with_duplicate_routes`].
     pub fn add<T>(mut self, path: impl Into<String>, callback: impl RouteCallback<T>) -> Self
     where
         T: SEResource,
     {
-        let path = path.into();
-        let new: RouteHandler = Box::new({
-            let log_path = path.clone();
-            move |e| {
-                let e = deserialize::<Notification<T>>(e);
-                match e {
-                    Ok(resource) => {
-                        log::debug!(
-                            "NotifServer: Successfully deserialized a resource on {log_path}"
-                        );
-                        let callback = callback.clone();
-                        Box::pin(async move { callback.callback(resource).await })
-                    }
-                    Err(err) => {
-                        log::error!(
-                            "NotifServer: Failed to deserialize resource on {log_path}: {err}"
-                        );
-                        Box::pin(async { SEPResponse::BadRequest(None) })
-                    }
-                }
-            }
-        });
-        self.router.routes.insert(path, new);
+        if let Err(err) = self.router.routes.add(path, callback) {
+            self.route_error.get_or_insert(err);
+        }
+        self
+    }
+
+    /// Add a route to the server, for notifications of a list resource, such as those of a Subscription to a `DERControlList`.
+    ///
+    /// The callback receives all items of the list included in the notification at once, as a [`ListNotification`].
+    /// Notifications containing a single item of the list, rather than the list itself, are also accepted.
+    pub fn add_list<L>(
+        mut self,
+        path: impl Into<String>,
+        callback: impl ListRouteCallback<L>,
+    ) -> Self
+    where
+        L: SEList,
+        L::Inner: SEResource,
+    {
+        if let Err(err) = self.router.routes.add_list(path, callback) {
+            self.route_error.get_or_insert(err);
+        }
+        self
+    }
+
+    /// Set how a route added with the path of an existing route is handled, by both [`ClientNotifServer::add`] & [`RouteTable::add`].
+    ///
+    /// If duplicates are rejected, the existing route is kept, and [`ClientNotifServer::run`] returns a [`DuplicateRoute`] error,
+    /// such that accidental collisions are caught during setup.
+    ///
+    /// Defaults to [`DuplicateRoutes::Replace`], if this function is not called.
+    pub fn with_duplicate_routes(self, duplicates: DuplicateRoutes) -> Self {
+        self.router
+            .routes
+            .reject_duplicates
+            .store(duplicates == DuplicateRoutes::Reject, Ordering::Relaxed);
         self
     }
 
+    /// Return a [`RouteTable`] handle to the routes of this server, such that routes can be added & removed while it is running.
+    pub fn route_table(&self) -> RouteTable {
+        self.router.routes.clone()
+    }
+
+    /// Start the Notification Server on a new task, as per [`ClientNotifServer::run`].
+    ///
+    /// Returns a [`RunningServer`] that can be used to monitor & stop the server.
+    pub fn spawn(self, shutdown: impl Future + Send + 'static) -> RunningServer {
+        let handle = self.shutdown_handle();
+        let task = tokio::spawn(self.run(shutdown));
+        RunningServer { handle, task }
+    }
+
     /// Start the Notification Server.
     ///
-    /// When the provided `shutdown` future completes, the server will shutdown gracefully.
+    /// When the provided `shutdown` future completes, or [`ShutdownHandle::shutdown`] is called, the server will shutdown gracefully.
     ///
     /// This function will return an error IFF the server could not be started.
     /// It will recover from all other errors.
     pub async fn run(self, shutdown: impl Future) -> Result<()> {
         tokio::pin!(shutdown);
-        let acceptor = self.cfg.map(|cfg| cfg.build());
+        let stopped = StoppedGuard(self.state.clone());
+        if let Some(err) = self.route_error {
+            return Err(err);
+        }
+        let mut cfg = self.cfg;
+        let hello = match cfg.as_mut().map(record_client_hello).transpose() {
+            Ok(hello) => hello,
+            Err(err) => return Err(err),
+        };
+        let acceptor = cfg.map(|cfg| cfg.build());
         let router = Arc::new(self.router);
-        let listener = TcpListener::bind(self.addr).await?;
+        let observer = router.observer.clone();
+        let mut backoff = self.bind_backoff;
+        let mut attempt = 0;
+        let listener = loop {
+            match bind(
+                self.addr,
+                self.backlog,
+                self.send_buffer_size,
+                self.recv_buffer_size,
+            ) {
+                Ok(listener) => break listener,
+                Err(err) if attempt < self.bind_retries => {
+                    attempt += 1;
+                    log::warn!(
+                        "NotifServer: Failed to bind to {}: {err}, retrying in {:?} ({attempt}/{})",
+                        self.addr,
+                        backoff,
+                        self.bind_retries
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        };
+        let mut shutdown_rx = self.shutdown.subscribe();
         let mut set = tokio::task::JoinSet::new();
+        let conns = self.conns;
+        self.state.send_replace(ServerState::Running);
         log::info!("NotifServer: Listening on {}", self.addr);
         loop {
             // Accept TCP Connection
             let (stream, addr) = tokio::select! {
                 _ = &mut shutdown => break,
+                _ = shutdown_rx.wait_for(|sd| *sd) => break,
                 res = listener.accept() => match res {
                     Ok((s,a)) => (s,a),
                     Err(err) => {
@@ -165,6 +1443,15 @@ impl ClientNotifServer {
                 }
             };
             log::debug!("NotifServer: Remote connecting from {}", addr);
+            let Some(guard) = ConnGuard::acquire(&conns, addr.ip(), self.per_ip_limit) else {
+                log::warn!(
+                    "NotifServer: Rejecting connection from {}, as it has reached the limit of concurrent connections",
+                    addr
+                );
+                observer.connection_rejected(addr);
+                continue;
+            };
+            observer.connection_accepted(addr);
 
             // Bind connection to service
             let service = service_fn({
@@ -175,33 +1462,1119 @@ impl ClientNotifServer {
                 }
             });
 
+            let conn_shutdown = self.shutdown.subscribe();
             if let Some(acceptor) = &acceptor {
-                // Perform TLS handshake
-                let ssl = Ssl::new(acceptor.context())?;
-                let stream = SslStream::new(ssl, stream)?;
-                let mut stream = Box::pin(stream);
-                if let Err(e) = stream.as_mut().accept().await {
-                    log::error!("NotifServer: Failed to perform TLS handshake: {e}");
-                    continue;
-                }
+                let stream = match Ssl::new(acceptor.context())
+                    .and_then(|ssl| SslStream::new(ssl, stream))
+                {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        log::error!("NotifServer: Failed to create TLS stream: {err}");
+                        continue;
+                    }
+                };
+                let observer = observer.clone();
+                let callback = self.diagnostics.clone();
+                let timeout = self.handshake_timeout;
                 set.spawn(async move {
-                    if let Err(err) = Http::new().serve_connection(stream, service).await {
+                    // Perform TLS handshake
+                    let mut stream = Box::pin(stream);
+                    let error = match tokio::time::timeout(timeout, stream.as_mut().accept()).await
+                    {
+                        Ok(Ok(())) => None,
+                        Ok(Err(e)) => Some(e.to_string()),
+                        Err(_) => Some(format!("Handshake timed out after {timeout:?}")),
+                    };
+                    if let Some(error) = error {
+                        let diagnostics =
+                            HandshakeDiagnostics::new(addr, error, stream.ssl(), hello);
+                        log::error!("NotifServer: Failed to perform TLS handshake: {diagnostics}");
+                        if let Some(callback) = &callback {
+                            callback(&diagnostics);
+                        }
+                        observer.handshake_failed(addr);
+                        return;
+                    }
+                    if let Err(err) = serve_connection!(stream, service, conn_shutdown) {
                         log::error!("NotifServer: Failed to handle HTTPS connection: {err}");
                     }
+                    drop(guard);
                 });
             // No TLS
             } else {
                 set.spawn(async move {
-                    if let Err(err) = Http::new().serve_connection(stream, service).await {
+                    if let Err(err) = serve_connection!(stream, service, conn_shutdown) {
                         log::error!("NotifServer: Failed to handle HTTP connection: {err}");
                     }
+                    drop(guard);
                 });
             }
         }
-        // Wait for all connection handlers to finish
+        // Signal open connections to close once their in-flight request is responded to, and wait for them to finish
         log::debug!("NotifServer: Attempting graceful shutdown");
-        set.shutdown().await;
+        self.state.send_replace(ServerState::Draining);
+        self.shutdown.send_replace(true);
+        let drain = async { while set.join_next().await.is_some() {} };
+        if tokio::time::timeout(self.drain_timeout, drain)
+            .await
+            .is_err()
+        {
+            log::warn!(
+                "NotifServer: Aborting {} connection(s) still open after {:?}",
+                set.len(),
+                self.drain_timeout
+            );
+            set.shutdown().await;
+        }
+        log::debug!(
+            "NotifServer: Running {} teardown hook(s)",
+            self.teardown.len()
+        );
+        for hook in self.teardown {
+            hook().await;
+        }
+        // All connections, and hence all other references to the router, have been dropped.
+        // Routes are cleared as they may also be referenced by RouteTable handles.
+        router.routes.clear();
+        drop(router);
+        drop(stopped);
         log::info!("NotifServer: Server has been shutdown.");
         Ok(())
     }
 }
+
+/// Determine whether the server supports subscriptions to the given resource, as per it's `subscribable` attribute.
+pub fn is_subscribable<R: SESubscribableResource>(resource: &R) -> bool {
+    resource
+        .subscribable()
+        .is_some_and(|s| s != SubscribableType::NoSubscriptionsSupported)
+}
+
+/// How updates to a resource will be received, as determined by [`Client::subscribe_or_poll`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateMethod {
+    /// A Subscription was created, w/ the Location header value of the Subscription, if it exists
+    Subscribed(Option<String>),
+    /// The resource is being polled using [`Client::start_poll`]
+    Polling,
+}
+
+/// Why a `notificationURI` could not be constructed, or reached, as returned by [`notification_uri`] & [`check_notification_uri`].
+///
+/// Returned within an [`anyhow::Error`], and can be retrieved using [`anyhow::Error::downcast_ref`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationUriError {
+    /// The URI could not be parsed, or is not an absolute URI with a host. Contains the reason.
+    Invalid(String),
+    /// The URI does not use HTTPS, as required by IEEE 2030.5.
+    NotHttps,
+    /// The route path is empty, or contains a query or fragment.
+    InvalidRoute(String),
+    /// A TCP connection could not be established to the URI's host. Contains the reason.
+    Unreachable(String),
+}
+
+impl std::fmt::Display for NotificationUriError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotificationUriError::Invalid(reason) => {
+                write!(f, "Invalid notification URI: {reason}")
+            }
+            NotificationUriError::NotHttps => write!(f, "Notification URI does not use HTTPS"),
+            NotificationUriError::InvalidRoute(route) => {
+                write!(f, "Invalid notification route path: {route}")
+            }
+            NotificationUriError::Unreachable(reason) => {
+                write!(f, "Notification URI is unreachable: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for NotificationUriError {}
+
+/// The server rejected the creation of a Subscription on a SubscriptionList, as returned by [`Client::subscribe`],
+/// indicating it does not support subscriptions there, even if the resource was advertised as subscribable.
+///
+/// Callers should switch to polling the resource instead, such as is done by [`Client::subscribe_or_poll`].
+///
+/// Returned within an [`anyhow::Error`], and can be retrieved using [`anyhow::Error::downcast_ref`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionsUnsupported {
+    /// The relative path of the SubscriptionList
+    pub subscription_list: String,
+    /// The status the server responded with, either 405 Method Not Allowed or 501 Not Implemented
+    pub status: StatusCode,
+}
+
+impl std::fmt::Display for SubscriptionsUnsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Subscriptions are not supported at {}, server responded with {}",
+            self.subscription_list, self.status
+        )
+    }
+}
+
+impl std::error::Error for SubscriptionsUnsupported {}
+
+/// Construct the absolute `notificationURI` of a [`Subscription`], for use with [`Client::subscribe`],
+/// from the public base URL of a [`ClientNotifServer`], and the path of one of it's routes.
+///
+/// The public base URL is the address at which the *server* can reach the notification server, such as `https://203.0.113.5:1338`.
+/// It may differ from the address the notification server listens on, such as when it is behind NAT.
+/// The base URL may include a path prefix, to which the route is appended.
+///
+/// Returns a [`NotificationUriError`] if the base URL is not an absolute HTTPS URL, or the route is not a path.
+pub fn notification_uri(public_base: &str, route: &str) -> Result<String> {
+    let base: hyper::Uri = public_base
+        .parse()
+        .map_err(|e: hyper::http::uri::InvalidUri| NotificationUriError::Invalid(e.to_string()))?;
+    match base.scheme_str() {
+        Some("https") => (),
+        Some(_) => return Err(NotificationUriError::NotHttps.into()),
+        None => {
+            return Err(NotificationUriError::Invalid("missing scheme".to_owned()).into());
+        }
+    }
+    let Some(authority) = base.authority() else {
+        return Err(NotificationUriError::Invalid("missing host".to_owned()).into());
+    };
+    if base.query().is_some() {
+        return Err(NotificationUriError::Invalid("base URL contains a query".to_owned()).into());
+    }
+    let route = route.trim_start_matches('/');
+    if route.is_empty() || route.contains(['?', '#']) {
+        return Err(NotificationUriError::InvalidRoute(route.to_owned()).into());
+    }
+    let prefix = base.path().trim_end_matches('/');
+    Ok(format!("https://{authority}{prefix}/{route}"))
+}
+
+/// Check that a TCP connection can be established to the host of the given `notificationURI`, within `timeout`.
+///
+/// This only determines whether the URI is reachable from this host, which may not be the case for the server, such as if a firewall is in the way.
+///
+/// Returns a [`NotificationUriError`] if the URI is invalid, or a connection could not be established.
+pub async fn check_notification_uri(uri: &str, timeout: Duration) -> Result<()> {
+    let uri: hyper::Uri = uri
+        .parse()
+        .map_err(|e: hyper::http::uri::InvalidUri| NotificationUriError::Invalid(e.to_string()))?;
+    let host = uri
+        .host()
+        .ok_or_else(|| NotificationUriError::Invalid("missing host".to_owned()))?;
+    // Strip the brackets of IPv6 literals
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = uri.port_u16().unwrap_or(443);
+    match tokio::time::timeout(timeout, tokio::net::TcpStream::connect((host, port))).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(NotificationUriError::Unreachable(e.to_string()).into()),
+        Err(_) => Err(NotificationUriError::Unreachable("timed out".to_owned()).into()),
+    }
+}
+
+impl Client {
+    /// Create a [`Subscription`] on the SubscriptionList at the given relative path, for the resource at `subscribed_resource`.
+    ///
+    /// [`Notification`] resources will be sent to `notification_uri`, which must be an absolute URI routing to a [`ClientNotifServer`],
+    /// such as one constructed by [`notification_uri`].
+    ///
+    /// `limit` is the maximum number of list items to be included in notifications,
+    /// or, for non-list resources, a limit of 0 will exclude the resource representation from notifications.
+    ///
+    /// If the server responds with 405 Method Not Allowed or 501 Not Implemented, a [`SubscriptionsUnsupported`] is returned.
+    pub async fn subscribe(
+        &self,
+        subscription_list: &str,
+        subscribed_resource: &str,
+        notification_uri: &str,
+        limit: Uint32,
+    ) -> Result<SEPResponse> {
+        let sub = Subscription {
+            condition: None,
+            encoding: HTTPEncoding::SEPXML,
+            level: String16("+S1".to_owned()),
+            limit,
+            notification_uri: notification_uri.to_owned(),
+            subscribed_resource: subscribed_resource.to_owned(),
+            href: None,
+        };
+        let unsupported = |status| SubscriptionsUnsupported {
+            subscription_list: subscription_list.to_owned(),
+            status,
+        };
+        match self.post(subscription_list, &sub).await {
+            Ok(SEPResponse::MethodNotAllowed(_)) => {
+                Err(unsupported(StatusCode::METHOD_NOT_ALLOWED).into())
+            }
+            Err(
+                err @ ClientError::ServerError {
+                    status: StatusCode::NOT_IMPLEMENTED,
+                    ..
+                },
+            ) => Err(anyhow::Error::new(err).context(unsupported(StatusCode::NOT_IMPLEMENTED))),
+            res => Ok(res?),
+        }
+    }
+
+    /// Given a previously retrieved resource, subscribe to it if the server supports subscriptions for it, otherwise begin polling it.
+    ///
+    /// A Subscription is created as per [`Client::subscribe`]. If the resource is not subscribable,
+    /// or the server does not create the Subscription, the resource is instead polled as per [`Client::start_poll`], with the given callback.
+    ///
+    /// Returns an error if the given resource does not have an href.
+    pub async fn subscribe_or_poll<R: SESubscribableResource>(
+        &self,
+        resource: &R,
+        subscription_list: &str,
+        notification_uri: &str,
+        limit: Uint32,
+        poll_rate: Option<Uint32>,
+        callback: impl PollCallback<R>,
+    ) -> Result<UpdateMethod> {
+        let href = resource
+            .href()
+            .context("Resource does not contain an href")?
+            .to_owned();
+        if is_subscribable(resource) {
+            match self
+                .subscribe(subscription_list, &href, notification_uri, limit)
+                .await
+            {
+                Ok(SEPResponse::Created(loc)) => return Ok(UpdateMethod::Subscribed(loc)),
+                Ok(res) => log::warn!(
+                    "Client: Subscription to {} at {href} was not created, with response: {res}. Falling back to polling.",
+                    R::name()
+                ),
+                Err(err) if err.downcast_ref::<SubscriptionsUnsupported>().is_some() => log::info!(
+                    "Client: {err}, polling {} at {href} instead.",
+                    R::name()
+                ),
+                Err(err) => log::warn!(
+                    "Client: Subscription to {} at {href} failed with reason: {err}. Falling back to polling.",
+                    R::name()
+                ),
+            }
+        } else {
+            log::info!(
+                "Client: {} at {href} is not subscribable, polling instead.",
+                R::name()
+            );
+        }
+        self.start_poll(href, poll_rate, callback).await;
+        Ok(UpdateMethod::Polling)
+    }
+}
+
+/// Manages many Subscriptions, whose notifications are all received by a single running [`ClientNotifServer`].
+///
+/// [`SubscriptionManager::subscribe`] & [`SubscriptionManager::unsubscribe`] both add or remove the route on the server,
+/// and create or delete the Subscription on the IEEE 2030.5 server, using the [`Client`], such that the two remain in sync.
+///
+/// If the client has a [`StateStore`](crate::persist::StateStore), Subscriptions are saved to it, and can be re-established after a restart using [`SubscriptionManager::resume`].
+pub struct SubscriptionManager {
+    client: Client,
+    server: RunningServer,
+    routes: RouteTable,
+    public_base: String,
+    subscription_list: String,
+    // The Location of the Subscription of each route
+    subscriptions: tokio::sync::Mutex<HashMap<String, String>>,
+}
+
+impl SubscriptionManager {
+    /// Spawn the given server, as per [`ClientNotifServer::spawn`], to receive the notifications of Subscriptions
+    /// created on the SubscriptionList at the given relative path, such as an [`EndDevice`]'s SubscriptionListLink.
+    ///
+    /// `public_base` is the base URL the server is reachable at, as per [`notification_uri`].
+    ///
+    /// [`EndDevice`]: sep2_common::packages::edev::EndDevice
+    pub fn start(
+        server: ClientNotifServer,
+        client: Client,
+        public_base: impl Into<String>,
+        subscription_list: impl Into<String>,
+    ) -> Self {
+        let routes = server.route_table();
+        SubscriptionManager {
+            client,
+            server: server.spawn(std::future::pending::<()>()),
+            routes,
+            public_base: public_base.into(),
+            subscription_list: subscription_list.into(),
+            subscriptions: tokio::sync::Mutex::default(),
+        }
+    }
+
+    /// Subscribe to the resource at `subscribed_resource`, receiving it's notifications on the given route, as per [`Client::subscribe`].
+    ///
+    /// Returns the Location of the created Subscription.
+    /// Returns an error if the route is already in use, or if the server did not create the Subscription, in which case the route is removed.
+    /// If the server does not support subscriptions on the SubscriptionList, the error is a [`SubscriptionsUnsupported`].
+    pub async fn subscribe<T>(
+        &self,
+        route: &str,
+        subscribed_resource: &str,
+        limit: Uint32,
+        callback: impl RouteCallback<T>,
+    ) -> Result<String>
+    where
+        T: SEResource,
+    {
+        let mut subs = self.subscriptions.lock().await;
+        if self.routes.contains(route) {
+            bail!("Route {route} is already in use");
+        }
+        self.routes.add(route, callback)?;
+        self.create(&mut subs, route, T::name(), subscribed_resource, limit)
+            .await
+    }
+
+    /// Subscribe to the list resource at `subscribed_resource`, as per [`SubscriptionManager::subscribe`],
+    /// receiving it's notifications on a route added as per [`ClientNotifServer::add_list`].
+    pub async fn subscribe_list<L>(
+        &self,
+        route: &str,
+        subscribed_resource: &str,
+        limit: Uint32,
+        callback: impl ListRouteCallback<L>,
+    ) -> Result<String>
+    where
+        L: SEList,
+        L::Inner: SEResource,
+    {
+        let mut subs = self.subscriptions.lock().await;
+        if self.routes.contains(route) {
+            bail!("Route {route} is already in use");
+        }
+        self.routes.add_list(route, callback)?;
+        self.create(&mut subs, route, L::name(), subscribed_resource, limit)
+            .await
+    }
+
+    // Create the Subscription for a newly added route, receiving the given resource type, removing the route if it could not be created
+    async fn create(
+        &self,
+        subs: &mut HashMap<String, String>,
+        route: &str,
+        resource: &str,
+        subscribed_resource: &str,
+        limit: Uint32,
+    ) -> Result<String> {
+        let res = match notification_uri(&self.public_base, route) {
+            Ok(uri) => {
+                self.client
+                    .subscribe(&self.subscription_list, subscribed_resource, &uri, limit)
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+        match res {
+            Ok(SEPResponse::Created(Some(loc))) => {
+                log::info!(
+                    "SubscriptionManager: Subscribed to {subscribed_resource} on {route}, at {loc}"
+                );
+                subs.insert(route.to_owned(), loc.clone());
+                self.client
+                    .state_recorder()
+                    .record_subscription(SubscriptionRecord {
+                        route: route.to_owned(),
+                        resource: resource.to_owned(),
+                        subscription_list: self.subscription_list.clone(),
+                        subscribed_resource: subscribed_resource.to_owned(),
+                        location: loc.clone(),
+                        limit: limit.get(),
+                    });
+                Ok(loc)
+            }
+            Ok(res) => {
+                self.routes.remove(route);
+                bail!("Subscription to {subscribed_resource} was not created, with response: {res}")
+            }
+            Err(err) => {
+                self.routes.remove(route);
+                Err(err)
+            }
+        }
+    }
+
+    /// Delete the Subscription of the given route from the server, and then remove the route.
+    ///
+    /// Returns an error if the route has no Subscription, or if it could not be deleted, in which case the route is kept,
+    /// as the server may continue to send notifications to it.
+    pub async fn unsubscribe(&self, route: &str) -> Result<()> {
+        let mut subs = self.subscriptions.lock().await;
+        let loc = subs
+            .get(route)
+            .with_context(|| format!("Route {route} has no Subscription"))?;
+        match self.client.delete(loc).await? {
+            SEPResponse::NoContent => (),
+            res => bail!("Subscription at {loc} was not deleted, with response: {res}"),
+        }
+        log::info!("SubscriptionManager: Unsubscribed from {loc} on {route}");
+        subs.remove(route);
+        self.routes.remove(route);
+        self.client.state_recorder().remove_subscription(route);
+        Ok(())
+    }
+
+    /// Re-establish the Subscriptions on this manager's SubscriptionList saved to the client's [`StateStore`](crate::persist::StateStore),
+    /// such as by a previous run of the device.
+    ///
+    /// The route of each Subscription is added with the callback registered for it's resource type, and the Subscription is retrieved from the server.
+    /// If it no longer exists, or no longer matches the route, it is created again, as per [`SubscriptionManager::subscribe`].
+    /// If it could not be retrieved due to a connection error, it is assumed to still exist.
+    ///
+    /// Subscriptions of resource types without a callback, or whose route is already in use, are logged, and discarded.
+    ///
+    /// Returns the routes resumed, or an error if the client has no [`StateStore`](crate::persist::StateStore).
+    pub async fn resume(&self, callbacks: &ResumeCallbacks) -> Result<Vec<String>> {
+        let records = self
+            .client
+            .state_recorder()
+            .take_subscriptions(&self.subscription_list)?;
+        let mut subs = self.subscriptions.lock().await;
+        let mut out = vec![];
+        for record in records {
+            let route = record.route.clone();
+            if self.routes.contains(&route) {
+                log::warn!("SubscriptionManager: Route {route} is already in use, discarding it's persisted Subscription");
+                continue;
+            }
+            match callbacks.add_route(&self.routes, &record) {
+                Ok(true) => (),
+                Ok(false) => {
+                    log::warn!(
+                        "SubscriptionManager: No callback for persisted Subscription of Resource {} on {route}, discarding it",
+                        record.resource
+                    );
+                    continue;
+                }
+                Err(err) => {
+                    log::warn!("SubscriptionManager: Failed to add route {route}: {err}");
+                    continue;
+                }
+            }
+            if self.is_valid(&record).await {
+                log::info!(
+                    "SubscriptionManager: Resumed Subscription to {} on {route}, at {}",
+                    record.subscribed_resource,
+                    record.location
+                );
+                subs.insert(route.clone(), record.location.clone());
+                self.client.state_recorder().record_subscription(record);
+            } else {
+                log::info!(
+                    "SubscriptionManager: Persisted Subscription at {} is no longer valid, creating it again",
+                    record.location
+                );
+                if let Err(err) = self
+                    .create(
+                        &mut subs,
+                        &route,
+                        &record.resource,
+                        &record.subscribed_resource,
+                        Uint32(record.limit),
+                    )
+                    .await
+                {
+                    log::warn!(
+                        "SubscriptionManager: Failed to resume Subscription on {route}: {err}"
+                    );
+                    continue;
+                }
+            }
+            out.push(route);
+        }
+        self.client.state_recorder().save();
+        Ok(out)
+    }
+
+    // Whether a persisted Subscription still exists on the server, and notifies the same route
+    async fn is_valid(&self, record: &SubscriptionRecord) -> bool {
+        match self.client.get::<Subscription>(&record.location).await {
+            Ok(sub) => {
+                sub.subscribed_resource == record.subscribed_resource
+                    && notification_uri(&self.public_base, &record.route)
+                        .is_ok_and(|uri| uri == sub.notification_uri)
+            }
+            Err(err @ ClientError::Transport(_)) => {
+                log::warn!(
+                    "SubscriptionManager: Failed to retrieve the Subscription at {}, assuming it still exists: {err}",
+                    record.location
+                );
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// The Location of the Subscription of each route.
+    pub async fn subscriptions(&self) -> HashMap<String, String> {
+        self.subscriptions.lock().await.clone()
+    }
+
+    /// The running server receiving notifications.
+    pub fn server(&self) -> &RunningServer {
+        &self.server
+    }
+
+    /// Delete all Subscriptions, and then shutdown the server, waiting for it to stop.
+    ///
+    /// Subscriptions that could not be deleted are logged.
+    pub async fn shutdown(self) -> Result<()> {
+        let routes: Vec<String> = self.subscriptions.lock().await.keys().cloned().collect();
+        for route in routes {
+            if let Err(err) = self.unsubscribe(&route).await {
+                log::warn!("SubscriptionManager: Failed to unsubscribe {route}: {err}");
+            }
+        }
+        self.server.shutdown_handle().shutdown();
+        self.server.join().await
+    }
+}
+
+#[test]
+fn subscribable_resources() {
+    use sep2_common::packages::edev::EndDevice;
+    let mut edev = EndDevice::default();
+    assert!(!is_subscribable(&edev));
+    edev.subscribable = Some(SubscribableType::NoSubscriptionsSupported);
+    assert!(!is_subscribable(&edev));
+    edev.subscribable = Some(SubscribableType::NonConditionalSubscriptions);
+    assert!(is_subscribable(&edev));
+}
+
+#[test]
+fn notification_freshness() {
+    let now = SystemTime::now();
+    let window = Duration::from_secs(60);
+    let date = |t: SystemTime| HeaderValue::from_str(&httpdate::fmt_http_date(t)).unwrap();
+    assert!(is_fresh(Some(&date(now)), now, window));
+    assert!(is_fresh(
+        Some(&date(now - Duration::from_secs(30))),
+        now,
+        window
+    ));
+    assert!(!is_fresh(
+        Some(&date(now - Duration::from_secs(120))),
+        now,
+        window
+    ));
+    assert!(!is_fresh(
+        Some(&date(now + Duration::from_secs(120))),
+        now,
+        window
+    ));
+    assert!(!is_fresh(None, now, window));
+    assert!(!is_fresh(
+        Some(&HeaderValue::from_static("yesterday")),
+        now,
+        window
+    ));
+}
+
+#[test]
+fn notification_encoding() {
+    let ct = |v: &'static str| Some(HeaderValue::from_static(v));
+    assert_eq!(NotifEncoding::from_content_type(None), NotifEncoding::Xml);
+    assert_eq!(
+        NotifEncoding::from_content_type(ct("application/sep+xml").as_ref()),
+        NotifEncoding::Xml
+    );
+    assert_eq!(
+        NotifEncoding::from_content_type(ct("application/sep-exi; level=-S1").as_ref()),
+        NotifEncoding::Exi
+    );
+}
+
+#[test]
+fn per_ip_connection_limit() {
+    let conns = ConnCounts::default();
+    let ip: IpAddr = [127, 0, 0, 1].into();
+    let other: IpAddr = [127, 0, 0, 2].into();
+    let a = ConnGuard::acquire(&conns, ip, Some(2)).unwrap();
+    let _b = ConnGuard::acquire(&conns, ip, Some(2)).unwrap();
+    assert!(ConnGuard::acquire(&conns, ip, Some(2)).is_none());
+    assert!(ConnGuard::acquire(&conns, other, Some(2)).is_some());
+    drop(a);
+    assert!(ConnGuard::acquire(&conns, ip, Some(2)).is_some());
+    assert!(ConnGuard::acquire(&conns, ip, None).is_some());
+}
+
+#[tokio::test]
+async fn notification_metrics() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    let metrics = Arc::new(NotifMetrics::default());
+    let server = ClientNotifServer::new("127.0.0.1:0")
+        .unwrap()
+        .with_observer(metrics.clone())
+        .add("/dcap", |_: Notification<DeviceCapability>| async {
+            SEPResponse::Created(None)
+        });
+    let post = |path: &str, body: String| Request::post(path).body(Body::from(body)).unwrap();
+    let notif = sep2_common::serialize(&Notification::<DeviceCapability>::default()).unwrap();
+    let router = &server.router;
+    router.router(post("/dcap", notif.clone())).await.unwrap();
+    router
+        .router(post("/dcap", "<Notification>".into()))
+        .await
+        .unwrap();
+    router.router(post("/edev", notif)).await.unwrap();
+    router
+        .router(Request::get("/dcap").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    let dcap = metrics.route("/dcap").unwrap();
+    assert_eq!(dcap.handled, 1);
+    assert_eq!(dcap.rejected[&NotifRejection::Malformed], 1);
+    assert_eq!(dcap.rejected[&NotifRejection::MethodNotAllowed], 1);
+    assert_eq!(dcap.mean_callback_time(), dcap.callback_time);
+    let edev = metrics.route("/edev").unwrap();
+    assert_eq!(edev.handled, 0);
+    assert_eq!(edev.rejected[&NotifRejection::NotFound], 1);
+    assert_eq!(metrics.routes().len(), 2);
+}
+
+#[test]
+fn notif_server_builder() {
+    let server = ClientNotifServer::builder("127.0.0.1:0")
+        .with_backlog(8)
+        .with_per_ip_limit(2)
+        .with_replay_protection(Duration::from_secs(30))
+        .with_success_response(SuccessResponse::Ok)
+        .with_bind_retry(1, Duration::from_millis(10))
+        .with_drain_timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+    assert_eq!(server.backlog, 8);
+    assert_eq!(server.drain_timeout, Duration::from_secs(5));
+    assert_eq!(server.per_ip_limit, Some(2));
+    assert_eq!(server.router.freshness, Some(Duration::from_secs(30)));
+    assert_eq!(server.router.success, SuccessResponse::Ok);
+    assert_eq!(server.bind_retries, 1);
+    assert!(server.cfg.is_none());
+
+    // Certificates are only loaded once the server is built
+    let builder = ClientNotifServer::builder("127.0.0.1:0").with_https(
+        "missing_cert.pem",
+        "missing_key.pem",
+        "missing_ca.pem",
+    );
+    assert!(builder.build().is_err());
+    assert!(ClientNotifServer::builder("not an address")
+        .build()
+        .is_err());
+}
+
+#[tokio::test]
+async fn success_response_applied() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    let notif = sep2_common::serialize(&Notification::<DeviceCapability>::default()).unwrap();
+    let respond = |success, res: SEPResponse| {
+        let server = ClientNotifServer::new("127.0.0.1:0")
+            .unwrap()
+            .with_success_response(success)
+            .add("/dcap", move |_: Notification<DeviceCapability>| {
+                let res = res.clone();
+                async move { res }
+            });
+        let req = Request::post("/dcap")
+            .body(Body::from(notif.clone()))
+            .unwrap();
+        async move { server.router.router(req).await.unwrap() }
+    };
+    let res = respond(SuccessResponse::NoContent, SEPResponse::NoContent).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = respond(SuccessResponse::Ok, SEPResponse::NoContent).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(hyper::body::to_bytes(res.into_body())
+        .await
+        .unwrap()
+        .is_empty());
+    // Only the generic success is replaced
+    let res = respond(SuccessResponse::Ok, SEPResponse::Created(None)).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn deserialize_error_sink() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    let failures = Arc::new(std::sync::Mutex::new(vec![]));
+    let server = ClientNotifServer::new("127.0.0.1:0")
+        .unwrap()
+        .with_error_sink({
+            let failures = failures.clone();
+            move |failure| failures.lock().unwrap().push(failure)
+        })
+        .add("/dcap", |_: Notification<DeviceCapability>| async {
+            SEPResponse::Created(None)
+        });
+    let post = |body: Body| Request::post("/dcap").body(body).unwrap();
+    let notif = sep2_common::serialize(&Notification::<DeviceCapability>::default()).unwrap();
+    let res = server.router.router(post(notif.into())).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let res = server
+        .router
+        .router(post("<Notification>".into()))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = server
+        .router
+        .router(post(vec![0xff, 0xfe].into()))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let failures = failures.lock().unwrap();
+    assert_eq!(failures.len(), 2);
+    assert_eq!(failures[0].path, "/dcap");
+    assert_eq!(failures[0].body, "<Notification>");
+    assert_eq!(failures[1].body, "\u{fffd}\u{fffd}");
+}
+
+#[tokio::test]
+async fn subscriptions_unsupported() {
+    use crate::client::ScriptedTransport;
+    let status = |status| {
+        Response::builder()
+            .status(status)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header(hyper::header::ALLOW, "GET")
+            .body(Body::empty())
+            .unwrap(),
+        status(StatusCode::NOT_IMPLEMENTED),
+        status(StatusCode::INTERNAL_SERVER_ERROR),
+    ]);
+    let client = Client::with_transport("http://fake", transport, None);
+    let subscribe = || client.subscribe("/edev/1/sub", "/dcap", "https://fake/dcap", Uint32(0));
+    for expected in [StatusCode::METHOD_NOT_ALLOWED, StatusCode::NOT_IMPLEMENTED] {
+        let err = subscribe().await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<SubscriptionsUnsupported>(),
+            Some(&SubscriptionsUnsupported {
+                subscription_list: "/edev/1/sub".to_owned(),
+                status: expected,
+            })
+        );
+    }
+    // Other errors are returned as-is
+    let err = subscribe().await.unwrap_err();
+    assert!(err.downcast_ref::<SubscriptionsUnsupported>().is_none());
+}
+
+#[tokio::test]
+async fn bind_with_buffer_sizes() {
+    let addr = "127.0.0.1:0".parse().unwrap();
+    assert!(bind(addr, 16, Some(256 * 1024), Some(256 * 1024)).is_ok());
+    assert!(bind(addr, 16, None, None).is_ok());
+}
+
+#[tokio::test]
+async fn notification_uri_construction() {
+    assert_eq!(
+        notification_uri("https://203.0.113.5:1338", "/dcap").unwrap(),
+        "https://203.0.113.5:1338/dcap"
+    );
+    assert_eq!(
+        notification_uri("https://example.com/ntfy/", "edev").unwrap(),
+        "https://example.com/ntfy/edev"
+    );
+    let err = |base, route| {
+        notification_uri(base, route)
+            .unwrap_err()
+            .downcast::<NotificationUriError>()
+            .unwrap()
+    };
+    assert_eq!(
+        err("http://203.0.113.5:1338", "/dcap"),
+        NotificationUriError::NotHttps
+    );
+    assert!(matches!(
+        err("/ntfy", "/dcap"),
+        NotificationUriError::Invalid(_)
+    ));
+    assert!(matches!(
+        err("https://example.com", "/"),
+        NotificationUriError::InvalidRoute(_)
+    ));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let uri = notification_uri(&format!("https://127.0.0.1:{port}"), "/dcap").unwrap();
+    check_notification_uri(&uri, Duration::from_secs(1))
+        .await
+        .unwrap();
+    drop(listener);
+    let err = check_notification_uri(&uri, Duration::from_secs(1))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(NotificationUriError::Unreachable(_))
+    ));
+}
+
+#[cfg(feature = "der")]
+#[test]
+fn list_notification_items() {
+    use sep2_common::{
+        packages::der::{DERControl, DERControlList},
+        serialize,
+    };
+    let control = |href: &str| DERControl {
+        href: Some(href.to_owned()),
+        ..Default::default()
+    };
+    let list = DERControlList {
+        all: Uint32(2),
+        results: Uint32(2),
+        der_control: vec![control("/derc/0"), control("/derc/1")],
+        ..Default::default()
+    };
+    let notif = Notification {
+        resource: Some(list),
+        subscribed_resource: "/derp/0/derc".to_owned(),
+        ..Default::default()
+    };
+    let out = deserialize_list_notif::<DERControlList, _>(&serialize(&notif).unwrap()).unwrap();
+    assert_eq!(out.items, vec![control("/derc/0"), control("/derc/1")]);
+    assert_eq!(out.notification.resource.unwrap().all, Uint32(2));
+    assert_eq!(out.notification.subscribed_resource, "/derp/0/derc");
+
+    // A single item of the list
+    let notif = Notification {
+        resource: Some(control("/derc/0")),
+        ..Default::default()
+    };
+    let out = deserialize_list_notif::<DERControlList, _>(&serialize(&notif).unwrap()).unwrap();
+    assert_eq!(out.items, vec![control("/derc/0")]);
+    assert!(out.notification.resource.is_none());
+}
+
+#[tokio::test]
+async fn route_table_updates() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    let server = ClientNotifServer::new("127.0.0.1:0").unwrap();
+    let routes = server.route_table();
+    let notif = sep2_common::serialize(&Notification::<DeviceCapability>::default()).unwrap();
+    let post = || {
+        Request::post("/dcap")
+            .body(Body::from(notif.clone()))
+            .unwrap()
+    };
+    assert_eq!(
+        server.handle(post()).await.unwrap().status(),
+        StatusCode::NOT_FOUND
+    );
+    routes
+        .add("/dcap", |_: Notification<DeviceCapability>| async {
+            SEPResponse::Created(None)
+        })
+        .unwrap();
+    assert_eq!(routes.paths(), vec!["/dcap".to_owned()]);
+    assert_eq!(
+        server.handle(post()).await.unwrap().status(),
+        StatusCode::CREATED
+    );
+    assert!(routes.remove("/dcap"));
+    assert!(!routes.remove("/dcap"));
+    assert_eq!(
+        server.handle(post()).await.unwrap().status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn subscription_manager_in_sync() {
+    use crate::client::ScriptedTransport;
+    use sep2_common::packages::dcap::DeviceCapability;
+    let respond = |status, loc: Option<&str>| {
+        let mut res = Response::builder().status(status);
+        if let Some(loc) = loc {
+            res = res.header(hyper::header::LOCATION, loc);
+        }
+        res.body(Body::empty()).unwrap()
+    };
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        respond(StatusCode::CREATED, Some("/edev/0/sub/1")),
+        respond(StatusCode::BAD_REQUEST, None),
+        respond(StatusCode::NO_CONTENT, None),
+    ]);
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    let manager = SubscriptionManager::start(
+        ClientNotifServer::new("127.0.0.1:0").unwrap(),
+        client,
+        "https://device.local:1338",
+        "/edev/0/sub",
+    );
+    let callback = |_: Notification<DeviceCapability>| async { SEPResponse::NoContent };
+    let loc = manager
+        .subscribe("/dcap", "/dcap", Uint32(0), callback)
+        .await
+        .unwrap();
+    assert_eq!(loc, "/edev/0/sub/1");
+    assert!(manager.routes.contains("/dcap"));
+    // Routes may only be used by one Subscription
+    assert!(manager
+        .subscribe("/dcap", "/dcap", Uint32(0), callback)
+        .await
+        .is_err());
+    // Routes of Subscriptions that were not created are removed
+    assert!(manager
+        .subscribe("/edev", "/edev", Uint32(0), callback)
+        .await
+        .is_err());
+    assert!(!manager.routes.contains("/edev"));
+    assert_eq!(manager.subscriptions().await.len(), 1);
+
+    manager.unsubscribe("/dcap").await.unwrap();
+    assert!(!manager.routes.contains("/dcap"));
+    assert!(manager.subscriptions().await.is_empty());
+    assert!(manager.unsubscribe("/dcap").await.is_err());
+    {
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[2].method(), Method::DELETE);
+        assert_eq!(requests[2].uri().path(), "/edev/0/sub/1");
+    }
+    manager.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn subscriptions_resumed() {
+    use crate::client::ScriptedTransport;
+    use crate::persist::{FileStore, PersistedState, StateStore};
+    use sep2_common::packages::{dcap::DeviceCapability, time::Time};
+    let public_base = "https://device.local:1338";
+    let record = |route: &str, resource: &str, location: &str| SubscriptionRecord {
+        route: route.to_owned(),
+        resource: resource.to_owned(),
+        subscription_list: "/edev/0/sub".to_owned(),
+        subscribed_resource: route.to_owned(),
+        location: location.to_owned(),
+        limit: 0,
+    };
+    let path = std::env::temp_dir().join("sep2_client_subscriptions_resumed.state");
+    let store = FileStore::new(&path);
+    store
+        .save(&PersistedState {
+            polls: vec![],
+            subscriptions: vec![
+                record("/dcap", "DeviceCapability", "/edev/0/sub/1"),
+                record("/tm", "Time", "/edev/0/sub/2"),
+                record("/edev", "EndDeviceList", "/edev/0/sub/3"),
+            ],
+        })
+        .unwrap();
+
+    let subscription = Subscription {
+        encoding: HTTPEncoding::SEPXML,
+        level: String16("+S1".to_owned()),
+        limit: Uint32(0),
+        notification_uri: notification_uri(public_base, "/dcap").unwrap(),
+        subscribed_resource: "/dcap".to_owned(),
+        condition: None,
+        href: Some("/edev/0/sub/1".to_owned()),
+    };
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        // The Subscription to /dcap still exists
+        Response::new(Body::from(sep2_common::serialize(&subscription).unwrap())),
+        // The Subscription to /tm was deleted by the server, and is created again
+        Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap(),
+        Response::builder()
+            .status(StatusCode::CREATED)
+            .header(hyper::header::LOCATION, "/edev/0/sub/4")
+            .body(Body::empty())
+            .unwrap(),
+    ]);
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    client.set_state_store(store.clone()).unwrap();
+    let manager = SubscriptionManager::start(
+        ClientNotifServer::new("127.0.0.1:0").unwrap(),
+        client.clone(),
+        public_base,
+        "/edev/0/sub",
+    );
+    let callbacks = ResumeCallbacks::new()
+        .route(|_| |_: Notification<DeviceCapability>| async { SEPResponse::NoContent })
+        .route(|_| |_: Notification<Time>| async { SEPResponse::NoContent });
+    let mut resumed = manager.resume(&callbacks).await.unwrap();
+    resumed.sort();
+    assert_eq!(resumed, vec!["/dcap", "/tm"]);
+    assert!(!manager.routes.contains("/edev"));
+    let subs = manager.subscriptions().await;
+    assert_eq!(subs["/dcap"], "/edev/0/sub/1");
+    assert_eq!(subs["/tm"], "/edev/0/sub/4");
+    assert_eq!(transport.requests.lock().unwrap().len(), 3);
+
+    // Subscriptions without a callback are discarded
+    let mut saved = store.load().unwrap().unwrap().subscriptions;
+    saved.sort_by(|a, b| a.route.cmp(&b.route));
+    assert_eq!(
+        saved,
+        vec![
+            record("/dcap", "DeviceCapability", "/edev/0/sub/1"),
+            record("/tm", "Time", "/edev/0/sub/4"),
+        ]
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn duplicate_routes_handled() {
+    use sep2_common::packages::dcap::DeviceCapability;
+    let created = |_: Notification<DeviceCapability>| async { SEPResponse::Created(None) };
+    let no_content = |_: Notification<DeviceCapability>| async { SEPResponse::NoContent };
+    let notif = sep2_common::serialize(&Notification::<DeviceCapability>::default()).unwrap();
+    let post = || {
+        Request::post("/dcap")
+            .body(Body::from(notif.clone()))
+            .unwrap()
+    };
+
+    // Replaced by default
+    let server = ClientNotifServer::new("127.0.0.1:0")
+        .unwrap()
+        .add("/dcap", created)
+        .add("/dcap", no_content);
+    assert_eq!(
+        server.handle(post()).await.unwrap().status(),
+        StatusCode::NO_CONTENT
+    );
+
+    // Rejected, keeping the first route, and failing to start
+    let server = ClientNotifServer::builder("127.0.0.1:0")
+        .with_duplicate_routes(DuplicateRoutes::Reject)
+        .build()
+        .unwrap()
+        .add("/dcap", created)
+        .add("/dcap", no_content);
+    assert_eq!(
+        server.handle(post()).await.unwrap().status(),
+        StatusCode::CREATED
+    );
+    let err = server.route_table().add("/dcap", created).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<DuplicateRoute>(),
+        Some(&DuplicateRoute {
+            path: "/dcap".to_owned()
+        })
+    );
+    let err = server.run(std::future::pending::<()>()).await.unwrap_err();
+    assert!(err.downcast_ref::<DuplicateRoute>().is_some());
+}
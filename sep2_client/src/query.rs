@@ -0,0 +1,102 @@
+//! Query parameters for retrieving pages of an [`SEList`]
+//!
+//! As per IEEE 2030.5 section 5.5.2.2, a GET of a list resource may include the following query parameters:
+//! - `s`: the index of the first item to return
+//! - `a`: return only items with a time after the given time
+//! - `l`: the maximum number of items to return
+//!
+//! A [`ListQuery`] is typed by the list it applies to, and is consumed by [`Client::get_with_query`].
+//!
+//! [`Client::get_with_query`]: crate::client::Client::get_with_query
+
+use std::{fmt::Display, marker::PhantomData};
+
+use sep2_common::{packages::edev::EndDeviceList, traits::SEList};
+
+#[cfg(feature = "der")]
+use sep2_common::packages::der::DERControlList;
+
+/// The query parameters for a GET of the [`SEList`] `L`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListQuery<L: SEList> {
+    start: Option<u32>,
+    after: Option<i64>,
+    limit: Option<u8>,
+    list: PhantomData<fn() -> L>,
+}
+
+impl<L: SEList> Default for ListQuery<L> {
+    fn default() -> Self {
+        ListQuery {
+            start: None,
+            after: None,
+            limit: None,
+            list: PhantomData,
+        }
+    }
+}
+
+impl<L: SEList> ListQuery<L> {
+    /// Create a query with no parameters, returning the server's default page of the list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return items starting at the given index, where the first item is at zero
+    pub fn start(mut self, start: u32) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Return only items with a time after the given time, in seconds since the epoch
+    pub fn after(mut self, after: i64) -> Self {
+        self.after = Some(after);
+        self
+    }
+
+    /// Return at most the given number of items.
+    ///
+    /// Takes a `u8`, as the `l` query parameter is a UInt8, such that larger limits cannot be requested.
+    pub fn limit(mut self, limit: u8) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+/// The query string, without a leading `?`, e.g. `s=0&l=10`
+impl<L: SEList> Display for ListQuery<L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let params = [
+            self.start.map(|s| format!("s={s}")),
+            self.after.map(|a| format!("a={a}")),
+            self.limit.map(|l| format!("l={l}")),
+        ];
+        let params: Vec<String> = params.into_iter().flatten().collect();
+        write!(f, "{}", params.join("&"))
+    }
+}
+
+/// Query parameters for an [`EndDeviceList`]
+pub type EndDeviceListQuery = ListQuery<EndDeviceList>;
+
+/// Query parameters for a [`DERControlList`]
+#[cfg(feature = "der")]
+pub type DERControlListQuery = ListQuery<DERControlList>;
+
+#[test]
+fn query_string() {
+    assert_eq!(EndDeviceListQuery::new().to_string(), "");
+    assert_eq!(EndDeviceListQuery::new().limit(10).to_string(), "l=10");
+    assert_eq!(
+        EndDeviceListQuery::new().limit(u8::MAX).to_string(),
+        "l=255"
+    );
+    assert_eq!(
+        EndDeviceListQuery::new()
+            .limit(10)
+            .start(5)
+            .after(1700000000)
+            .to_string(),
+        "s=5&a=1700000000&l=10"
+    );
+}
@@ -0,0 +1,315 @@
+//! Response Function Set
+//!
+//! The status of a Response is represented by [`ResponseStatus`], which carries the numeric values defined in Table 27.
+//! This module provides conversions between a [`ResponseStatus`] and it's numeric value, and helpers for constructing [`Response`] resources.
+//!
+//! With the `event` feature, multiple [`Response`]s can be sent together using a `ResponseBatch`.
+//! Whether an event requires a Response with a given status, as per it's `responseRequired` field, is determined by [`RequiresResponse`].
+
+use anyhow::{bail, Result};
+use sep2_common::packages::{
+    identification::{Response, ResponseStatus},
+    primitives::HexBinary160,
+    types::MRIDType,
+};
+#[cfg(feature = "event")]
+use sep2_common::packages::{primitives::Uint32, response::ResponseList};
+
+#[cfg(feature = "event")]
+use sep2_common::traits::{SEIdentifiedObject, SERespondableResource};
+
+#[cfg(feature = "event")]
+use crate::client::{Client, ClientError, SEPResponse};
+use crate::time::SEPTime;
+
+/// Return the numeric value of the given [`ResponseStatus`], as per Table 27.
+pub fn status_code(status: ResponseStatus) -> u8 {
+    status as u8
+}
+
+/// Return the [`ResponseStatus`] with the given numeric value, as per Table 27.
+///
+/// Returns an error if the value is reserved, or otherwise does not correspond to a known status.
+pub fn status_from_code(code: u8) -> Result<ResponseStatus> {
+    use ResponseStatus::*;
+    let out = match code {
+        1 => EventReceived,
+        2 => EventStarted,
+        3 => EventCompleted,
+        4 => EventOptOut,
+        5 => EventOptIn,
+        6 => EventCancelled,
+        7 => EventSuperseded,
+        8 => EventPartialOptOut,
+        9 => EventPartialOptIn,
+        10 => EventCompletedNoUserParticipation,
+        11 => EventAcknowledge,
+        12 => EventNoDisplay,
+        13 => EventAbortedServer,
+        14 => EventAbortedProgram,
+        252 => EventNotApplicable,
+        253 => EventInvalid,
+        254 => EventExpired,
+        _ => bail!("{code} is not a known Response status"),
+    };
+    Ok(out)
+}
+
+/// Construct a [`Response`] from the given device, for the resource with the given mRID.
+pub fn new_response(
+    lfdi: HexBinary160,
+    subject: MRIDType,
+    status: ResponseStatus,
+    time: SEPTime,
+) -> Response {
+    Response {
+        created_date_time: Some(time.into()),
+        end_device_lfdi: lfdi,
+        status: Some(status),
+        subject,
+        href: None,
+    }
+}
+
+/// Implemented by event types whose `responseRequired` bitmap is interpreted as per their function set's column of Table 27.
+///
+/// The bits of `responseRequired` request Responses with different statuses for each function set,
+/// so Responses should only be sent for statuses this returns true for.
+/// An absent `responseRequired` requires no Responses.
+#[cfg(feature = "event")]
+pub trait RequiresResponse: SERespondableResource + SEIdentifiedObject {
+    /// Whether the server requires a Response with the given status for this event.
+    fn requires_response(&self, status: ResponseStatus) -> bool;
+}
+
+#[cfg(feature = "der")]
+impl RequiresResponse for sep2_common::packages::der::DERControl {
+    fn requires_response(&self, status: ResponseStatus) -> bool {
+        crate::client::der_response_required(status, self.response_required)
+    }
+}
+
+#[cfg(feature = "drlc")]
+impl RequiresResponse for sep2_common::packages::drlc::EndDeviceControl {
+    fn requires_response(&self, status: ResponseStatus) -> bool {
+        crate::client::drlc_response_required(status, self.response_required)
+    }
+}
+
+#[cfg(feature = "messaging")]
+impl RequiresResponse for sep2_common::packages::messaging::TextMessage {
+    fn requires_response(&self, status: ResponseStatus) -> bool {
+        crate::client::msg_response_required(status, self.response_required)
+    }
+}
+
+#[cfg(feature = "pricing")]
+impl RequiresResponse for sep2_common::packages::pricing::TimeTariffInterval {
+    fn requires_response(&self, status: ResponseStatus) -> bool {
+        crate::client::pricing_response_required(status, self.response_required)
+    }
+}
+
+/// A collection of [`Response`]s to be sent to the server together, such as those produced while processing the events of a single poll.
+///
+/// Responses are grouped by the ResponseList they are sent to.
+/// Each group of more than one Response is POSTed as a single [`ResponseList`], where the server supports it.
+#[cfg(feature = "event")]
+#[derive(Debug, Clone, Default)]
+pub struct ResponseBatch {
+    pending: Vec<(String, Response)>,
+}
+
+#[cfg(feature = "event")]
+impl ResponseBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a Response to the batch, to be sent to the ResponseList at the given relative path, typically an event's `replyTo`.
+    pub fn push(&mut self, reply_to: impl Into<String>, response: Response) {
+        self.pending.push((reply_to.into(), response));
+    }
+
+    /// Add a Response with the given status for the given event to the batch, to be sent to the event's `replyTo`.
+    ///
+    /// The Response is only added if it is required by the event, as per [`RequiresResponse::requires_response`], and the event has a `replyTo`.
+    /// Returns whether it was added.
+    pub fn push_event<E: RequiresResponse>(
+        &mut self,
+        lfdi: HexBinary160,
+        event: &E,
+        status: ResponseStatus,
+        time: SEPTime,
+    ) -> bool {
+        let Some(reply_to) = event.reply_to() else {
+            return false;
+        };
+        if !event.requires_response(status) {
+            return false;
+        }
+        self.push(reply_to, new_response(lfdi, *event.mrid(), status, time));
+        true
+    }
+
+    /// The number of Responses in the batch.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Send all Responses in the batch, returning the result of sending each, by subject, in the order they were added.
+    ///
+    /// If the server does not accept a ResponseList, responding with anything other than 201 Created or 204 No Content,
+    /// each Response in that group is instead POSTed individually.
+    pub async fn send(self, client: &Client) -> Vec<(MRIDType, Result<SEPResponse, ClientError>)> {
+        let mut groups: Vec<(String, Vec<usize>)> = vec![];
+        for (idx, (reply_to, _)) in self.pending.iter().enumerate() {
+            match groups.iter_mut().find(|(path, _)| path == reply_to) {
+                Some((_, idxs)) => idxs.push(idx),
+                None => groups.push((reply_to.clone(), vec![idx])),
+            }
+        }
+
+        let mut results: Vec<Option<Result<SEPResponse, ClientError>>> =
+            self.pending.iter().map(|_| None).collect();
+        for (path, idxs) in groups {
+            if idxs.len() > 1 {
+                let list = ResponseList {
+                    response: idxs.iter().map(|&i| self.pending[i].1.clone()).collect(),
+                    all: Uint32(idxs.len() as u32),
+                    results: Uint32(idxs.len() as u32),
+                    href: None,
+                };
+                match client.post(&path, &list).await {
+                    Ok(res @ (SEPResponse::Created(_) | SEPResponse::NoContent)) => {
+                        for &i in &idxs {
+                            results[i] = Some(Ok(res.clone()));
+                        }
+                        continue;
+                    }
+                    Ok(res) => log::info!(
+                        "ResponseBatch: Server did not accept a ResponseList at {path} ({res}), sending Responses individually."
+                    ),
+                    Err(err) => log::info!(
+                        "ResponseBatch: Failed to send a ResponseList to {path} ({err}), sending Responses individually."
+                    ),
+                }
+            }
+            for i in idxs {
+                results[i] = Some(client.post(&path, &self.pending[i].1).await);
+            }
+        }
+
+        self.pending
+            .into_iter()
+            .zip(results)
+            // Every Response belongs to exactly one group
+            .map(|((_, response), res)| (response.subject, res.unwrap()))
+            .collect()
+    }
+}
+
+#[test]
+fn status_code_roundtrip() {
+    for code in 0..=u8::MAX {
+        if let Ok(status) = status_from_code(code) {
+            assert_eq!(status_code(status), code);
+        }
+    }
+    assert_eq!(status_code(ResponseStatus::EventCancelled), 6);
+    assert_eq!(status_from_code(254).unwrap(), ResponseStatus::EventExpired);
+    assert!(status_from_code(0).is_err());
+    assert!(status_from_code(15).is_err());
+}
+
+#[cfg(feature = "event")]
+#[tokio::test]
+async fn response_batch_fallback() {
+    use crate::client::ScriptedTransport;
+    use hyper::{Body, Method, StatusCode};
+    use std::sync::Arc;
+    let status = |status| {
+        hyper::Response::builder()
+            .status(status)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let transport = Arc::new(ScriptedTransport::default());
+    transport.responses.lock().unwrap().extend([
+        // The ResponseList for /a is accepted
+        status(StatusCode::CREATED),
+        // The ResponseList for /b is not, so each Response is sent individually
+        status(StatusCode::METHOD_NOT_ALLOWED),
+        status(StatusCode::CREATED),
+        status(StatusCode::BAD_REQUEST),
+    ]);
+    let client = Client::with_transport("http://fake", transport.clone(), None);
+    let rsp = |subject: u128| {
+        new_response(
+            HexBinary160::default(),
+            sep2_common::packages::primitives::HexBinary128(subject),
+            ResponseStatus::EventReceived,
+            crate::time::current_time(),
+        )
+    };
+    let mut batch = ResponseBatch::new();
+    batch.push("/a", rsp(1));
+    batch.push("/b", rsp(2));
+    batch.push("/a", rsp(3));
+    batch.push("/b", rsp(4));
+    assert_eq!(batch.len(), 4);
+    let results = batch.send(&client).await;
+
+    let subjects: Vec<u128> = results.iter().map(|(s, _)| s.0).collect();
+    assert_eq!(subjects, [1, 2, 3, 4]);
+    assert!(matches!(results[0].1, Ok(SEPResponse::Created(_))));
+    assert!(matches!(results[1].1, Ok(SEPResponse::Created(_))));
+    assert!(matches!(results[2].1, Ok(SEPResponse::Created(_))));
+    assert!(matches!(results[3].1, Err(ClientError::BadRequest { .. })));
+
+    let requests = transport.requests.lock().unwrap();
+    assert_eq!(requests.len(), 4);
+    assert!(requests.iter().all(|r| r.method() == Method::POST));
+    assert_eq!(requests[0].uri().path(), "/a");
+    assert_eq!(requests[1].uri().path(), "/b");
+}
+
+#[cfg(feature = "der")]
+#[test]
+fn der_requires_response() {
+    use sep2_common::packages::{der::DERControl, identification::ResponseRequired};
+    let mut control = DERControl {
+        reply_to: Some("/rsps/1/rsp".to_owned()),
+        response_required: Some(ResponseRequired::MessageReceived),
+        ..Default::default()
+    };
+    assert!(control.requires_response(ResponseStatus::EventReceived));
+    assert!(!control.requires_response(ResponseStatus::EventStarted));
+
+    let time = crate::time::current_time();
+    let mut batch = ResponseBatch::new();
+    assert!(batch.push_event(
+        HexBinary160::default(),
+        &control,
+        ResponseStatus::EventReceived,
+        time
+    ));
+    assert!(!batch.push_event(
+        HexBinary160::default(),
+        &control,
+        ResponseStatus::EventStarted,
+        time
+    ));
+    control.response_required = None;
+    assert!(!batch.push_event(
+        HexBinary160::default(),
+        &control,
+        ResponseStatus::EventReceived,
+        time
+    ));
+    assert_eq!(batch.len(), 1);
+}
@@ -2,26 +2,51 @@
 
 use std::path::Path;
 
-use anyhow::{anyhow, Result};
-use sep2_common::packages::{primitives::HexBinary160, types::SFDIType};
+use anyhow::{anyhow, bail, Result};
+use sep2_common::packages::{
+    primitives::{HexBinary160, Int64},
+    types::SFDIType,
+};
 use sha2::{Digest, Sha256};
 use x509_parser::{parse_x509_certificate, pem::parse_x509_pem};
 
-/// Generate a LFDI hash value from a PEM or DER certificate file
-pub fn lfdi_gen(cert_path: impl AsRef<Path>) -> Result<HexBinary160> {
+use crate::time::SEPTime;
+
+// Read the DER encoding of a PEM or DER certificate file
+fn read_cert_der(cert_path: impl AsRef<Path>) -> Result<Vec<u8>> {
     let cert = std::fs::read(cert_path)?;
-    let der = match parse_x509_pem(&cert) {
-        Ok((_, pem)) => pem.contents,
+    match parse_x509_pem(&cert) {
+        Ok((_, pem)) => Ok(pem.contents),
         Err(_) => match parse_x509_certificate(&cert) {
-            Ok(_) => cert,
-            Err(_) => Err(anyhow!("Unknown certificate format, expected DER or PEM"))?,
+            Ok(_) => Ok(cert),
+            Err(_) => Err(anyhow!("Unknown certificate format, expected DER or PEM")),
         },
-    };
+    }
+}
+
+/// Generate a LFDI hash value from a PEM or DER certificate file
+pub fn lfdi_gen(cert_path: impl AsRef<Path>) -> Result<HexBinary160> {
+    Ok(lfdi_from_der(&read_cert_der(cert_path)?))
+}
+
+/// Return when the PEM or DER certificate file expires, as per it's `notAfter` field.
+pub fn cert_not_after(cert_path: impl AsRef<Path>) -> Result<SEPTime> {
+    not_after_from_der(&read_cert_der(cert_path)?)
+}
+
+/// Return when a DER encoded certificate expires, as per it's `notAfter` field.
+pub fn not_after_from_der(der: &[u8]) -> Result<SEPTime> {
+    let (_, cert) = parse_x509_certificate(der)?;
+    Ok(Int64(cert.validity().not_after.timestamp()).into())
+}
+
+/// Generate a LFDI hash value from a DER encoded certificate
+pub fn lfdi_from_der(der: &[u8]) -> HexBinary160 {
     let mut hasher = Sha256::new();
-    hasher.update(&der);
+    hasher.update(der);
     let mut out: [u8; 20] = Default::default();
     out.copy_from_slice(&hasher.finalize()[0..20]);
-    Ok(HexBinary160(out))
+    HexBinary160(out)
 }
 
 /// Generate a SFDI hash value from a LFDI hash value
@@ -41,7 +66,45 @@ pub fn security_init(cert_path: impl AsRef<Path>) -> Result<(HexBinary160, SFDIT
     Ok((lfdi, sfdi))
 }
 
-// Generate a Luhn Algorithm Check Digit
+/// Format a SFDI for display, as the zero padded 12 digits of its value in groups of three, followed by the check digit.
+///
+/// i.e. `167261211391` is displayed as `016-726-121-139-1`.
+pub fn format_sfdi(sfdi: u64) -> String {
+    let digits = format!("{:012}", sfdi / 10);
+    let mut out = String::with_capacity(17);
+    for (i, group) in digits.as_bytes().chunks(3).enumerate() {
+        if i > 0 {
+            out.push('-');
+        }
+        out.push_str(std::str::from_utf8(group).unwrap());
+    }
+    out.push('-');
+    out.push_str(&(sfdi % 10).to_string());
+    out
+}
+
+/// Parse a SFDI, such as one entered by an installer, verifying its check digit.
+///
+/// Groups of digits may be separated by `-` or whitespace, as produced by [`format_sfdi`].
+pub fn parse_sfdi(sfdi: &str) -> Result<u64> {
+    let digits: String = sfdi
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .collect();
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        bail!("SFDI must contain only decimal digits, optionally separated by '-'");
+    }
+    let value: u64 = digits.parse()?;
+    if value / 10 >= 1 << 36 {
+        bail!("SFDI {sfdi} exceeds 36 bits");
+    }
+    if check_digit(value / 10) != value % 10 {
+        bail!("SFDI {sfdi} has an incorrect check digit");
+    }
+    Ok(value)
+}
+
+// Generate the check digit of a SFDI, such that the sum of all it's digits is a multiple of 10 - 2030.5-2018 - 6.3.3
 fn check_digit(mut x: u64) -> u64 {
     let mut sum = 0;
     while x != 0 {
@@ -53,7 +116,16 @@ fn check_digit(mut x: u64) -> u64 {
 
 #[test]
 fn security_generates() {
-    let _ = security_init("../certs/client_cert.pem").unwrap();
+    let (lfdi, sfdi) = security_init("../certs/client_cert.pem").unwrap();
+    let cert_lfdi = crate::tls::lfdi_from_cert("../certs/client_cert.pem").unwrap();
+    assert_eq!(cert_lfdi, lfdi.0);
+    assert_eq!(crate::tls::sfdi_from_lfdi(cert_lfdi), sfdi.get());
+}
+
+#[test]
+fn cert_expiry_read() {
+    let not_after = cert_not_after("../certs/client_cert.pem").unwrap();
+    assert!(not_after > crate::time::current_time());
 }
 
 /// Generating SFDI as per the specification
@@ -64,5 +136,37 @@ fn example_sfdi_gen() {
         0x31, 0x98, 0x4E, 0x23, 0xE5,
     ]);
     let sfdi = sfdi_gen(&lfdi);
-    assert_eq!(sfdi.get(), 167261211391)
+    assert_eq!(sfdi.get(), 167261211391);
+    assert_eq!(crate::tls::sfdi_from_lfdi(lfdi.0), 167261211391);
+}
+
+/// The SFDI is the leftmost 36 bits of the LFDI, with a check digit appended
+#[test]
+fn sfdi_check_digit() {
+    assert_eq!(check_digit(16726121139), 1);
+    assert_eq!(check_digit(0), 0);
+    assert_eq!(check_digit(5), 5);
+    assert_eq!(check_digit(19), 0);
+    // The remaining bits of the LFDI are ignored
+    let mut lfdi = [0xFF; 20];
+    lfdi[0..4].copy_from_slice(&[0; 4]);
+    lfdi[4] = 0x0F;
+    assert_eq!(sfdi_gen(&HexBinary160(lfdi)).get(), 0);
+    assert_eq!(
+        sfdi_gen(&HexBinary160([0xFF; 20])).get(),
+        ((1 << 36) - 1) * 10 + 7
+    );
+}
+
+#[test]
+fn example_sfdi_display() {
+    assert_eq!(format_sfdi(167261211391), "016-726-121-139-1");
+    assert_eq!(parse_sfdi("016-726-121-139-1").unwrap(), 167261211391);
+    assert_eq!(parse_sfdi("167261211391").unwrap(), 167261211391);
+    assert_eq!(parse_sfdi(" 016 726 121 139 1 ").unwrap(), 167261211391);
+    // Incorrect check digit
+    assert!(parse_sfdi("016-726-121-139-2").is_err());
+    assert!(parse_sfdi("016-726-121-13a-1").is_err());
+    assert!(parse_sfdi("").is_err());
+    assert!(parse_sfdi("999999999999999").is_err());
 }
@@ -1,14 +1,26 @@
 //! Time Function Set
 
 use std::{
-    sync::atomic::AtomicI64,
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicI64, AtomicU64},
+        Mutex,
+    },
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use sep2_common::packages::{primitives::Int64, time::Time};
+use sep2_common::packages::{primitives::Int64, time::Time, types::DateTimeInterval};
 
 static TIME_OFFSET: AtomicI64 = AtomicI64::new(0);
 
+// Number of offsets retained for determining drift
+const DRIFT_SAMPLES: usize = 16;
+// Offsets calculated by `update_time_offset`, and when they were calculated, oldest first
+static OFFSET_SAMPLES: Mutex<VecDeque<(Instant, i64)>> = Mutex::new(VecDeque::new());
+// Drift, in seconds per hour, above which a warning is logged, stored as the bits of an f64
+static DRIFT_THRESHOLD: AtomicU64 = AtomicU64::new(DEFAULT_DRIFT_THRESHOLD.to_bits());
+const DEFAULT_DRIFT_THRESHOLD: f64 = 10.0;
+
 /// IEEE 2030.5 Representation of SystemTime
 #[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
 pub struct SEPTime(SystemTime);
@@ -25,6 +37,19 @@ impl From<SEPTime> for SystemTime {
     }
 }
 
+impl From<SystemTime> for SEPTime {
+    fn from(value: SystemTime) -> Self {
+        SEPTime(value)
+    }
+}
+
+/// Times beyond those representable by the platform saturate, as per [`SEPTime`]'s `Add<i64>`.
+impl From<Int64> for SEPTime {
+    fn from(value: Int64) -> Self {
+        SEPTime(UNIX_EPOCH) + value.get()
+    }
+}
+
 impl From<SEPTime> for Int64 {
     fn from(value: SEPTime) -> Self {
         Int64(i64::from(value))
@@ -33,7 +58,11 @@ impl From<SEPTime> for Int64 {
 
 impl From<SEPTime> for i64 {
     fn from(value: SEPTime) -> Self {
-        u64::from(value) as i64
+        // Times before the unix epoch are negative, as per [`Int64`] TimeType values
+        match value.0.duration_since(UNIX_EPOCH) {
+            Ok(since) => i64::try_from(since.as_secs()).unwrap_or(i64::MAX),
+            Err(before) => 0i64.saturating_sub_unsigned(before.duration().as_secs()),
+        }
     }
 }
 
@@ -47,19 +76,79 @@ impl From<SEPTime> for u64 {
     }
 }
 
+/// Adds the given number of seconds, which may be negative.
+///
+/// Saturates at the earliest or latest time representable by the platform's [`SystemTime`], in whole seconds, rather than panicking.
 impl std::ops::Add<i64> for SEPTime {
     type Output = SEPTime;
 
-    fn add(mut self, rhs: i64) -> Self::Output {
-        let sign = rhs.signum();
+    fn add(self, rhs: i64) -> Self::Output {
+        let forward = rhs.is_positive();
         // Duration cannot be negative, this branching is required
         let duration = Duration::from_secs(rhs.unsigned_abs());
-        if sign.is_positive() {
-            self.0 += duration;
+        let time = if forward {
+            self.0.checked_add(duration)
         } else {
-            self.0 -= duration;
+            self.0.checked_sub(duration)
+        };
+        SEPTime(time.unwrap_or_else(|| saturate(self.0, forward)))
+    }
+}
+
+// The latest, or earliest, time representable a whole number of seconds from the given time.
+// Found by taking the largest steps that do not overflow, halving the step each time it does.
+fn saturate(mut time: SystemTime, forward: bool) -> SystemTime {
+    let mut step = Duration::from_secs(1 << 63);
+    while step >= Duration::from_secs(1) {
+        let next = if forward {
+            time.checked_add(step)
+        } else {
+            time.checked_sub(step)
+        };
+        match next {
+            Some(next) => time = next,
+            None => step /= 2,
         }
-        self
+    }
+    time
+}
+
+/// Scheduling queries on a [`DateTimeInterval`], such as the interval of an event.
+///
+/// Intervals are half-open, including their start but not their end.
+/// As such, an interval with a duration of zero contains no instants, and overlaps no other interval.
+pub trait DateTimeIntervalExt {
+    /// The time at which the interval starts
+    fn start_time(&self) -> SEPTime;
+    /// The time at which the interval ends, being it's start plus it's duration, saturating as per [`SEPTime`]'s `Add<i64>`.
+    fn end(&self) -> SEPTime;
+    /// Whether the given time falls within the interval
+    fn contains(&self, time: SEPTime) -> bool;
+    /// Whether the interval shares any instant with the other
+    fn overlaps(&self, other: &Self) -> bool;
+    /// Whether the interval has ended, as of `now`
+    fn is_past(&self, now: SEPTime) -> bool;
+}
+
+impl DateTimeIntervalExt for DateTimeInterval {
+    fn start_time(&self) -> SEPTime {
+        self.start.into()
+    }
+
+    fn end(&self) -> SEPTime {
+        self.start_time() + i64::from(self.duration.get())
+    }
+
+    fn contains(&self, time: SEPTime) -> bool {
+        self.start_time() <= time && time < self.end()
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        self.start_time().max(other.start_time()) < self.end().min(other.end())
+    }
+
+    fn is_past(&self, now: SEPTime) -> bool {
+        self.end() <= now
     }
 }
 
@@ -68,18 +157,92 @@ pub fn current_time() -> SEPTime {
     SEPTime::now()
 }
 
+/// Return the current time, as an [`Int64`] number of seconds since the unix epoch.
+///
+/// This is the representation used by the TimeType fields of IEEE 2030.5 resources.
+pub fn current_time_int64() -> Int64 {
+    current_time().into()
+}
+
 /// Return the current time, with the global time offset supplied.
 pub fn current_time_with_offset() -> SEPTime {
-    current_time() + TIME_OFFSET.load(std::sync::atomic::Ordering::Relaxed)
+    current_time() + time_offset()
 }
 
 /// Given a Time resource, calculate it's offset from the system time,
 /// and set that offset to be applied to all future calls to [`current_time_with_offset`]
 pub fn update_time_offset(time: Time) {
-    let offset = time.current_time.get() - i64::from(current_time());
+    // Both are seconds since the unix epoch, a positive offset indicates the server is ahead
+    let offset = time.current_time.get() - current_time_int64().get();
+    set_time_offset(offset);
+    let drift = {
+        let mut samples = OFFSET_SAMPLES.lock().unwrap();
+        record_offset(&mut samples, Instant::now(), offset);
+        drift_rate(&samples)
+    };
+    if let Some(drift) = drift.filter(|drift| drift.abs() > time_drift_threshold()) {
+        log::warn!(
+            "Time: Server time is drifting from the system time by {drift:.2} seconds per hour, exceeding the threshold of {} seconds per hour",
+            time_drift_threshold()
+        );
+    }
+}
+
+/// Return the rate at which the offset calculated by [`update_time_offset`] is changing, in seconds per hour.
+///
+/// A positive drift indicates the server's clock is gaining on the system clock.
+/// A persistent drift may indicate a degrading server clock, or varying network asymmetry.
+///
+/// Calculated from the most recent offsets, returns `None` until at least two offsets have been calculated.
+pub fn time_drift() -> Option<f64> {
+    drift_rate(&OFFSET_SAMPLES.lock().unwrap())
+}
+
+/// Set the drift, in seconds per hour, above which [`update_time_offset`] logs a warning.
+///
+/// Defaults to 10 seconds per hour, if this function is not called.
+pub fn set_time_drift_threshold(seconds_per_hour: f64) {
+    DRIFT_THRESHOLD.store(
+        seconds_per_hour.to_bits(),
+        std::sync::atomic::Ordering::Relaxed,
+    );
+}
+
+/// Return the drift, in seconds per hour, above which [`update_time_offset`] logs a warning.
+pub fn time_drift_threshold() -> f64 {
+    f64::from_bits(DRIFT_THRESHOLD.load(std::sync::atomic::Ordering::Relaxed))
+}
+
+// Record an offset calculated at the given instant, discarding the oldest if there are too many
+fn record_offset(samples: &mut VecDeque<(Instant, i64)>, at: Instant, offset: i64) {
+    if samples.len() == DRIFT_SAMPLES {
+        samples.pop_front();
+    }
+    samples.push_back((at, offset));
+}
+
+// The change in offset between the oldest and newest samples, in seconds per hour
+fn drift_rate(samples: &VecDeque<(Instant, i64)>) -> Option<f64> {
+    let (first_at, first) = samples.front()?;
+    let (last_at, last) = samples.back()?;
+    let hours = last_at.duration_since(*first_at).as_secs_f64() / 3600.0;
+    (hours > 0.0).then(|| (last - first) as f64 / hours)
+}
+
+/// Set the offset, in seconds, applied to all future calls to [`current_time_with_offset`].
+///
+/// Intended for restoring a previously persisted offset, as returned by [`time_offset`], at startup,
+/// such that a device with an inaccurate clock has a reasonable time before it retrieves a Time resource from the server.
+/// The offset is replaced by the next call to [`update_time_offset`].
+pub fn set_time_offset(offset: i64) {
     TIME_OFFSET.store(offset, std::sync::atomic::Ordering::Relaxed);
 }
 
+/// Return the offset, in seconds, currently applied by [`current_time_with_offset`].
+pub fn time_offset() -> i64 {
+    TIME_OFFSET.load(std::sync::atomic::Ordering::Relaxed)
+}
+
 /// Intermittently sleep until the provided instant,
 /// waking at an interval defined by `rate`.
 ///
@@ -104,8 +267,100 @@ fn septime_add() {
 
 #[test]
 fn septime_offset() {
-    let mut some_time = Time::default();
-    some_time.current_time = (current_time() + 100).into();
+    let some_time = Time {
+        current_time: (current_time() + 100).into(),
+        ..Default::default()
+    };
     update_time_offset(some_time);
     assert!(current_time_with_offset() > current_time());
+    let offset = i64::from(current_time_with_offset()) - current_time_int64().get();
+    assert!((99..=101).contains(&offset));
+
+    // Restoring a persisted offset
+    let persisted = time_offset();
+    set_time_offset(0);
+    assert!(current_time_with_offset() <= current_time());
+    set_time_offset(persisted);
+    assert_eq!(time_offset(), persisted);
+}
+
+#[test]
+fn time_drift_rate() {
+    let start = Instant::now();
+    let hours = |h: u64| start + Duration::from_secs(h * 3600);
+    let mut samples = VecDeque::new();
+    assert_eq!(drift_rate(&samples), None);
+    record_offset(&mut samples, hours(0), 5);
+    assert_eq!(drift_rate(&samples), None);
+    record_offset(&mut samples, hours(1), 7);
+    record_offset(&mut samples, hours(2), 9);
+    assert_eq!(drift_rate(&samples), Some(2.0));
+
+    // Only the most recent samples are retained
+    for h in 3..=DRIFT_SAMPLES as u64 + 2 {
+        record_offset(&mut samples, hours(h), 9 - (h as i64 - 2) * 3);
+    }
+    assert_eq!(samples.len(), DRIFT_SAMPLES);
+    assert_eq!(drift_rate(&samples), Some(-3.0));
+}
+
+#[test]
+fn septime_int64_roundtrip() {
+    for secs in [-100, 0, 100] {
+        assert_eq!(Int64::from(SEPTime::from(Int64(secs))), Int64(secs));
+    }
+}
+
+#[test]
+fn septime_saturates() {
+    // Neither overflowing the platform's SystemTime, nor converting back, panics
+    let earliest = SEPTime::from(Int64(i64::MIN));
+    let latest = SEPTime::from(Int64(i64::MAX));
+    assert!(earliest < SEPTime::from(Int64(0)));
+    assert!(latest > current_time());
+    assert!(SEPTime::from(Int64::from(latest)) == latest);
+    assert!(latest + 1 == latest);
+    assert!(earliest + -1 == earliest);
+    assert!(i64::from(earliest) < 0);
+}
+
+#[cfg(test)]
+use sep2_common::packages::primitives::Uint32;
+
+#[test]
+fn interval_queries() {
+    let interval = |start, duration| DateTimeInterval {
+        start: Int64(start),
+        duration: Uint32(duration),
+    };
+    let at = |secs| SEPTime::from(Int64(secs));
+    let a = interval(100, 50);
+    assert!(a.end() == at(150));
+    assert!(a.contains(at(100)));
+    assert!(a.contains(at(149)));
+    assert!(!a.contains(at(150)));
+    assert!(!a.contains(at(99)));
+    assert!(!a.is_past(at(149)));
+    assert!(a.is_past(at(150)));
+
+    assert!(a.overlaps(&interval(149, 10)));
+    assert!(a.overlaps(&interval(90, 20)));
+    assert!(a.overlaps(&interval(110, 10)));
+    // Adjacent intervals do not overlap
+    assert!(!a.overlaps(&interval(150, 10)));
+    assert!(!a.overlaps(&interval(90, 10)));
+
+    let zero = interval(120, 0);
+    assert!(zero.end() == at(120));
+    assert!(!zero.contains(at(120)));
+    assert!(!zero.overlaps(&a));
+    assert!(!a.overlaps(&zero));
+    assert!(zero.is_past(at(120)));
+
+    // An interval ending beyond the latest representable time ends at that time
+    let unending = interval(i64::MAX - 10, u32::MAX);
+    assert!(unending.end() == at(i64::MAX));
+    assert!(unending.contains(at(i64::MAX - 10)));
+    assert!(!unending.is_past(current_time()));
+    assert!(interval(i64::MIN, 10).is_past(current_time()));
 }
@@ -2,38 +2,124 @@
 //!
 //! Provides an interface for parsing & verifying 2030.5 certificates, as per IEEE 2030.5 section 6.11
 //!
+//! TLS is provided by `openssl`, which is the only supported backend.
+//! IEEE 2030.5 requires the `TLS_ECDHE_ECDSA_WITH_AES_128_CCM_8` cipher suite, which `rustls` does not implement,
+//! such that a `rustls` backend could not connect to a compliant server (see [rustls#1034](https://github.com/rustls/rustls/issues/1034)).
+//! When cross-compiling, such as to musl targets, `openssl`'s `vendored` feature can be enabled to build it from source.
+//!
 
-use std::path::Path;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
 use std::time::Duration;
 
-use anyhow::{bail, Result};
-use hyper::client::{HttpConnector, ResponseFuture};
-use hyper::{Body, Client, Request};
+use anyhow::{anyhow, bail, Context as _, Result};
+use hyper::client::HttpConnector;
+use hyper::service::Service;
+use hyper::{Body, Client, Request, Uri};
 use hyper_openssl::HttpsConnector;
-use openssl::ssl::{SslConnector, SslConnectorBuilder, SslFiletype, SslMethod, SslVerifyMode};
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::ssl::{
+    SslConnector, SslConnectorBuilder, SslContextBuilder, SslFiletype, SslMethod, SslVerifyMode,
+};
+use openssl::stack::Stack;
+use openssl::x509::store::X509StoreBuilder;
+use openssl::x509::X509;
+use openssl::x509::{X509Ref, X509StoreContext, X509StoreContextRef};
 
 #[cfg(feature = "pubsub")]
-use openssl::ssl::{SslAcceptor, SslAcceptorBuilder};
-use x509_parser::prelude::ParsedExtension;
+use openssl::{
+    ex_data::Index,
+    ssl::{ClientHelloResponse, Ssl, SslAcceptor, SslAcceptorBuilder, SslVersion},
+};
+use sep2_common::packages::primitives::HexBinary160;
+use x509_parser::prelude::{GeneralName, ParsedExtension, SubjectAlternativeName};
+
+use crate::client::{Transport, TransportFuture};
+use crate::security::{lfdi_gen, sfdi_gen};
 
-pub(crate) type HTTPSConnector = HttpsConnector<HttpConnector>;
+pub(crate) type HTTPSConnector = CountingConnector<HttpsConnector<HttpConnector>>;
 pub(crate) type HTTPSClient = Client<HTTPSConnector, Body>;
-pub(crate) type HTTPClient = Client<HttpConnector, Body>;
+pub(crate) type HTTPClient = Client<CountingConnector<HttpConnector>, Body>;
 pub(crate) type TlsClientConfig = SslConnectorBuilder;
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub(crate) enum ClientInner {
     Https(HTTPSClient),
     Http(HTTPClient),
+    Custom(Arc<dyn Transport>),
 }
 
 impl ClientInner {
-    pub(crate) fn request(&self, req: Request<Body>) -> ResponseFuture {
+    pub(crate) fn request(&self, req: Request<Body>) -> TransportFuture {
         match self {
-            ClientInner::Https(c) => c.request(req),
-            ClientInner::Http(c) => c.request(req),
+            ClientInner::Https(c) => {
+                let res = c.request(req);
+                Box::pin(async move { Ok(res.await?) })
+            }
+            ClientInner::Http(c) => {
+                let res = c.request(req);
+                Box::pin(async move { Ok(res.await?) })
+            }
+            ClientInner::Custom(t) => t.request(req),
+        }
+    }
+}
+
+// Load the trusted root CA(s) at the given path.
+// The path may be either a single PEM file, or a directory of PEM files, such as an OpenSSL `CApath`.
+fn load_ca(builder: &mut SslContextBuilder, rootca_path: &Path) -> Result<()> {
+    if !rootca_path.is_dir() {
+        builder.set_ca_file(rootca_path)?;
+        return Ok(());
+    }
+    // Hashed symlinks in a `CApath` point to the same files, only load them once
+    let mut seen = HashSet::new();
+    // A single unusable entry, such as a dangling symlink, shouldn't prevent trusting the rest
+    for entry in std::fs::read_dir(rootca_path)? {
+        let path = match entry.and_then(|entry| entry.path().canonicalize()) {
+            Ok(path) => path,
+            Err(err) => {
+                log::warn!(
+                    "Skipping entry in {} that could not be resolved: {err}",
+                    rootca_path.display()
+                );
+                continue;
+            }
+        };
+        if !path.is_file() || !seen.insert(path.clone()) {
+            continue;
+        }
+        let pem = match std::fs::read(&path) {
+            Ok(pem) => pem,
+            Err(err) => {
+                log::warn!("Skipping {} as it could not be read: {err}", path.display());
+                continue;
+            }
+        };
+        match X509::stack_from_pem(&pem) {
+            Ok(certs) if !certs.is_empty() => {
+                log::debug!("Loading Certificate Authority File {}", path.display());
+                for cert in certs {
+                    if let Err(err) = builder.cert_store_mut().add_cert(cert) {
+                        log::warn!(
+                            "Skipping a certificate in {} that could not be trusted: {err}",
+                            path.display()
+                        );
+                    }
+                }
+            }
+            _ => log::debug!(
+                "Skipping {} as it does not contain any PEM certificates",
+                path.display()
+            ),
         }
     }
+    Ok(())
 }
 
 pub(crate) fn create_client_tls_cfg(
@@ -41,36 +127,403 @@ pub(crate) fn create_client_tls_cfg(
     pk_path: impl AsRef<Path>,
     rootca_path: impl AsRef<Path>,
 ) -> Result<TlsClientConfig> {
-    let mut builder = SslConnector::builder(SslMethod::tls_client())?;
-    log::debug!("Setting CipherSuite");
-    builder.set_cipher_list("ECDHE-ECDSA-AES128-CCM8")?;
+    let mut builder = create_client_tls_cfg_without_identity(rootca_path)?;
     log::debug!("Loading Certificate File");
     builder.set_certificate_file(cert_path, SslFiletype::PEM)?;
     log::debug!("Loading Private Key File");
-    builder.set_private_key_file(pk_path, SslFiletype::PEM)?;
-    log::debug!("Loading Certificate Authority File");
-    builder.set_ca_file(rootca_path)?;
+    let pkey = load_private_key(pk_path, None)?;
+    builder.set_private_key(&pkey)?;
+    Ok(builder)
+}
+
+pub(crate) fn create_client_tls_cfg_from_identity(
+    identity: &ClientIdentity,
+    rootca_path: impl AsRef<Path>,
+) -> Result<TlsClientConfig> {
+    let mut builder = create_client_tls_cfg_without_identity(rootca_path)?;
+    log::debug!("Setting Certificate");
+    builder.set_certificate(&identity.cert)?;
+    log::debug!("Setting Private Key");
+    builder.set_private_key(&identity.pkey)?;
+    Ok(builder)
+}
+
+// Create a client TLS configuration that does not present a certificate by default
+pub(crate) fn create_client_tls_cfg_without_identity(
+    rootca_path: impl AsRef<Path>,
+) -> Result<TlsClientConfig> {
+    let mut builder = SslConnector::builder(SslMethod::tls_client())?;
+    log::debug!("Setting CipherSuite");
+    builder.set_cipher_list("ECDHE-ECDSA-AES128-CCM8")?;
+    log::debug!("Loading Certificate Authority File(s)");
+    load_ca(&mut builder, rootca_path.as_ref())?;
     log::debug!("Setting verification mode");
     builder.set_verify(SslVerifyMode::PEER);
     Ok(builder)
 }
 
+// Trust the root CA(s) at each of the given paths, in addition to those already loaded
+pub(crate) fn add_trusted_roots(builder: &mut SslContextBuilder, roots: &[PathBuf]) -> Result<()> {
+    for root in roots {
+        load_ca(builder, root)
+            .with_context(|| format!("Failed to load trusted root(s) {}", root.display()))?;
+    }
+    Ok(())
+}
+
+// Load a PEM private key from the given file, decrypting it with the passphrase, if given
+fn load_private_key(pk_path: impl AsRef<Path>, passphrase: Option<&[u8]>) -> Result<PKey<Private>> {
+    let pk_path = pk_path.as_ref();
+    let pem = std::fs::read(pk_path)
+        .with_context(|| format!("Failed to read private key file {}", pk_path.display()))?;
+    private_key_from_pem(&pem, passphrase)
+        .with_context(|| format!("Failed to load private key file {}", pk_path.display()))
+}
+
+fn private_key_from_pem(pem: &[u8], passphrase: Option<&[u8]>) -> Result<PKey<Private>> {
+    // Both PKCS#8 & legacy OpenSSL encrypted PEM keys contain this marker
+    let encrypted = pem.windows(9).any(|w| w == b"ENCRYPTED");
+    match passphrase {
+        // Without a passphrase, OpenSSL would otherwise prompt for one on the terminal
+        None if encrypted => bail!(
+            "Private key is encrypted, but no passphrase was given. Use ClientIdentity::from_encrypted_pem_files"
+        ),
+        None => Ok(PKey::private_key_from_pem(pem)?),
+        Some(passphrase) => PKey::private_key_from_pem_passphrase(pem, passphrase).map_err(|e| {
+            if encrypted {
+                anyhow!("Failed to decrypt private key, the passphrase is likely incorrect: {e}")
+            } else {
+                e.into()
+            }
+        }),
+    }
+}
+
+/// A client certificate and it's private key, which may be presented to a server during the TLS handshake.
+///
+/// Once loaded, an identity can be shared between a [`Client`] and a [`ClientNotifServer`], such that the TLS configuration for both
+/// is created from the same certificate and private key.
+///
+/// [`Client`]: crate::client::Client
+/// [`ClientNotifServer`]: crate::pubsub::ClientNotifServer
+#[derive(Clone)]
+pub struct ClientIdentity {
+    cert: X509,
+    pkey: PKey<Private>,
+}
+
+impl ClientIdentity {
+    /// Load a client certificate and private key from the given PEM files
+    ///
+    /// Returns an error if the private key is encrypted, see [`ClientIdentity::from_encrypted_pem_files`].
+    pub fn from_pem_files(cert_path: impl AsRef<Path>, pk_path: impl AsRef<Path>) -> Result<Self> {
+        let cert = X509::from_pem(&std::fs::read(cert_path)?)?;
+        let pkey = load_private_key(pk_path, None)?;
+        Ok(ClientIdentity { cert, pkey })
+    }
+
+    /// Load a client certificate, and a passphrase protected private key, from the given PEM files.
+    ///
+    /// Returns an error if the private key could not be decrypted with the given passphrase.
+    ///
+    /// The resulting identity can be used with [`Client::new_https_from_identity`] and [`ClientNotifServer::with_https_identity`].
+    ///
+    /// [`Client::new_https_from_identity`]: crate::client::Client::new_https_from_identity
+    /// [`ClientNotifServer::with_https_identity`]: crate::pubsub::ClientNotifServer::with_https_identity
+    pub fn from_encrypted_pem_files(
+        cert_path: impl AsRef<Path>,
+        pk_path: impl AsRef<Path>,
+        passphrase: &[u8],
+    ) -> Result<Self> {
+        let cert = X509::from_pem(&std::fs::read(cert_path)?)?;
+        let pkey = load_private_key(pk_path, Some(passphrase))?;
+        Ok(ClientIdentity { cert, pkey })
+    }
+
+    // The DER encoding of the certificate
+    pub(crate) fn cert_der(&self) -> Result<Vec<u8>> {
+        Ok(self.cert.to_der()?)
+    }
+}
+
+// The private key is intentionally omitted
+impl std::fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientIdentity")
+            .field("subject", &self.cert.subject_name())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Chooses which of a set of [`ClientIdentity`] to present to a server, given the URI of the server being connected to.
+///
+/// Returns the index of the chosen identity, or `None` to present no certificate.
+pub type IdentitySelector = Box<dyn Fn(&Uri) -> Option<usize> + Send + Sync>;
+
+/// A callback run for each certificate of the server's chain during the TLS handshake, after OpenSSL has verified it.
+///
+/// Receives whether OpenSSL's verification of the certificate succeeded, and the store context,
+/// from which the certificate, it's position in the chain, and the chain itself can be retrieved.
+/// Returns whether the TLS handshake should continue.
+///
+/// This does not replace, nor alter, hostname verification.
+pub type VerifyCallback = Box<dyn Fn(bool, &mut X509StoreContextRef) -> bool + Send + Sync>;
+
+// Run the given callback when verifying each certificate of the server's chain
+pub(crate) fn set_verify_callback(cfg: &mut TlsClientConfig, verify: VerifyCallback) {
+    cfg.set_verify_callback(SslVerifyMode::PEER, verify);
+}
+
+/// The SHA-256 fingerprint of a DER encoded certificate
+pub type CertFingerprint = [u8; 32];
+
+/// A callback run when the certificate chain presented by a server differs from the pinned chain.
+///
+/// Receives the pinned chain, and the newly presented chain.
+pub type ChainChangeCallback = Arc<dyn Fn(&[CertFingerprint], &[CertFingerprint]) + Send + Sync>;
+
+/// Trust-on-first-use pinning of the certificate chain presented by a server.
+///
+/// The chain the server's certificate is verified against, from the server's certificate to the trusted root (SERCA),
+/// is recorded on the first successful handshake. On subsequent handshakes, if the chain differs from the recorded chain,
+/// a warning is logged, the [`ChainChangeCallback`] is run, and, if changes are rejected, the handshake fails.
+/// A change in the chain may indicate the server's certificate has been reissued, or that the connection is being intercepted.
+///
+/// Pinning is shared between clones, such that the recorded chain can be retrieved with [`ChainPinning::pinned`],
+/// and persisted by operators, to be restored with [`ChainPinning::with_pinned`].
+#[derive(Clone, Default)]
+pub struct ChainPinning {
+    pinned: Arc<RwLock<Option<Vec<CertFingerprint>>>>,
+    on_change: Option<ChainChangeCallback>,
+    reject: bool,
+}
+
+impl ChainPinning {
+    /// Create a pinning that will record the chain presented on the next handshake.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the given chain as the pinned chain, such as a chain recorded by a previous run, instead of recording one.
+    pub fn with_pinned(self, chain: Vec<CertFingerprint>) -> Self {
+        *self.pinned.write().unwrap() = Some(chain);
+        self
+    }
+
+    /// Run the given callback whenever the server presents a chain that differs from the pinned chain.
+    pub fn on_change(
+        mut self,
+        on_change: impl Fn(&[CertFingerprint], &[CertFingerprint]) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_change = Some(Arc::new(on_change));
+        self
+    }
+
+    /// Set whether handshakes presenting a chain that differs from the pinned chain should fail.
+    ///
+    /// Defaults to false, if this function is not called, in which case the change is only alerted.
+    pub fn reject_changes(mut self, reject: bool) -> Self {
+        self.reject = reject;
+        self
+    }
+
+    /// The pinned chain, if one has been recorded, from the server's certificate to the trusted root.
+    pub fn pinned(&self) -> Option<Vec<CertFingerprint>> {
+        self.pinned.read().unwrap().clone()
+    }
+
+    // Check the verified chain of the server's certificate against the pinned chain, recording it if none is pinned.
+    // Returns whether the handshake should continue.
+    pub(crate) fn check(&self, preverify_ok: bool, ctx: &mut X509StoreContextRef) -> bool {
+        // The chain is only complete once the server's certificate itself is verified
+        if !preverify_ok || ctx.error_depth() != 0 {
+            return preverify_ok;
+        }
+        let Some(chain) = ctx.chain().map(chain_fingerprints) else {
+            return true;
+        };
+        let mut pinned = self.pinned.write().unwrap();
+        match pinned.as_ref() {
+            None => {
+                log::info!("Pinning the certificate chain presented by the server");
+                *pinned = Some(chain);
+                true
+            }
+            Some(expected) if *expected == chain => true,
+            Some(expected) => {
+                log::warn!(
+                    "The certificate chain presented by the server differs from the pinned chain"
+                );
+                if let Some(on_change) = &self.on_change {
+                    on_change(expected, &chain);
+                }
+                !self.reject
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for ChainPinning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChainPinning")
+            .field("pinned", &self.pinned())
+            .field("reject", &self.reject)
+            .finish_non_exhaustive()
+    }
+}
+
+// The SHA-256 fingerprints of each certificate in the given chain
+fn chain_fingerprints(chain: &openssl::stack::StackRef<X509>) -> Vec<CertFingerprint> {
+    chain
+        .iter()
+        .filter_map(|cert| cert.digest(MessageDigest::sha256()).ok())
+        .filter_map(|digest| digest.as_ref().try_into().ok())
+        .collect()
+}
+
+/// Counters for the requests made, and connections opened by a [`Client`]
+///
+/// [`Client`]: crate::client::Client
+#[derive(Debug, Default)]
+pub(crate) struct ConnectionCounters {
+    pub(crate) requests: AtomicU64,
+    pub(crate) connections: AtomicU64,
+}
+
+/// A connector that counts the number of new connections it has been asked to establish
+#[derive(Clone, Debug)]
+pub(crate) struct CountingConnector<C> {
+    inner: C,
+    counters: Arc<ConnectionCounters>,
+}
+
+impl<C: Service<Uri>> Service<Uri> for CountingConnector<C> {
+    type Response = C::Response;
+    type Error = C::Error;
+    type Future = C::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        self.counters.connections.fetch_add(1, Ordering::Relaxed);
+        self.inner.call(uri)
+    }
+}
+
+/// Options applied to the TCP sockets of a client's connections
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct SocketOptions {
+    pub(crate) keepalive: Option<Duration>,
+    pub(crate) send_buffer_size: Option<usize>,
+    pub(crate) recv_buffer_size: Option<usize>,
+    pub(crate) local_address: Option<IpAddr>,
+}
+
+impl SocketOptions {
+    fn http_connector(&self) -> HttpConnector {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        http.set_keepalive(self.keepalive);
+        http.set_send_buffer_size(self.send_buffer_size);
+        http.set_recv_buffer_size(self.recv_buffer_size);
+        http.set_local_address(self.local_address);
+        http
+    }
+}
+
+fn create_https_connector(
+    tls_config: TlsClientConfig,
+    socket: SocketOptions,
+) -> HttpsConnector<HttpConnector> {
+    HttpsConnector::with_connector(socket.http_connector(), tls_config).unwrap()
+}
+
 pub(crate) fn create_client(
     tls_config: TlsClientConfig,
-    tcp_keepalive: Option<Duration>,
-) -> Client<HTTPSConnector, Body> {
-    let mut http = HttpConnector::new();
-    http.enforce_http(false);
-    http.set_keepalive(tcp_keepalive);
-    let https = HttpsConnector::with_connector(http, tls_config).unwrap();
-    Client::builder().build::<HTTPSConnector, hyper::Body>(https)
+    socket: SocketOptions,
+    counters: Arc<ConnectionCounters>,
+) -> HTTPSClient {
+    let https = create_https_connector(tls_config, socket);
+    Client::builder().build::<HTTPSConnector, hyper::Body>(CountingConnector {
+        inner: https,
+        counters,
+    })
 }
 
-pub(crate) fn create_http_client(tcp_keepalive: Option<Duration>) -> Client<HttpConnector, Body> {
-    let mut http = HttpConnector::new();
-    http.enforce_http(false);
-    http.set_keepalive(tcp_keepalive);
-    Client::builder().build::<HttpConnector, hyper::Body>(http)
+// Create a client that presents one of the given identities to each server it connects to, as chosen by the selector.
+// Connections are pooled per-server, so the selector is only consulted when a new connection is established.
+pub(crate) fn create_multi_identity_client(
+    tls_config: TlsClientConfig,
+    identities: Vec<ClientIdentity>,
+    selector: IdentitySelector,
+    socket: SocketOptions,
+    counters: Arc<ConnectionCounters>,
+) -> HTTPSClient {
+    let mut https = create_https_connector(tls_config, socket);
+    https.set_callback(move |conf, uri| {
+        match selector(uri).and_then(|idx| identities.get(idx)) {
+            Some(id) => {
+                log::debug!(
+                    "Presenting client certificate {:?} to {}",
+                    id.cert.subject_name(),
+                    uri
+                );
+                conf.set_certificate(&id.cert)?;
+                conf.set_private_key(&id.pkey)?;
+            }
+            None => log::warn!("No client certificate was selected for {}", uri),
+        }
+        Ok(())
+    });
+    Client::builder().build::<HTTPSConnector, hyper::Body>(CountingConnector {
+        inner: https,
+        counters,
+    })
+}
+
+// Create a client that trusts only the given self-signed peer certificate, and does not verify the server's hostname
+#[cfg(feature = "test_util")]
+pub(crate) fn create_test_client(
+    cert_path: impl AsRef<Path>,
+    pk_path: impl AsRef<Path>,
+    peer_cert_path: impl AsRef<Path>,
+    counters: Arc<ConnectionCounters>,
+) -> Result<HTTPSClient> {
+    let mut cfg = create_client_tls_cfg(cert_path, pk_path, peer_cert_path)?;
+    allow_partial_chain(&mut cfg)?;
+    let mut https = create_https_connector(cfg, SocketOptions::default());
+    https.set_callback(|conf, _| {
+        conf.set_verify_hostname(false);
+        Ok(())
+    });
+    Ok(
+        Client::builder().build::<HTTPSConnector, hyper::Body>(CountingConnector {
+            inner: https,
+            counters,
+        }),
+    )
+}
+
+// Trust certificates in the store as anchors, even if they are not self-signed CAs
+#[cfg(feature = "test_util")]
+fn allow_partial_chain(builder: &mut SslContextBuilder) -> Result<()> {
+    use openssl::x509::verify::X509VerifyFlags;
+    Ok(builder
+        .cert_store_mut()
+        .set_flags(X509VerifyFlags::PARTIAL_CHAIN)?)
+}
+
+pub(crate) fn create_http_client(
+    socket: SocketOptions,
+    counters: Arc<ConnectionCounters>,
+) -> HTTPClient {
+    let http = socket.http_connector();
+    Client::builder().build::<CountingConnector<HttpConnector>, hyper::Body>(CountingConnector {
+        inner: http,
+        counters,
+    })
 }
 
 #[cfg(feature = "pubsub")]
@@ -86,20 +539,109 @@ pub(crate) fn create_server_tls_config(
     // If they gave us a way to cosntruct Acceptors and Connectors from Contexts,
     // we wouldn't need to double up on configs here
 
-    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls_server())?;
-    log::debug!("Setting CipherSuite");
-    builder.set_cipher_list("ECDHE-ECDSA-AES128-CCM8")?;
+    let mut builder = create_server_tls_config_without_identity(rootca_path)?;
     log::debug!("Loading Certificate File");
     builder.set_certificate_file(cert_path, SslFiletype::PEM)?;
     log::debug!("Loading Private Key File");
-    builder.set_private_key_file(pk_path, SslFiletype::PEM)?;
-    log::debug!("Loading Certificate Authority File");
-    builder.set_ca_file(rootca_path)?;
+    let pkey = load_private_key(pk_path, None)?;
+    builder.set_private_key(&pkey)?;
+    Ok(builder)
+}
+
+// Create a server configuration that trusts only the given self-signed peer certificate
+#[cfg(all(feature = "pubsub", feature = "test_util"))]
+pub(crate) fn create_test_server_tls_config(
+    cert_path: impl AsRef<Path>,
+    pk_path: impl AsRef<Path>,
+    peer_cert_path: impl AsRef<Path>,
+) -> Result<TlsServerConfig> {
+    let mut builder = create_server_tls_config(cert_path, pk_path, peer_cert_path)?;
+    allow_partial_chain(&mut builder)?;
+    Ok(builder)
+}
+
+#[cfg(feature = "pubsub")]
+pub(crate) fn create_server_tls_config_from_identity(
+    identity: &ClientIdentity,
+    rootca_path: impl AsRef<Path>,
+) -> Result<TlsServerConfig> {
+    let mut builder = create_server_tls_config_without_identity(rootca_path)?;
+    log::debug!("Setting Certificate");
+    builder.set_certificate(&identity.cert)?;
+    log::debug!("Setting Private Key");
+    builder.set_private_key(&identity.pkey)?;
+    Ok(builder)
+}
+
+// The parameters offered by a peer in it's ClientHello
+#[cfg(feature = "pubsub")]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ClientHelloParams {
+    // The legacy_version field, peers offering TLS 1.3 do so in an extension, and report TLS 1.2 here
+    pub(crate) version: Option<&'static str>,
+    pub(crate) ciphers: Vec<String>,
+}
+
+// Record the parameters offered in the ClientHello of each handshake, returning the index they are stored at on the `Ssl`
+#[cfg(feature = "pubsub")]
+pub(crate) fn record_client_hello(
+    builder: &mut TlsServerConfig,
+) -> Result<Index<Ssl, ClientHelloParams>> {
+    let index = Ssl::new_ex_index()?;
+    builder.set_client_hello_callback(move |ssl, _| {
+        let version = ssl.client_hello_legacy_version().map(|v| match v {
+            SslVersion::SSL3 => "SSLv3",
+            SslVersion::TLS1 => "TLSv1",
+            SslVersion::TLS1_1 => "TLSv1.1",
+            SslVersion::TLS1_2 => "TLSv1.2",
+            SslVersion::TLS1_3 => "TLSv1.3",
+            _ => "unknown",
+        });
+        let ciphers = ssl
+            .client_hello_ciphers()
+            .map(<[u8]>::to_vec)
+            .and_then(|bytes| ssl.bytes_to_cipher_list(&bytes, false).ok())
+            .map(|list| {
+                list.suites
+                    .iter()
+                    .map(|c| c.standard_name().unwrap_or(c.name()).to_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        ssl.set_ex_data(index, ClientHelloParams { version, ciphers });
+        Ok(ClientHelloResponse::SUCCESS)
+    });
+    Ok(index)
+}
+
+#[cfg(feature = "pubsub")]
+fn create_server_tls_config_without_identity(
+    rootca_path: impl AsRef<Path>,
+) -> Result<TlsServerConfig> {
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls_server())?;
+    log::debug!("Setting CipherSuite");
+    builder.set_cipher_list("ECDHE-ECDSA-AES128-CCM8")?;
+    log::debug!("Loading Certificate Authority File(s)");
+    load_ca(&mut builder, rootca_path.as_ref())?;
     log::debug!("Setting verification mode");
     builder.set_verify(SslVerifyMode::FAIL_IF_NO_PEER_CERT | SslVerifyMode::PEER);
     Ok(builder)
 }
 
+/// Generate the LFDI of a PEM or DER client certificate file, such as the `cert_path` given to [`Client::new_https`].
+///
+/// The LFDI is the SHA-256 hash of the DER encoded certificate, truncated to it's leftmost 160 bits, as per [`lfdi_gen`].
+///
+/// [`Client::new_https`]: crate::client::Client::new_https
+pub fn lfdi_from_cert(cert_path: impl AsRef<Path>) -> Result<[u8; 20]> {
+    Ok(lfdi_gen(cert_path)?.0)
+}
+
+/// Generate the SFDI of a LFDI, as the leftmost 36 bits of the LFDI, with a sum-of-digits check digit appended, as per [`sfdi_gen`].
+pub fn sfdi_from_lfdi(lfdi: [u8; 20]) -> u64 {
+    sfdi_gen(&HexBinary160(lfdi)).get()
+}
+
 // The `rust-openssl` crate we use for openssl bindings currently does not expose an interface necessary to check these extensions: <https://github.com/sfackler/rust-openssl/issues/373>
 //
 // In the meantime, we use `x509_parser` to parse and verify that the required extensions are present, for both self-signed Client Certificates and device certificates, as per the specification.
@@ -108,13 +650,16 @@ pub(crate) fn create_server_tls_config(
 ///
 /// Newly purchased or acquired certificates in an IEEE 2030.5 certificate chain will satisfy these requirements.
 ///
-/// Currently this function isn't called when instantiating a [`Client`]` nor a [`ClientNotifServer`], but that may change in the future.
-///
+/// Currently this function isn't called when instantiating a [`Client`] nor a [`ClientNotifServer`], nor during their TLS handshakes,
+/// including those constructed by the `test_util` constructors, but that may change in the future.
 ///
 /// A valid 'device certificate' *must* be used by a [`ClientNotifServer`], i.e. NOT a self signed certificate
 /// "The use of TLS (IETF RFC 5246) requires that all hosts implementing server functionality SHALL use a
 /// device certificate whereby the server presents its device certificate as part of the TLS handshake"
 ///
+/// The SubjectAlternativeName extension must contain a single HardwareModuleName, and no other names.
+/// If it does not, the returned error contains a [`SubjectAltNameError`].
+///
 /// See section 6.11.8.3.3 for more.
 ///
 /// [`Client`]: crate::client::Client
@@ -148,8 +693,9 @@ pub fn check_device_cert(cert_path: impl AsRef<Path>) -> Result<()> {
                     bail!("CertificatePolicies extension must be critical.")
                 }
             }
-            ParsedExtension::SubjectAlternativeName(_) => {
+            ParsedExtension::SubjectAlternativeName(names) => {
                 if critical {
+                    check_subject_alt_name(names)?;
                     san = true;
                 } else {
                     bail!("SubjectAlternativeName extension must be critical")
@@ -194,6 +740,72 @@ pub fn check_device_cert(cert_path: impl AsRef<Path>) -> Result<()> {
     Ok(())
 }
 
+/// The OID of the HardwareModuleName form of otherName, as per RFC 4108
+const HARDWARE_MODULE_NAME_OID: &str = "1.3.6.1.5.5.7.8.4";
+
+/// The reason the SubjectAlternativeName extension of a device certificate was rejected by [`check_device_cert`].
+///
+/// Returned within an [`anyhow::Error`], and can be retrieved using [`anyhow::Error::downcast_ref`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubjectAltNameError {
+    /// The extension contains a name that is not a HardwareModuleName, such as a dNSName. Contains the type of the name.
+    DisallowedName(String),
+    /// The extension does not contain a HardwareModuleName.
+    MissingHardwareModuleName,
+    /// The extension contains more than one HardwareModuleName.
+    MultipleHardwareModuleNames,
+}
+
+impl std::fmt::Display for SubjectAltNameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubjectAltNameError::DisallowedName(kind) => write!(
+                f,
+                "SubjectAlternativeName extension contains a disallowed {kind}, only a HardwareModuleName is permitted"
+            ),
+            SubjectAltNameError::MissingHardwareModuleName => {
+                write!(f, "SubjectAlternativeName extension does not contain a HardwareModuleName")
+            }
+            SubjectAltNameError::MultipleHardwareModuleNames => write!(
+                f,
+                "SubjectAlternativeName extension contains more than one HardwareModuleName"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SubjectAltNameError {}
+
+// "The subjectAltName extension SHALL contain a single HardwareModuleName as specified in IETF RFC 4108"
+fn check_subject_alt_name(
+    san: &SubjectAlternativeName,
+) -> std::result::Result<(), SubjectAltNameError> {
+    let mut hw_names = 0;
+    for name in &san.general_names {
+        let kind = match name {
+            GeneralName::OtherName(oid, _) if oid.to_id_string() == HARDWARE_MODULE_NAME_OID => {
+                hw_names += 1;
+                continue;
+            }
+            GeneralName::OtherName(oid, _) => format!("otherName ({oid})"),
+            GeneralName::RFC822Name(_) => "rfc822Name".to_owned(),
+            GeneralName::DNSName(_) => "dNSName".to_owned(),
+            GeneralName::X400Address(_) => "x400Address".to_owned(),
+            GeneralName::DirectoryName(_) => "directoryName".to_owned(),
+            GeneralName::EDIPartyName(_) => "ediPartyName".to_owned(),
+            GeneralName::URI(_) => "uniformResourceIdentifier".to_owned(),
+            GeneralName::IPAddress(_) => "iPAddress".to_owned(),
+            GeneralName::RegisteredID(_) => "registeredID".to_owned(),
+        };
+        return Err(SubjectAltNameError::DisallowedName(kind));
+    }
+    match hw_names {
+        0 => Err(SubjectAltNameError::MissingHardwareModuleName),
+        1 => Ok(()),
+        _ => Err(SubjectAltNameError::MultipleHardwareModuleNames),
+    }
+}
+
 /// Verify that the PEM encoded certificate at the given path meets IEEE 2030.5 "Self Signed Client Certificate" requirements.
 ///
 /// See Section 6.11.8.4.3 for more
@@ -308,3 +920,147 @@ pub fn check_ca(cert_path: impl AsRef<Path>) -> Result<()> {
 }
 
 // TODO: Should we do checks on the supplied root ca?
+
+/// Verify that the PEM encoded certificate at `leaf_path` chains to the trusted root at `root_path`,
+/// via each of the PEM encoded certificates at `intermediate_paths`, in order.
+///
+/// Each link in the chain is checked for a matching AuthorityKeyIdentifier & SubjectKeyIdentifier, and then the
+/// chain as a whole is verified by OpenSSL, including each signature.
+///
+/// Returns an error describing the link that failed, if any.
+///
+/// This does not check the extensions of each certificate, see [`check_device_cert`] & [`check_ca`].
+pub fn verify_chain(
+    leaf_path: impl AsRef<Path>,
+    intermediate_paths: &[impl AsRef<Path>],
+    root_path: impl AsRef<Path>,
+) -> Result<()> {
+    let leaf = X509::from_pem(&std::fs::read(leaf_path)?)?;
+    let root = X509::from_pem(&std::fs::read(root_path)?)?;
+    let mut intermediates = Stack::new()?;
+    let mut chain = vec![leaf.clone()];
+    for path in intermediate_paths {
+        let cert = X509::from_pem(&std::fs::read(path)?)?;
+        chain.push(cert.clone());
+        intermediates.push(cert)?;
+    }
+    chain.push(root.clone());
+
+    for (depth, link) in chain.windows(2).enumerate() {
+        let (child, parent) = (&link[0], &link[1]);
+        check_key_identifiers(child, parent).map_err(|e| {
+            anyhow::anyhow!(
+                "Link {depth} ({:?} -> {:?}) is invalid: {e}",
+                child.subject_name(),
+                parent.subject_name()
+            )
+        })?;
+    }
+
+    let mut store = X509StoreBuilder::new()?;
+    store.add_cert(root)?;
+    let store = store.build();
+    let mut ctx = X509StoreContext::new()?;
+    let (verified, err) = ctx.init(&store, &leaf, &intermediates, |c| {
+        let verified = c.verify_cert()?;
+        let err = (!verified).then(|| {
+            let subject = c
+                .current_cert()
+                .map(|cert| format!("{:?}", cert.subject_name()))
+                .unwrap_or_default();
+            format!(
+                "Certificate {subject} at depth {} failed verification: {}",
+                c.error_depth(),
+                c.error()
+            )
+        });
+        Ok((verified, err))
+    })?;
+    if !verified {
+        bail!(err.unwrap_or_default())
+    }
+    Ok(())
+}
+
+// Check that the AuthorityKeyIdentifier of the child matches the SubjectKeyIdentifier of the parent, if both are present
+fn check_key_identifiers(child: &X509Ref, parent: &X509Ref) -> Result<()> {
+    match (child.authority_key_id(), parent.subject_key_id()) {
+        (Some(aki), Some(ski)) if aki.as_slice() != ski.as_slice() => {
+            bail!("AuthorityKeyIdentifier does not match the issuer's SubjectKeyIdentifier")
+        }
+        _ => Ok(()),
+    }
+}
+
+#[test]
+fn encrypted_private_key() {
+    use openssl::{ec::EcKey, nid::Nid, symm::Cipher};
+    let group = openssl::ec::EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let pkey = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+    let plain = pkey.private_key_to_pem_pkcs8().unwrap();
+    let encrypted = pkey
+        .private_key_to_pem_pkcs8_passphrase(Cipher::aes_256_cbc(), b"hunter2")
+        .unwrap();
+
+    assert!(private_key_from_pem(&plain, None).is_ok());
+    assert!(private_key_from_pem(&plain, Some(b"unused")).is_ok());
+    let loaded = private_key_from_pem(&encrypted, Some(b"hunter2")).unwrap();
+    assert!(loaded.public_eq(&pkey));
+    let err = private_key_from_pem(&encrypted, Some(b"wrong")).unwrap_err();
+    assert!(err.to_string().contains("passphrase is likely incorrect"));
+    let err = private_key_from_pem(&encrypted, None).unwrap_err();
+    assert!(err.to_string().contains("no passphrase was given"));
+}
+
+#[test]
+fn subject_alt_name_contents() {
+    use x509_parser::der_parser::oid::Oid;
+    let hw_oid = Oid::from(&[1, 3, 6, 1, 5, 5, 7, 8, 4]).unwrap();
+    let other_oid = Oid::from(&[1, 2, 3]).unwrap();
+    let check = |general_names| check_subject_alt_name(&SubjectAlternativeName { general_names });
+
+    assert_eq!(
+        check(vec![GeneralName::OtherName(hw_oid.clone(), &[])]),
+        Ok(())
+    );
+    assert_eq!(
+        check(vec![]),
+        Err(SubjectAltNameError::MissingHardwareModuleName)
+    );
+    assert_eq!(
+        check(vec![
+            GeneralName::OtherName(hw_oid.clone(), &[]),
+            GeneralName::OtherName(hw_oid.clone(), &[])
+        ]),
+        Err(SubjectAltNameError::MultipleHardwareModuleNames)
+    );
+    assert_eq!(
+        check(vec![
+            GeneralName::OtherName(hw_oid, &[]),
+            GeneralName::DNSName("example.com")
+        ]),
+        Err(SubjectAltNameError::DisallowedName("dNSName".to_owned()))
+    );
+    assert_eq!(
+        check(vec![GeneralName::OtherName(other_oid, &[])]),
+        Err(SubjectAltNameError::DisallowedName(
+            "otherName (1.2.3)".to_owned()
+        ))
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn unusable_ca_entries_skipped() {
+    let dir = std::env::temp_dir().join("sep2_client_unusable_ca_entries");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy("../certs/rootCA.pem", dir.join("rootCA.pem")).unwrap();
+    std::os::unix::fs::symlink(dir.join("missing.pem"), dir.join("dangling.pem")).unwrap();
+    std::fs::write(dir.join("notes.txt"), "not a certificate").unwrap();
+
+    let mut builder = openssl::ssl::SslContext::builder(SslMethod::tls()).unwrap();
+    load_ca(&mut builder, &dir).unwrap();
+    assert_eq!(builder.build().cert_store().all_certificates().len(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,9 +1,19 @@
-use std::{future, sync::Arc, time::Duration};
+use std::{
+    future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use sep2_client::client::{Client, SEPResponse};
+use sep2_client::{
+    client::{Client, ClientError, ConnectivityError, RequestCtx, SEPResponse},
+    query::EndDeviceListQuery,
+    security::security_init,
+    tls::{verify_chain, ChainPinning, ClientIdentity},
+};
 use sep2_common::packages::{dcap::DeviceCapability, edev::EndDevice, primitives::Uint32};
 use sep2_test_server::TestServer;
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 
 fn test_setup() -> Client {
     Client::new_https(
@@ -52,6 +62,89 @@ async fn basic_req() {
     client.delete("/edev/3").await.unwrap();
 }
 
+#[tokio::test]
+async fn connection_stats() {
+    let client = test_setup();
+    client.get::<DeviceCapability>("/dcap").await.unwrap();
+    client.get::<DeviceCapability>("/dcap").await.unwrap();
+    let stats = client.connection_stats();
+    assert_eq!(stats.requests, 2);
+    assert!(stats.new_connections >= 1);
+}
+
+#[tokio::test]
+async fn query_req() {
+    let client = test_setup();
+    client
+        .get_with_query("/edev", &EndDeviceListQuery::new().start(0).limit(1))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn conditional_req() {
+    let client = test_setup();
+    // Test server does not provide ETags, so the resource is always retrieved
+    for _ in 0..2 {
+        assert!(client
+            .get_if_changed::<DeviceCapability>("/dcap")
+            .await
+            .unwrap()
+            .is_some());
+    }
+    client.clear_etags();
+}
+
+#[tokio::test]
+async fn raw_req() {
+    let client = test_setup();
+    let (dcap, xml) = client.get_raw::<DeviceCapability>("/dcap").await.unwrap();
+    assert!(xml.contains("<DeviceCapability"));
+    assert_eq!(dcap, sep2_common::deserialize(&xml).unwrap());
+}
+
+#[tokio::test]
+async fn streaming_req() {
+    let client = test_setup();
+    let out = client
+        .post_streaming("/edev", EndDevice::default())
+        .await
+        .unwrap();
+    assert!(matches!(out, SEPResponse::Created(_)));
+}
+
+#[tokio::test]
+async fn client_fdi() {
+    let client = test_setup();
+    let (lfdi, sfdi) = security_init("../certs/client_cert.pem").unwrap();
+    assert_eq!(client.lfdi(), Some(lfdi));
+    assert_eq!(client.sfdi(), Some(sfdi));
+    let identity = ClientIdentity::from_pem_files(
+        "../certs/client_cert.pem",
+        "../certs/client_private_key.pem",
+    )
+    .unwrap();
+    let client = Client::new_https_from_identity(
+        "https://127.0.0.1:1337",
+        &identity,
+        "../certs/rootCA.pem",
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(client.lfdi(), Some(lfdi));
+    let client = Client::new("http://127.0.0.1:1337", None, None).unwrap();
+    assert_eq!(client.lfdi(), None);
+}
+
+#[tokio::test]
+async fn debug_fmt() {
+    let client = test_setup();
+    let out = format!("{client:?}");
+    assert!(out.contains("https://127.0.0.1:1337"));
+    assert!(out.contains("HTTPS"));
+}
+
 #[tokio::test]
 async fn basic_poll() {
     let client = test_setup();
@@ -71,3 +164,244 @@ async fn basic_poll() {
     assert!(output.read().await.len() == 2);
     assert!(all_eq(output.read().await.as_ref()));
 }
+
+#[tokio::test]
+async fn shutdown() {
+    let client = test_setup();
+    client
+        .start_poll("/dcap", Some(Uint32(1)), |_: DeviceCapability| async {})
+        .await;
+    tokio::time::timeout(Duration::from_secs(5), client.shutdown())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn await_registration() {
+    let client = test_setup();
+    let edev = client
+        .await_registration("/edev/3", Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert!(edev.function_set_assignments_list_link.is_some());
+    // Never registered
+    assert!(client
+        .await_registration("/edev/9", Duration::from_secs(1))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn cancelled_req() {
+    let client = test_setup();
+    let token = CancellationToken::new();
+    token.cancel();
+    let err = client
+        .get_cancellable::<DeviceCapability>("/dcap", &token)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::Cancelled));
+}
+
+#[tokio::test]
+async fn ctx_req() {
+    let client = test_setup();
+    let ctx = RequestCtx {
+        deadline: Some(Instant::now() + Duration::from_secs(5)),
+        ..Default::default()
+    };
+    client
+        .get_with_ctx::<DeviceCapability>("/dcap", &ctx)
+        .await
+        .unwrap();
+    // Nothing listening, retries exceed deadline
+    let client = Client::new("http://127.0.0.1:1336", None, None).unwrap();
+    let ctx = RequestCtx {
+        deadline: Some(Instant::now() + Duration::from_millis(200)),
+        retries: 5,
+        ..Default::default()
+    };
+    let err = client
+        .get_with_ctx::<DeviceCapability>("/dcap", &ctx)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::DeadlineExceeded));
+}
+
+#[tokio::test]
+async fn rootca_dir() {
+    let client = Client::new_https(
+        "https://127.0.0.1:1337",
+        "../certs/client_cert.pem",
+        "../certs/client_private_key.pem",
+        "../certs",
+        None,
+        None,
+    )
+    .unwrap();
+    client.get::<DeviceCapability>("/dcap").await.unwrap();
+}
+
+#[tokio::test]
+async fn connectivity() {
+    let client = test_setup();
+    client.check_connectivity().await.unwrap();
+    // Nothing listening
+    let client = Client::new_https(
+        "https://127.0.0.1:1336",
+        "../certs/client_cert.pem",
+        "../certs/client_private_key.pem",
+        "../certs/rootCA.pem",
+        None,
+        None,
+    )
+    .unwrap();
+    let err = client.check_connectivity().await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ConnectivityError>(),
+        Some(ConnectivityError::Tcp(_))
+    ));
+}
+
+#[tokio::test]
+async fn multiple_identities() {
+    let identities = vec![ClientIdentity::from_pem_files(
+        "../certs/client_cert.pem",
+        "../certs/client_private_key.pem",
+    )
+    .unwrap()];
+    let client = Client::new_https_with_identities(
+        "https://127.0.0.1:1337",
+        identities,
+        Box::new(|uri| (uri.port_u16() == Some(1337)).then_some(0)),
+        "../certs/rootCA.pem",
+        None,
+        None,
+    )
+    .unwrap();
+    client.get::<DeviceCapability>("/dcap").await.unwrap();
+}
+
+#[test]
+fn cert_chain() {
+    let none: &[&str] = &[];
+    verify_chain("../certs/client_cert.pem", none, "../certs/rootCA.pem").unwrap();
+    // Not issued by the client certificate
+    assert!(verify_chain("../certs/server_cert.pem", none, "../certs/client_cert.pem").is_err());
+}
+
+#[tokio::test]
+async fn builder_req() {
+    let client = Client::builder("https://127.0.0.1:1337")
+        .with_https(
+            "../certs/client_cert.pem",
+            "../certs/client_private_key.pem",
+            "../certs/rootCA.pem",
+        )
+        .with_send_buffer_size(256 * 1024)
+        .with_recv_buffer_size(256 * 1024)
+        .with_tcp_keepalive(Duration::from_secs(60))
+        .with_local_address(std::net::Ipv4Addr::LOCALHOST.into())
+        .build()
+        .unwrap();
+    let (lfdi, _) = security_init("../certs/client_cert.pem").unwrap();
+    assert_eq!(client.lfdi(), Some(lfdi));
+    client.get::<DeviceCapability>("/dcap").await.unwrap();
+    // Certificates are loaded on build
+    assert!(Client::builder("https://127.0.0.1:1337")
+        .with_https("missing.pem", "missing.pem", "../certs/rootCA.pem")
+        .build()
+        .is_err());
+}
+
+#[tokio::test]
+async fn verify_callback() {
+    let builder = || {
+        Client::builder("https://127.0.0.1:1337").with_https(
+            "../certs/client_cert.pem",
+            "../certs/client_private_key.pem",
+            "../certs/rootCA.pem",
+        )
+    };
+    let seen = Arc::new(std::sync::Mutex::new(vec![]));
+    let client = builder()
+        .with_verify_callback({
+            let seen = seen.clone();
+            move |ok, ctx| {
+                seen.lock().unwrap().push(ctx.error_depth());
+                ok
+            }
+        })
+        .build()
+        .unwrap();
+    client.get::<DeviceCapability>("/dcap").await.unwrap();
+    // Called for each certificate in the chain, including the server certificate at depth 0
+    assert!(seen.lock().unwrap().contains(&0));
+
+    let client = builder()
+        .with_verify_callback(|_, _| false)
+        .build()
+        .unwrap();
+    assert!(client.get::<DeviceCapability>("/dcap").await.is_err());
+}
+
+#[tokio::test]
+async fn chain_pinning() {
+    let builder = |pinning: &ChainPinning| {
+        Client::builder("https://127.0.0.1:1337")
+            .with_https(
+                "../certs/client_cert.pem",
+                "../certs/client_private_key.pem",
+                "../certs/rootCA.pem",
+            )
+            .with_trusted_root("../certs/rootCA.pem")
+            .with_chain_pinning(pinning.clone())
+            .build()
+            .unwrap()
+    };
+    // Recorded on first connect, and unchanged on subsequent connects
+    let pinning = ChainPinning::new().reject_changes(true);
+    builder(&pinning)
+        .get::<DeviceCapability>("/dcap")
+        .await
+        .unwrap();
+    let pinned = pinning.pinned().unwrap();
+    assert!(!pinned.is_empty());
+    builder(&pinning)
+        .get::<DeviceCapability>("/dcap")
+        .await
+        .unwrap();
+    assert_eq!(pinning.pinned().unwrap(), pinned);
+
+    // A changed chain is alerted, and rejected if configured
+    let changes = Arc::new(std::sync::Mutex::new(0));
+    let pinning = ChainPinning::new().with_pinned(vec![[0; 32]]).on_change({
+        let changes = changes.clone();
+        move |old, new| {
+            assert_eq!(old, [[0; 32]]);
+            assert_eq!(new, pinned);
+            *changes.lock().unwrap() += 1;
+        }
+    });
+    builder(&pinning)
+        .get::<DeviceCapability>("/dcap")
+        .await
+        .unwrap();
+    let pinning = pinning.reject_changes(true);
+    assert!(builder(&pinning)
+        .get::<DeviceCapability>("/dcap")
+        .await
+        .is_err());
+    assert_eq!(*changes.lock().unwrap(), 2);
+
+    // Additional roots must exist
+    assert!(Client::builder("https://127.0.0.1:1337")
+        .with_https(
+            "../certs/client_cert.pem",
+            "../certs/client_private_key.pem",
+            "../certs/rootCA.pem",
+        )
+        .with_trusted_root("missing.pem")
+        .build()
+        .is_err());
+}
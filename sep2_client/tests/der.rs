@@ -10,7 +10,7 @@ use sep2_client::{
 };
 use sep2_common::{
     packages::{
-        der::{DERControl, DERProgram},
+        der::{DERControl, DERProgram, DefaultDERControl},
         identification::ResponseStatus,
         objects::EventStatusType,
         primitives::{HexBinary128, Int64, Uint32},
@@ -21,6 +21,13 @@ use sep2_common::{
 use tokio::sync::RwLock;
 
 fn test_setup() -> (Schedule<DERControl>, DERControlHandler) {
+    let handler = DERControlHandler {
+        logs: Arc::new(RwLock::new(vec![])),
+    };
+    (test_schedule(handler.clone()), handler)
+}
+
+fn test_schedule(handler: impl EventCallback<DERControl>) -> Schedule<DERControl> {
     let client = Client::new_https(
         "https://127.0.0.1:1337",
         "../certs/client_cert.pem",
@@ -32,17 +39,11 @@ fn test_setup() -> (Schedule<DERControl>, DERControlHandler) {
     .unwrap();
     let device =
         SEDevice::new_from_cert("../certs/client_cert.pem", DeviceCategoryType::all()).unwrap();
-    let handler = DERControlHandler {
-        logs: Arc::new(RwLock::new(vec![])),
-    };
-    (
-        Schedule::new(
-            client,
-            Arc::new(RwLock::new(device)),
-            handler.clone(),
-            Duration::from_secs(0),
-        ),
+    Schedule::new(
+        client,
+        Arc::new(RwLock::new(device)),
         handler,
+        Duration::from_secs(0),
     )
 }
 
@@ -81,9 +82,11 @@ impl EventCallback<DERControl> for DERControlHandler {
 
 // Create an event, as would be acquired from the server
 fn create_event(status: EventStatusType, count: i64, start: i64, duration: u32) -> DERControl {
-    let mut out = DERControl::default();
-    out.mrid = HexBinary128(count.try_into().unwrap());
-    out.creation_time = Int64(count);
+    let mut out = DERControl {
+        mrid: HexBinary128(count.try_into().unwrap()),
+        creation_time: Int64(count),
+        ..Default::default()
+    };
     out.event_status.current_status = status;
     out.interval.start = Int64(start);
     out.interval.duration = Uint32(duration);
@@ -136,6 +139,41 @@ async fn basic_der_scheduler() {
     );
 }
 
+/// Test the scheduler with a debounce window, where the revert of the first event is coalesced with the start of the second
+#[tokio::test]
+async fn debounce_der_scheduler() {
+    let program = DERProgram::default();
+    // T0
+    let (mut schedule, logs) = test_setup();
+    schedule.set_debounce(Duration::from_secs(3));
+    // T1 -> T3
+    let first = create_event(
+        EventStatusType::Scheduled,
+        1,
+        i64::from(current_time()) + 1,
+        2,
+    );
+    // T4 -> T6
+    let second = create_event(
+        EventStatusType::Scheduled,
+        2,
+        i64::from(current_time()) + 4,
+        2,
+    );
+    schedule.add_event(first, &program, 0).await;
+    schedule.add_event(second, &program, 0).await;
+    // Wait until all events end, and the final revert is no longer deferred
+    tokio::time::sleep(Duration::from_secs(11)).await;
+    assert_eq!(
+        logs.logs.read().await.as_ref(),
+        vec![
+            "DERControl Started: 1",
+            "DERControl Started: 2",
+            "DERControl Complete: 2"
+        ]
+    );
+}
+
 /// Test the scheduler with overlapping events that get superseded
 #[tokio::test]
 async fn superseded_der_scheduler() {
@@ -191,6 +229,46 @@ async fn superseded_der_scheduler() {
     );
 }
 
+/// Test that a snapshot reflects the state of every event in the schedule
+#[tokio::test]
+async fn snapshot_der_scheduler() {
+    let program = DERProgram {
+        mrid: HexBinary128(10),
+        ..Default::default()
+    };
+    let (mut schedule, _) = test_setup();
+    // T1 -> T3
+    let first = create_event(
+        EventStatusType::Scheduled,
+        1,
+        i64::from(current_time()) + 1,
+        2,
+    );
+    // T10 -> T12
+    let second = create_event(
+        EventStatusType::Scheduled,
+        2,
+        i64::from(current_time()) + 10,
+        2,
+    );
+    let second_start = second.interval.start.get();
+    schedule.add_event(second, &program, 0).await;
+    schedule.add_event(first, &program, 0).await;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    // T2
+    let snapshot = schedule.snapshot().await;
+    assert_eq!(snapshot.len(), 2);
+    assert_eq!(snapshot[0].mrid, HexBinary128(1));
+    assert_eq!(snapshot[0].status, EIStatus::Active);
+    assert_eq!(snapshot[1].mrid, HexBinary128(2));
+    assert_eq!(snapshot[1].status, EIStatus::Scheduled);
+    assert_eq!(snapshot[1].start, second_start);
+    assert_eq!(snapshot[1].end, second_start + 2);
+    assert!(snapshot
+        .iter()
+        .all(|ev| ev.program_mrid == HexBinary128(10)));
+}
+
 /// Test the scheduler with events that get cancelled while in progress
 #[tokio::test]
 async fn cancelling_der_scheduler() {
@@ -337,3 +415,104 @@ async fn schedule_der_differing_primacy() {
         vec!["DERControl Started: 2", "DERControl Complete: 2",]
     );
 }
+
+/// Test the scheduler with an event the device cannot comply with, which is never completed
+#[tokio::test]
+async fn declined_der_scheduler() {
+    let program = DERProgram::default();
+    let logs: Arc<RwLock<Vec<String>>> = Arc::new(RwLock::new(vec![]));
+    let handler = {
+        let logs = logs.clone();
+        move |ei: &EventInstance<DERControl>| {
+            let logs = logs.clone();
+            let (mrid, status) = (ei.event().mrid().0, ei.status());
+            async move {
+                logs.write().await.push(format!("{mrid}: {status:?}"));
+                match (mrid, status) {
+                    // The device is offline
+                    (1, EIStatus::Active) => ResponseStatus::EventNotApplicable,
+                    _ => status.into(),
+                }
+            }
+        }
+    };
+    let mut schedule = test_schedule(handler);
+    // T1 -> T3
+    let first = create_event(
+        EventStatusType::Scheduled,
+        1,
+        i64::from(current_time()) + 1,
+        2,
+    );
+    // T4 -> T6
+    let second = create_event(
+        EventStatusType::Scheduled,
+        2,
+        i64::from(current_time()) + 4,
+        2,
+    );
+    schedule.add_event(first, &program, 0).await;
+    schedule.add_event(second, &program, 0).await;
+    tokio::time::sleep(Duration::from_secs(7)).await;
+    assert_eq!(
+        logs.read().await.as_ref(),
+        vec!["1: Active", "2: Active", "2: Complete"]
+    );
+}
+
+/// Test the scheduler applying the DefaultDERControl whenever no event is active
+#[tokio::test]
+async fn default_der_scheduler() {
+    let program = DERProgram::default();
+    // T0
+    let (mut schedule, logs) = test_setup();
+    schedule.set_default_handler({
+        let logs = logs.logs.clone();
+        move |default: &DefaultDERControl| {
+            let logs = logs.clone();
+            let mrid = default.mrid.0;
+            async move {
+                logs.write().await.push(format!("Default Applied: {mrid}"));
+            }
+        }
+    });
+    let first_default = DefaultDERControl {
+        mrid: HexBinary128(10),
+        ..Default::default()
+    };
+    let second_default = DefaultDERControl {
+        mrid: HexBinary128(20),
+        ..Default::default()
+    };
+    schedule.update_default(first_default).await;
+    // T1 -> T3
+    let first = create_event(
+        EventStatusType::Scheduled,
+        1,
+        i64::from(current_time()) + 1,
+        2,
+    );
+    schedule.add_event(first, &program, 0).await;
+    // The default changes while an event is active
+    while logs.logs.read().await.len() < 2 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    schedule.update_default(second_default.clone()).await;
+    // Wait until the event ends
+    tokio::time::sleep(Duration::from_secs(4)).await;
+    // An unchanged default is not re-applied
+    schedule.update_default(second_default).await;
+    assert_eq!(
+        logs.logs.read().await.as_ref(),
+        vec![
+            "Default Applied: 10",
+            "DERControl Started: 1",
+            "DERControl Complete: 1",
+            "Default Applied: 20"
+        ]
+    );
+    assert_eq!(
+        schedule.default_control().await.unwrap().mrid,
+        HexBinary128(20)
+    );
+}
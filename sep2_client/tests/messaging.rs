@@ -21,6 +21,13 @@ use sep2_common::{
 use tokio::sync::RwLock;
 
 fn test_setup() -> (Schedule<TextMessage>, TextMessageHandler) {
+    let handler = TextMessageHandler {
+        logs: Arc::new(RwLock::new(vec![])),
+    };
+    (test_schedule(handler.clone()), handler)
+}
+
+fn test_schedule(handler: impl EventCallback<TextMessage>) -> Schedule<TextMessage> {
     let client = Client::new_https(
         "https://127.0.0.1:1337",
         "../certs/client_cert.pem",
@@ -32,17 +39,11 @@ fn test_setup() -> (Schedule<TextMessage>, TextMessageHandler) {
     .unwrap();
     let device =
         SEDevice::new_from_cert("../certs/client_cert.pem", DeviceCategoryType::all()).unwrap();
-    let handler = TextMessageHandler {
-        logs: Arc::new(RwLock::new(vec![])),
-    };
-    (
-        Schedule::new(
-            client,
-            Arc::new(RwLock::new(device)),
-            handler.clone(),
-            Duration::from_secs(0),
-        ),
+    Schedule::new(
+        client,
+        Arc::new(RwLock::new(device)),
         handler,
+        Duration::from_secs(0),
     )
 }
 
@@ -81,9 +82,11 @@ impl EventCallback<TextMessage> for TextMessageHandler {
 
 // Create an event, as would be acquired from the server
 fn create_event(status: EventStatusType, count: i64, start: i64, duration: u32) -> TextMessage {
-    let mut out = TextMessage::default();
-    out.mrid = HexBinary128(count.try_into().unwrap());
-    out.creation_time = Int64(count);
+    let mut out = TextMessage {
+        mrid: HexBinary128(count.try_into().unwrap()),
+        creation_time: Int64(count),
+        ..Default::default()
+    };
     out.event_status.current_status = status;
     out.interval.start = Int64(start);
     out.interval.duration = Uint32(duration);
@@ -295,3 +298,47 @@ async fn schedule_msg_differing_primacy() {
         ]
     );
 }
+
+/// Test the scheduler with a message the device will not display, which is never completed
+#[tokio::test]
+async fn declined_msg_scheduler() {
+    let program = MessagingProgram::default();
+    let logs: Arc<RwLock<Vec<String>>> = Arc::new(RwLock::new(vec![]));
+    let handler = {
+        let logs = logs.clone();
+        move |ei: &EventInstance<TextMessage>| {
+            let logs = logs.clone();
+            let (mrid, status) = (ei.event().mrid().0, ei.status());
+            async move {
+                logs.write().await.push(format!("{mrid}: {status:?}"));
+                match (mrid, status) {
+                    // The device has no display available
+                    (1, EIStatus::Active) => ResponseStatus::EventNoDisplay,
+                    _ => status.into(),
+                }
+            }
+        }
+    };
+    let mut schedule = test_schedule(handler);
+    // T1 -> T3
+    let first = create_event(
+        EventStatusType::Scheduled,
+        1,
+        i64::from(current_time()) + 1,
+        2,
+    );
+    // T4 -> T6
+    let second = create_event(
+        EventStatusType::Scheduled,
+        2,
+        i64::from(current_time()) + 4,
+        2,
+    );
+    schedule.add_event(first, &program, 0).await;
+    schedule.add_event(second, &program, 0).await;
+    tokio::time::sleep(Duration::from_secs(7)).await;
+    assert_eq!(
+        logs.read().await.as_ref(),
+        vec!["1: Active", "2: Active", "2: Complete"]
+    );
+}
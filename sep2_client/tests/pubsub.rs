@@ -1,14 +1,11 @@
 #![cfg(feature = "messaging")]
 use sep2_client::{
     client::{Client, SEPResponse},
-    pubsub::{ClientNotifServer, RouteCallback},
+    pubsub::{ClientNotifServer, RouteCallback, ServerState},
+    tls::ClientIdentity,
 };
 use sep2_common::packages::{dcap::DeviceCapability, edev::EndDevice, pubsub::Notification};
-use std::{
-    convert::Infallible,
-    future::{self, Future},
-    time::Duration,
-};
+use std::{convert::Infallible, future, sync::Arc, time::Duration};
 
 fn test_setup() -> Client {
     Client::new_https(
@@ -27,11 +24,8 @@ fn test_setup() -> Client {
 struct DCAPHandler;
 
 impl RouteCallback<DeviceCapability> for DCAPHandler {
-    fn callback(
-        &self,
-        _: Notification<DeviceCapability>,
-    ) -> impl Future<Output = SEPResponse> + Send {
-        async move { SEPResponse::Created(None) }
+    async fn callback(&self, _: Notification<DeviceCapability>) -> SEPResponse {
+        SEPResponse::Created(None)
     }
 }
 
@@ -90,3 +84,384 @@ async fn basic_post() {
         SEPResponse::NotFound,
     ));
 }
+
+#[tokio::test]
+async fn shutdown_handle() {
+    let server = ClientNotifServer::new("127.0.0.1:1339")
+        .unwrap()
+        .add("/dcap", DCAPHandler);
+    let handle = server.shutdown_handle();
+    assert_eq!(handle.state(), ServerState::NotStarted);
+    let task = tokio::spawn(server.run(future::pending::<Infallible>()));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(handle.state(), ServerState::Running);
+    handle.clone().shutdown();
+    handle.stopped().await;
+    assert_eq!(handle.state(), ServerState::Stopped);
+    task.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn shared_identity() {
+    let identity = ClientIdentity::from_pem_files(
+        "../certs/server_cert.pem",
+        "../certs/server_private_key.pem",
+    )
+    .unwrap();
+    let server = ClientNotifServer::new("127.0.0.1:1340")
+        .unwrap()
+        .with_https_identity(&identity, "../certs/rootCA.pem")
+        .unwrap()
+        .add("/dcap", DCAPHandler);
+    let handle = server.shutdown_handle();
+    tokio::spawn(server.run(future::pending::<Infallible>()));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let client = Client::new_https_from_identity(
+        "https://127.0.0.1:1340",
+        &identity,
+        "../certs/rootCA.pem",
+        None,
+        None,
+    )
+    .unwrap();
+    let notif: Notification<DeviceCapability> = Default::default();
+    assert!(matches!(
+        client.post("/dcap", &notif).await.unwrap(),
+        SEPResponse::Created(None)
+    ));
+    handle.shutdown();
+}
+
+#[tokio::test]
+async fn bind_retry() {
+    let addr = "127.0.0.1:1341";
+    // Address is in use for longer than we retry for
+    let held = std::net::TcpListener::bind(addr).unwrap();
+    let server = ClientNotifServer::new(addr)
+        .unwrap()
+        .with_bind_retry(1, Duration::from_millis(10));
+    assert!(server.run(future::pending::<Infallible>()).await.is_err());
+    // Address becomes free during a retry
+    let server = ClientNotifServer::new(addr)
+        .unwrap()
+        .with_bind_retry(5, Duration::from_millis(100));
+    let handle = server.shutdown_handle();
+    let task = tokio::spawn(server.run(future::pending::<Infallible>()));
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    drop(held);
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(handle.state(), ServerState::Running);
+    handle.shutdown();
+    task.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn running_server() {
+    let server = ClientNotifServer::new("127.0.0.1:1342")
+        .unwrap()
+        .add("/dcap", DCAPHandler);
+    let running = server.spawn(future::pending::<Infallible>());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(running.state(), ServerState::Running);
+    assert_eq!(running.active_connections(), 0);
+    let conn = tokio::net::TcpStream::connect("127.0.0.1:1342")
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(running.active_connections(), 1);
+    drop(conn);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(running.active_connections(), 0);
+    assert!(!running.is_finished());
+    running.shutdown_handle().shutdown();
+    running.join().await.unwrap();
+}
+
+#[tokio::test]
+async fn teardown_hooks() {
+    let resource = Arc::new(());
+    let order = Arc::new(std::sync::Mutex::new(vec![]));
+    let server = ClientNotifServer::new("127.0.0.1:1344")
+        .unwrap()
+        .add("/dcap", {
+            let resource = resource.clone();
+            move |_: Notification<DeviceCapability>| {
+                let _ = &resource;
+                async { SEPResponse::NoContent }
+            }
+        })
+        .with_teardown({
+            let order = order.clone();
+            move || async move { order.lock().unwrap().push(1) }
+        })
+        .with_teardown({
+            let order = order.clone();
+            move || async move { order.lock().unwrap().push(2) }
+        });
+    let running = server.spawn(future::pending::<Infallible>());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(order.lock().unwrap().is_empty());
+    running.shutdown_handle().shutdown();
+    running.join().await.unwrap();
+    assert_eq!(*order.lock().unwrap(), [1, 2]);
+    // The route callback has been dropped
+    assert_eq!(Arc::strong_count(&resource), 1);
+}
+
+#[tokio::test]
+async fn graceful_drain() {
+    // A route that responds slowly, such that it's request is in-flight when shutdown begins
+    let slow = |delay: Duration| {
+        move |_: Notification<DeviceCapability>| async move {
+            tokio::time::sleep(delay).await;
+            SEPResponse::Created(None)
+        }
+    };
+    let post = |addr: &str| {
+        let req = hyper::Request::post(format!("http://{addr}/dcap"))
+            .body(hyper::Body::from(
+                sep2_common::serialize(&Notification::<DeviceCapability>::default()).unwrap(),
+            ))
+            .unwrap();
+        tokio::spawn(hyper::Client::new().request(req))
+    };
+
+    // The in-flight request is responded to
+    let server = ClientNotifServer::new("127.0.0.1:1347")
+        .unwrap()
+        .add("/dcap", slow(Duration::from_millis(500)));
+    let running = server.spawn(future::pending::<Infallible>());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let res = post("127.0.0.1:1347");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    running.shutdown_handle().shutdown();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(running.state(), ServerState::Draining);
+    let res = res.await.unwrap().unwrap();
+    assert_eq!(res.status(), hyper::StatusCode::CREATED);
+    running.join().await.unwrap();
+
+    // The in-flight request is aborted once the drain timeout elapses
+    let server = ClientNotifServer::new("127.0.0.1:1348")
+        .unwrap()
+        .with_drain_timeout(Duration::from_millis(200))
+        .add("/dcap", slow(Duration::from_secs(60)));
+    let handle = server.shutdown_handle();
+    let running = server.spawn(future::pending::<Infallible>());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let res = post("127.0.0.1:1348");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    handle.shutdown();
+    tokio::time::timeout(Duration::from_secs(2), running.join())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(handle.state(), ServerState::Stopped);
+    assert!(res.await.unwrap().is_err());
+}
+
+// Write a new self-signed certificate & private key to the temp directory
+#[cfg(feature = "test_util")]
+fn self_signed(name: &str) -> (std::path::PathBuf, std::path::PathBuf) {
+    use openssl::{
+        asn1::Asn1Time, bn::BigNum, ec::EcGroup, ec::EcKey, hash::MessageDigest, nid::Nid,
+        pkey::PKey, x509::X509NameBuilder, x509::X509,
+    };
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let pkey = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+    let mut subject = X509NameBuilder::new().unwrap();
+    subject.append_entry_by_text("CN", name).unwrap();
+    let subject = subject.build();
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    cert.set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap())
+        .unwrap();
+    cert.set_subject_name(&subject).unwrap();
+    cert.set_issuer_name(&subject).unwrap();
+    cert.set_pubkey(&pkey).unwrap();
+    cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    cert.sign(&pkey, MessageDigest::sha256()).unwrap();
+    let dir = std::env::temp_dir();
+    let cert_path = dir.join(format!("sep2_client_{name}_cert.pem"));
+    let pk_path = dir.join(format!("sep2_client_{name}_key.pem"));
+    std::fs::write(&cert_path, cert.build().to_pem().unwrap()).unwrap();
+    std::fs::write(&pk_path, pkey.private_key_to_pem_pkcs8().unwrap()).unwrap();
+    (cert_path, pk_path)
+}
+
+#[cfg(feature = "test_util")]
+#[tokio::test]
+async fn self_signed_pair() {
+    let (server_cert, server_key) = self_signed("test_server");
+    let (client_cert, client_key) = self_signed("test_client");
+    let server =
+        ClientNotifServer::new_test("127.0.0.1:1343", &server_cert, &server_key, &client_cert)
+            .unwrap()
+            .add("/dcap", DCAPHandler);
+    let running = server.spawn(future::pending::<Infallible>());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let client = Client::new_test(
+        "https://127.0.0.1:1343",
+        &client_cert,
+        &client_key,
+        &server_cert,
+    )
+    .unwrap();
+    let notif: Notification<DeviceCapability> = Default::default();
+    assert!(matches!(
+        client.post("/dcap", &notif).await.unwrap(),
+        SEPResponse::Created(None)
+    ));
+    // Mutual auth is still required
+    let (other_cert, other_key) = self_signed("test_other");
+    let other = Client::new_test(
+        "https://127.0.0.1:1343",
+        &other_cert,
+        &other_key,
+        &server_cert,
+    )
+    .unwrap();
+    assert!(other.post("/dcap", &notif).await.is_err());
+    running.shutdown_handle().shutdown();
+}
+
+#[tokio::test]
+async fn handshake_diagnostics() {
+    use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+    let failures = Arc::new(std::sync::Mutex::new(vec![]));
+    let server = ClientNotifServer::new("127.0.0.1:1345")
+        .unwrap()
+        .with_https(
+            "../certs/server_cert.pem",
+            "../certs/server_private_key.pem",
+            "../certs/rootCA.pem",
+        )
+        .unwrap()
+        .with_handshake_diagnostics({
+            let failures = failures.clone();
+            move |diagnostics| failures.lock().unwrap().push(diagnostics.clone())
+        });
+    let running = server.spawn(future::pending::<Infallible>());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // A peer that presents no certificate
+    let mut connector = SslConnector::builder(SslMethod::tls_client()).unwrap();
+    connector.set_verify(SslVerifyMode::NONE);
+    connector
+        .set_cipher_list("ECDHE-ECDSA-AES128-CCM8")
+        .unwrap();
+    connector
+        .set_max_proto_version(Some(openssl::ssl::SslVersion::TLS1_2))
+        .unwrap();
+    let ssl = connector
+        .build()
+        .configure()
+        .unwrap()
+        .into_ssl("localhost")
+        .unwrap();
+    let tcp = tokio::net::TcpStream::connect("127.0.0.1:1345")
+        .await
+        .unwrap();
+    let mut stream = tokio_openssl::SslStream::new(ssl, tcp).unwrap();
+    let _ = std::pin::Pin::new(&mut stream).connect().await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let failures = failures.lock().unwrap();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].offered_version, Some("TLSv1.2"));
+    assert!(failures[0]
+        .offered_ciphers
+        .iter()
+        .any(|c| c == "TLS_ECDHE_ECDSA_WITH_AES_128_CCM_8"));
+    assert_eq!(failures[0].peer_certificate, None);
+    running.shutdown_handle().shutdown();
+}
+
+#[tokio::test]
+async fn stalled_handshake() {
+    use tokio::io::AsyncReadExt;
+    let failures = Arc::new(std::sync::Mutex::new(vec![]));
+    let server = ClientNotifServer::new("127.0.0.1:1346")
+        .unwrap()
+        .with_https(
+            "../certs/server_cert.pem",
+            "../certs/server_private_key.pem",
+            "../certs/rootCA.pem",
+        )
+        .unwrap()
+        .with_handshake_timeout(Duration::from_millis(500))
+        .with_handshake_diagnostics({
+            let failures = failures.clone();
+            move |diagnostics| failures.lock().unwrap().push(diagnostics.clone())
+        })
+        .add("/dcap", DCAPHandler);
+    let running = server.spawn(future::pending::<Infallible>());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // A peer that connects, but never sends a ClientHello
+    let mut stalled = tokio::net::TcpStream::connect("127.0.0.1:1346")
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    // Does not delay other connections
+    let client = Client::new_https(
+        "https://127.0.0.1:1346",
+        "../certs/client_cert.pem",
+        "../certs/client_private_key.pem",
+        "../certs/rootCA.pem",
+        None,
+        None,
+    )
+    .unwrap();
+    let notif: Notification<DeviceCapability> = Default::default();
+    let res = tokio::time::timeout(Duration::from_millis(300), client.post("/dcap", &notif))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(res, SEPResponse::Created(None)));
+    assert!(failures.lock().unwrap().is_empty());
+
+    // Until the stalled handshake times out, and the connection is dropped
+    let mut buf = [0; 16];
+    let closed = tokio::time::timeout(Duration::from_secs(2), stalled.read(&mut buf)).await;
+    assert_eq!(closed.unwrap().unwrap(), 0);
+    let failures = failures.lock().unwrap();
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].error.contains("timed out"));
+    running.shutdown_handle().shutdown();
+}
+
+#[tokio::test]
+async fn handle_without_connection() {
+    use hyper::{Body, Request, StatusCode};
+    let server = ClientNotifServer::new("127.0.0.1:0")
+        .unwrap()
+        .add("/dcap", DCAPHandler);
+    let post = |path: &str, body: Body| Request::post(path).body(body).unwrap();
+    let notif = sep2_common::serialize(&Notification::<DeviceCapability>::default()).unwrap();
+
+    let res = server.handle(post("/dcap", notif.clone().into())).await;
+    assert_eq!(res.unwrap().status(), StatusCode::CREATED);
+    let res = server.handle(post("/edev", notif.into())).await;
+    assert_eq!(res.unwrap().status(), StatusCode::NOT_FOUND);
+    let res = server.handle(post("/dcap", "<Notification>".into())).await;
+    assert_eq!(res.unwrap().status(), StatusCode::BAD_REQUEST);
+    // EXI is not supported, but a malformed body is still a bad request
+    let exi = |body: Body| {
+        Request::post("/dcap")
+            .header("Content-Type", "application/sep-exi")
+            .body(body)
+            .unwrap()
+    };
+    let res = server.handle(exi(vec![0x80u8, 0x40].into())).await;
+    assert_eq!(res.unwrap().status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    let res = server.handle(exi(Body::empty())).await;
+    assert_eq!(res.unwrap().status(), StatusCode::BAD_REQUEST);
+    let res = server
+        .handle(Request::get("/dcap").body(Body::empty()).unwrap())
+        .await;
+    assert_eq!(res.unwrap().status(), StatusCode::METHOD_NOT_ALLOWED);
+}